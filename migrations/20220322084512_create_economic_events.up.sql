@@ -0,0 +1,18 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS economic_events
+(
+    id                        VARCHAR(26) PRIMARY KEY NOT NULL,
+    note                      TEXT,
+    action_id                 VARCHAR(26) NOT NULL REFERENCES actions(id),
+    provider_id               VARCHAR(26) NOT NULL REFERENCES agents(id),
+    receiver_id               VARCHAR(26) NOT NULL REFERENCES agents(id),
+    resource_quantity         INT,
+    unit_id                   VARCHAR(26) REFERENCES units(id),
+    resource_specification_id VARCHAR(26) REFERENCES resource_specifications(id),
+    input_of_id               VARCHAR(26) REFERENCES processes(id),
+    output_of_id              VARCHAR(26) REFERENCES processes(id),
+    has_point_in_time         INT,
+    has_beginning             INT,
+    has_end                   INT,
+    inserted_at               INT DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
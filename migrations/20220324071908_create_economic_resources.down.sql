@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE economic_events DROP COLUMN to_resource_inventoried_as_id;
+ALTER TABLE economic_events DROP COLUMN resource_inventoried_as_id;
+DROP TABLE IF EXISTS economic_resources;
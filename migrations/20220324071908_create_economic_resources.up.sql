@@ -0,0 +1,18 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS economic_resources
+(
+    id                     VARCHAR(26) PRIMARY KEY NOT NULL,
+    name                   TEXT NOT NULL,
+    note                   TEXT,
+    tracking_identifier    TEXT,
+    conforms_to_id         VARCHAR(26) NOT NULL REFERENCES resource_specifications(id),
+    primary_accountable_id VARCHAR(26) REFERENCES agents(id),
+    current_location       TEXT,
+    accounting_quantity    INT DEFAULT 0 NOT NULL,
+    onhand_quantity        INT DEFAULT 0 NOT NULL,
+    unit_id                VARCHAR(26) REFERENCES units(id),
+    inserted_at            INT DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+ALTER TABLE economic_events ADD COLUMN resource_inventoried_as_id VARCHAR(26) REFERENCES economic_resources(id);
+ALTER TABLE economic_events ADD COLUMN to_resource_inventoried_as_id VARCHAR(26) REFERENCES economic_resources(id);
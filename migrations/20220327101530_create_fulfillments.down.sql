@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE commitments DROP COLUMN finished;
+DROP TABLE IF EXISTS fulfillments;
@@ -0,0 +1,15 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS fulfillments
+(
+    id                VARCHAR(26) PRIMARY KEY NOT NULL,
+    fulfilled_by_id   VARCHAR(26) NOT NULL REFERENCES economic_events(id),
+    fulfills_id       VARCHAR(26) NOT NULL REFERENCES commitments(id),
+    resource_quantity INT,
+    effort_quantity   INT,
+    note              TEXT,
+    inserted_at       INT DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS unique_fulfillments_fulfilled_by_id_fulfills_id ON fulfillments (fulfilled_by_id, fulfills_id);
+
+ALTER TABLE commitments ADD COLUMN finished BOOLEAN DEFAULT FALSE NOT NULL;
@@ -0,0 +1,6 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS satisfactions;
+DROP TABLE IF EXISTS proposed_to;
+DROP TABLE IF EXISTS proposed_intents;
+DROP TABLE IF EXISTS proposals;
+DROP TABLE IF EXISTS intents;
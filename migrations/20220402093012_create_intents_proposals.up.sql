@@ -0,0 +1,62 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS intents
+(
+    id                        VARCHAR(26) PRIMARY KEY NOT NULL,
+    name                      TEXT,
+    note                      TEXT,
+    action_id                 VARCHAR(26) NOT NULL REFERENCES actions(id),
+    provider_id               VARCHAR(26) REFERENCES agents(id),
+    receiver_id               VARCHAR(26) REFERENCES agents(id),
+    resource_specification_id VARCHAR(26) REFERENCES resource_specifications(id),
+    resource_quantity         INT,
+    unit_id                   VARCHAR(26) REFERENCES units(id),
+    due_at                    INT,
+    finished                  BOOLEAN DEFAULT FALSE NOT NULL,
+    inserted_at               INT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    CHECK ((provider_id IS NULL) <> (receiver_id IS NULL))
+);
+
+CREATE TABLE IF NOT EXISTS proposals
+(
+    id            VARCHAR(26) PRIMARY KEY NOT NULL,
+    name          TEXT NOT NULL,
+    note          TEXT,
+    unit_based    BOOLEAN DEFAULT FALSE NOT NULL,
+    has_beginning INT,
+    has_end       INT,
+    inserted_at   INT DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS proposed_intents
+(
+    id          INTEGER PRIMARY KEY NOT NULL,
+    proposal_id VARCHAR(26) NOT NULL REFERENCES proposals(id),
+    intent_id   VARCHAR(26) NOT NULL REFERENCES intents(id),
+    reciprocal  BOOLEAN DEFAULT FALSE NOT NULL,
+    inserted_at INT DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS unique_proposed_intents_proposal_id_intent_id ON proposed_intents (proposal_id, intent_id);
+
+CREATE TABLE IF NOT EXISTS proposed_to
+(
+    id          INTEGER PRIMARY KEY NOT NULL,
+    proposal_id VARCHAR(26) NOT NULL REFERENCES proposals(id),
+    agent_id    VARCHAR(26) NOT NULL REFERENCES agents(id),
+    inserted_at INT DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS unique_proposed_to_proposal_id_agent_id ON proposed_to (proposal_id, agent_id);
+
+CREATE TABLE IF NOT EXISTS satisfactions
+(
+    id                VARCHAR(26) PRIMARY KEY NOT NULL,
+    satisfies_id      VARCHAR(26) NOT NULL REFERENCES intents(id),
+    satisfied_by_id   VARCHAR(26) NOT NULL REFERENCES commitments(id),
+    resource_quantity INT,
+    effort_quantity   INT,
+    note              TEXT,
+    inserted_at       INT DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS unique_satisfactions_satisfies_id_satisfied_by_id ON satisfactions (satisfies_id, satisfied_by_id);
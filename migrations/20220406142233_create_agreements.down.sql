@@ -0,0 +1,6 @@
+-- Add down migration script here
+ALTER TABLE economic_events DROP COLUMN realization_of_id;
+ALTER TABLE commitments DROP COLUMN clause_of_id;
+ALTER TABLE commitments DROP COLUMN receiver_id;
+ALTER TABLE commitments DROP COLUMN provider_id;
+DROP TABLE IF EXISTS agreements;
@@ -0,0 +1,13 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS agreements
+(
+    id          VARCHAR(26) PRIMARY KEY NOT NULL,
+    name        TEXT NOT NULL,
+    note        TEXT,
+    inserted_at INT DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+ALTER TABLE commitments ADD COLUMN provider_id VARCHAR(26) REFERENCES agents(id);
+ALTER TABLE commitments ADD COLUMN receiver_id VARCHAR(26) REFERENCES agents(id);
+ALTER TABLE commitments ADD COLUMN clause_of_id VARCHAR(26) REFERENCES agreements(id);
+ALTER TABLE economic_events ADD COLUMN realization_of_id VARCHAR(26) REFERENCES agreements(id);
@@ -0,0 +1,8 @@
+-- Add down migration script here
+ALTER TABLE processes DROP COLUMN based_on_id;
+DROP TABLE IF EXISTS recipe_flows;
+DROP TABLE IF EXISTS recipe_exchanges;
+DROP TABLE IF EXISTS recipe_processes;
+DROP TABLE IF EXISTS recipes;
+DROP TABLE IF EXISTS recipe_resources;
+DROP TABLE IF EXISTS process_specifications;
@@ -0,0 +1,63 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS process_specifications
+(
+    id          VARCHAR(26) PRIMARY KEY NOT NULL,
+    name        TEXT NOT NULL,
+    note        TEXT,
+    inserted_at INT DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS recipe_resources
+(
+    id                        VARCHAR(26) PRIMARY KEY NOT NULL,
+    name                      TEXT NOT NULL,
+    note                      TEXT,
+    resource_specification_id VARCHAR(26) REFERENCES resource_specifications(id),
+    unit_id                   VARCHAR(26) REFERENCES units(id),
+    inserted_at               INT DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS recipes
+(
+    id                VARCHAR(26) PRIMARY KEY NOT NULL,
+    name              TEXT NOT NULL,
+    note              TEXT,
+    primary_output_id VARCHAR(26) NOT NULL REFERENCES recipe_resources(id),
+    inserted_at       INT DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS recipe_processes
+(
+    id                       VARCHAR(26) PRIMARY KEY NOT NULL,
+    recipe_id                VARCHAR(26) NOT NULL REFERENCES recipes(id),
+    name                     TEXT NOT NULL,
+    note                     TEXT,
+    process_specification_id VARCHAR(26) REFERENCES process_specifications(id),
+    has_duration_seconds     INT,
+    inserted_at              INT DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS recipe_exchanges
+(
+    id          VARCHAR(26) PRIMARY KEY NOT NULL,
+    recipe_id   VARCHAR(26) NOT NULL REFERENCES recipes(id),
+    name        TEXT NOT NULL,
+    note        TEXT,
+    inserted_at INT DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS recipe_flows
+(
+    id                      VARCHAR(26) PRIMARY KEY NOT NULL,
+    note                    TEXT,
+    action_id               VARCHAR(26) NOT NULL REFERENCES actions(id),
+    recipe_flow_resource_id VARCHAR(26) NOT NULL REFERENCES recipe_resources(id),
+    resource_quantity       INT,
+    unit_id                 VARCHAR(26) REFERENCES units(id),
+    recipe_input_of_id      VARCHAR(26) REFERENCES recipe_processes(id),
+    recipe_output_of_id     VARCHAR(26) REFERENCES recipe_processes(id),
+    recipe_clause_of_id     VARCHAR(26) REFERENCES recipe_exchanges(id),
+    inserted_at             INT DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+ALTER TABLE processes ADD COLUMN based_on_id VARCHAR(26) REFERENCES process_specifications(id);
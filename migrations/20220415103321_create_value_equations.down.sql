@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS value_equation_weights;
+DROP TABLE IF EXISTS value_equations;
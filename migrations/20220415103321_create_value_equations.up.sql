@@ -0,0 +1,21 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS value_equations
+(
+    id               VARCHAR(26) PRIMARY KEY NOT NULL,
+    name             TEXT NOT NULL,
+    note             TEXT,
+    context_agent_id VARCHAR(26) REFERENCES agents(id),
+    inserted_at      INT DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS value_equation_weights
+(
+    id                        VARCHAR(26) PRIMARY KEY NOT NULL,
+    value_equation_id         VARCHAR(26) NOT NULL REFERENCES value_equations(id),
+    action_id                 VARCHAR(26) REFERENCES actions(id),
+    resource_specification_id VARCHAR(26) REFERENCES resource_specifications(id),
+    agent_relation_type_id    VARCHAR(26) REFERENCES agent_relation_types(id),
+    weight                    REAL NOT NULL,
+    inserted_at               INT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    CHECK ((action_id IS NOT NULL) + (resource_specification_id IS NOT NULL) + (agent_relation_type_id IS NOT NULL) = 1)
+);
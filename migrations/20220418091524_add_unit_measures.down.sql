@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE units DROP COLUMN conversion_factor;
+ALTER TABLE units DROP COLUMN dimension;
+ALTER TABLE units DROP COLUMN symbol;
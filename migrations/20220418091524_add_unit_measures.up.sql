@@ -0,0 +1,4 @@
+-- Add up migration script here
+ALTER TABLE units ADD COLUMN symbol TEXT;
+ALTER TABLE units ADD COLUMN dimension TEXT;
+ALTER TABLE units ADD COLUMN conversion_factor REAL;
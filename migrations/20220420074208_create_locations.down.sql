@@ -0,0 +1,8 @@
+-- Add down migration script here
+ALTER TABLE economic_events DROP COLUMN at_location_id;
+ALTER TABLE intents DROP COLUMN at_location_id;
+ALTER TABLE commitments DROP COLUMN at_location_id;
+ALTER TABLE processes DROP COLUMN at_location_id;
+ALTER TABLE agents DROP COLUMN primary_location_id;
+DROP INDEX IF EXISTS locations_lat_long;
+DROP TABLE IF EXISTS locations;
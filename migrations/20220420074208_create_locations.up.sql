@@ -0,0 +1,23 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS locations
+(
+    id               VARCHAR(26) PRIMARY KEY NOT NULL,
+    name             TEXT NOT NULL,
+    mappable_address TEXT,
+    lat              REAL,
+    long             REAL,
+    alt              REAL,
+    note             TEXT,
+    inserted_at      INT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    CHECK ((lat IS NULL) = (long IS NULL)),
+    CHECK (lat BETWEEN -90 AND 90),
+    CHECK (long BETWEEN -180 AND 180)
+);
+
+CREATE INDEX IF NOT EXISTS locations_lat_long ON locations(lat, long);
+
+ALTER TABLE agents ADD COLUMN primary_location_id VARCHAR(26) REFERENCES locations(id);
+ALTER TABLE processes ADD COLUMN at_location_id VARCHAR(26) REFERENCES locations(id);
+ALTER TABLE commitments ADD COLUMN at_location_id VARCHAR(26) REFERENCES locations(id);
+ALTER TABLE intents ADD COLUMN at_location_id VARCHAR(26) REFERENCES locations(id);
+ALTER TABLE economic_events ADD COLUMN at_location_id VARCHAR(26) REFERENCES locations(id);
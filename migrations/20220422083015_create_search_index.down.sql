@@ -0,0 +1,14 @@
+-- Add down migration script here
+DROP TRIGGER IF EXISTS commitments_search_delete;
+DROP TRIGGER IF EXISTS commitments_search_update;
+DROP TRIGGER IF EXISTS commitments_search_insert;
+DROP TRIGGER IF EXISTS processes_search_delete;
+DROP TRIGGER IF EXISTS processes_search_update;
+DROP TRIGGER IF EXISTS processes_search_insert;
+DROP TRIGGER IF EXISTS plans_search_delete;
+DROP TRIGGER IF EXISTS plans_search_update;
+DROP TRIGGER IF EXISTS plans_search_insert;
+DROP TRIGGER IF EXISTS agents_search_delete;
+DROP TRIGGER IF EXISTS agents_search_update;
+DROP TRIGGER IF EXISTS agents_search_insert;
+DROP TABLE IF EXISTS search_index;
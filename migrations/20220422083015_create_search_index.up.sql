@@ -0,0 +1,61 @@
+-- Add up migration script here
+CREATE VIRTUAL TABLE IF NOT EXISTS search_index USING fts5(
+    kind UNINDEXED,
+    id UNINDEXED,
+    title,
+    body,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO search_index (kind, id, title, body)
+SELECT 'agent', id, name, NULL FROM agents
+UNION ALL
+SELECT 'plan', id, title, description FROM plans
+UNION ALL
+SELECT 'process', id, title, description FROM processes
+UNION ALL
+SELECT 'commitment', id, NULL, description FROM commitments;
+
+CREATE TRIGGER IF NOT EXISTS agents_search_insert AFTER INSERT ON agents BEGIN
+    INSERT INTO search_index (kind, id, title) VALUES ('agent', new.id, new.name);
+END;
+CREATE TRIGGER IF NOT EXISTS agents_search_update AFTER UPDATE OF name ON agents BEGIN
+    UPDATE search_index SET title = new.name WHERE kind = 'agent' AND id = old.id;
+END;
+CREATE TRIGGER IF NOT EXISTS agents_search_delete AFTER DELETE ON agents BEGIN
+    DELETE FROM search_index WHERE kind = 'agent' AND id = old.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS plans_search_insert AFTER INSERT ON plans BEGIN
+    INSERT INTO search_index (kind, id, title, body)
+    VALUES ('plan', new.id, new.title, new.description);
+END;
+CREATE TRIGGER IF NOT EXISTS plans_search_update AFTER UPDATE OF title, description ON plans BEGIN
+    UPDATE search_index SET title = new.title, body = new.description
+    WHERE kind = 'plan' AND id = old.id;
+END;
+CREATE TRIGGER IF NOT EXISTS plans_search_delete AFTER DELETE ON plans BEGIN
+    DELETE FROM search_index WHERE kind = 'plan' AND id = old.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS processes_search_insert AFTER INSERT ON processes BEGIN
+    INSERT INTO search_index (kind, id, title, body)
+    VALUES ('process', new.id, new.title, new.description);
+END;
+CREATE TRIGGER IF NOT EXISTS processes_search_update AFTER UPDATE OF title, description ON processes BEGIN
+    UPDATE search_index SET title = new.title, body = new.description
+    WHERE kind = 'process' AND id = old.id;
+END;
+CREATE TRIGGER IF NOT EXISTS processes_search_delete AFTER DELETE ON processes BEGIN
+    DELETE FROM search_index WHERE kind = 'process' AND id = old.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS commitments_search_insert AFTER INSERT ON commitments BEGIN
+    INSERT INTO search_index (kind, id, body) VALUES ('commitment', new.id, new.description);
+END;
+CREATE TRIGGER IF NOT EXISTS commitments_search_update AFTER UPDATE OF description ON commitments BEGIN
+    UPDATE search_index SET body = new.description WHERE kind = 'commitment' AND id = old.id;
+END;
+CREATE TRIGGER IF NOT EXISTS commitments_search_delete AFTER DELETE ON commitments BEGIN
+    DELETE FROM search_index WHERE kind = 'commitment' AND id = old.id;
+END;
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS access_tokens_agent_id;
+DROP TABLE IF EXISTS access_tokens;
+ALTER TABLE agents DROP COLUMN password_hash;
@@ -0,0 +1,16 @@
+-- Add up migration script here
+ALTER TABLE agents ADD COLUMN password_hash TEXT;
+
+CREATE TABLE IF NOT EXISTS access_tokens
+(
+    id           VARCHAR(26) PRIMARY KEY NOT NULL,
+    agent_id     VARCHAR(26) NOT NULL REFERENCES agents(id) ON DELETE CASCADE,
+    kind         VARCHAR(100) NOT NULL,
+    name         TEXT,
+    token_hash   TEXT NOT NULL UNIQUE,
+    expires_at   INT,
+    last_used_at INT,
+    inserted_at  INT DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS access_tokens_agent_id ON access_tokens (agent_id);
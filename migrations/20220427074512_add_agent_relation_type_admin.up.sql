@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE agent_relation_types ADD COLUMN admin BOOLEAN NOT NULL DEFAULT FALSE;
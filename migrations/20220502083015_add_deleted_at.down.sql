@@ -0,0 +1,5 @@
+-- Add down migration script here
+ALTER TABLE commitments DROP COLUMN deleted_at;
+ALTER TABLE processes DROP COLUMN deleted_at;
+ALTER TABLE plans DROP COLUMN deleted_at;
+ALTER TABLE agents DROP COLUMN deleted_at;
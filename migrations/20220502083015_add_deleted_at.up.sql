@@ -0,0 +1,5 @@
+-- Add up migration script here
+ALTER TABLE agents ADD COLUMN deleted_at INT;
+ALTER TABLE plans ADD COLUMN deleted_at INT;
+ALTER TABLE processes ADD COLUMN deleted_at INT;
+ALTER TABLE commitments ADD COLUMN deleted_at INT;
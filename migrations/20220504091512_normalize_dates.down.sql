@@ -0,0 +1,2 @@
+-- Add down migration script here
+-- Normalized dates are read as they were before, so they are left as they are.
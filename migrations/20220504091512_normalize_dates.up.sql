@@ -0,0 +1,84 @@
+-- Add up migration script here
+-- Dates were stored as RFC 3339 text, as CURRENT_TIMESTAMP text or as seconds since the
+-- epoch. They are all rewritten as CURRENT_TIMESTAMP text, in UTC.
+UPDATE agents SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at),
+    deleted_at = COALESCE(CASE typeof(deleted_at) WHEN 'integer' THEN datetime(deleted_at, 'unixepoch') ELSE datetime(deleted_at) END, deleted_at);
+UPDATE plans SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at),
+    deleted_at = COALESCE(CASE typeof(deleted_at) WHEN 'integer' THEN datetime(deleted_at, 'unixepoch') ELSE datetime(deleted_at) END, deleted_at);
+UPDATE plan_agents SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE labels SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE processes SET
+    start_at = COALESCE(CASE typeof(start_at) WHEN 'integer' THEN datetime(start_at, 'unixepoch') ELSE datetime(start_at) END, start_at),
+    due_at = COALESCE(CASE typeof(due_at) WHEN 'integer' THEN datetime(due_at, 'unixepoch') ELSE datetime(due_at) END, due_at),
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at),
+    deleted_at = COALESCE(CASE typeof(deleted_at) WHEN 'integer' THEN datetime(deleted_at, 'unixepoch') ELSE datetime(deleted_at) END, deleted_at);
+UPDATE process_labels SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE process_agents SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE actions SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE units SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE resource_specifications SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE commitments SET
+    due_at = COALESCE(CASE typeof(due_at) WHEN 'integer' THEN datetime(due_at, 'unixepoch') ELSE datetime(due_at) END, due_at),
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at),
+    deleted_at = COALESCE(CASE typeof(deleted_at) WHEN 'integer' THEN datetime(deleted_at, 'unixepoch') ELSE datetime(deleted_at) END, deleted_at);
+UPDATE agent_relation_types SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE agent_relations SET
+    start_at = COALESCE(CASE typeof(start_at) WHEN 'integer' THEN datetime(start_at, 'unixepoch') ELSE datetime(start_at) END, start_at),
+    end_at = COALESCE(CASE typeof(end_at) WHEN 'integer' THEN datetime(end_at, 'unixepoch') ELSE datetime(end_at) END, end_at),
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE economic_events SET
+    has_point_in_time = COALESCE(CASE typeof(has_point_in_time) WHEN 'integer' THEN datetime(has_point_in_time, 'unixepoch') ELSE datetime(has_point_in_time) END, has_point_in_time),
+    has_beginning = COALESCE(CASE typeof(has_beginning) WHEN 'integer' THEN datetime(has_beginning, 'unixepoch') ELSE datetime(has_beginning) END, has_beginning),
+    has_end = COALESCE(CASE typeof(has_end) WHEN 'integer' THEN datetime(has_end, 'unixepoch') ELSE datetime(has_end) END, has_end),
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE economic_resources SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE fulfillments SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE intents SET
+    due_at = COALESCE(CASE typeof(due_at) WHEN 'integer' THEN datetime(due_at, 'unixepoch') ELSE datetime(due_at) END, due_at),
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE proposals SET
+    has_beginning = COALESCE(CASE typeof(has_beginning) WHEN 'integer' THEN datetime(has_beginning, 'unixepoch') ELSE datetime(has_beginning) END, has_beginning),
+    has_end = COALESCE(CASE typeof(has_end) WHEN 'integer' THEN datetime(has_end, 'unixepoch') ELSE datetime(has_end) END, has_end),
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE proposed_intents SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE proposed_to SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE satisfactions SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE agreements SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE process_specifications SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE recipe_resources SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE recipes SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE recipe_processes SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE recipe_exchanges SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE recipe_flows SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE value_equations SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE value_equation_weights SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE locations SET
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
+UPDATE access_tokens SET
+    expires_at = COALESCE(CASE typeof(expires_at) WHEN 'integer' THEN datetime(expires_at, 'unixepoch') ELSE datetime(expires_at) END, expires_at),
+    last_used_at = COALESCE(CASE typeof(last_used_at) WHEN 'integer' THEN datetime(last_used_at, 'unixepoch') ELSE datetime(last_used_at) END, last_used_at),
+    inserted_at = COALESCE(CASE typeof(inserted_at) WHEN 'integer' THEN datetime(inserted_at, 'unixepoch') ELSE datetime(inserted_at) END, inserted_at);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE intents DROP COLUMN exploded_into_plan_id;
+ALTER TABLE commitments DROP COLUMN exploded_into_plan_id;
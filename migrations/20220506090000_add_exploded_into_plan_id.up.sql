@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE commitments ADD COLUMN exploded_into_plan_id VARCHAR(26) REFERENCES plans(id);
+ALTER TABLE intents ADD COLUMN exploded_into_plan_id VARCHAR(26) REFERENCES plans(id);
@@ -0,0 +1,61 @@
+-- Add down migration script here
+
+ALTER TABLE units ADD COLUMN conversion_factor_number REAL;
+UPDATE units SET conversion_factor_number = conversion_factor WHERE conversion_factor IS NOT NULL;
+ALTER TABLE units DROP COLUMN conversion_factor;
+ALTER TABLE units RENAME COLUMN conversion_factor_number TO conversion_factor;
+
+ALTER TABLE value_equation_weights ADD COLUMN weight_number REAL DEFAULT 0 NOT NULL;
+UPDATE value_equation_weights SET weight_number = weight WHERE weight IS NOT NULL;
+ALTER TABLE value_equation_weights DROP COLUMN weight;
+ALTER TABLE value_equation_weights RENAME COLUMN weight_number TO weight;
+
+ALTER TABLE recipe_flows ADD COLUMN resource_quantity_number INT;
+UPDATE recipe_flows SET resource_quantity_number = resource_quantity WHERE resource_quantity IS NOT NULL;
+ALTER TABLE recipe_flows DROP COLUMN resource_quantity;
+ALTER TABLE recipe_flows RENAME COLUMN resource_quantity_number TO resource_quantity;
+
+ALTER TABLE satisfactions ADD COLUMN effort_quantity_number INT;
+UPDATE satisfactions SET effort_quantity_number = effort_quantity WHERE effort_quantity IS NOT NULL;
+ALTER TABLE satisfactions DROP COLUMN effort_quantity;
+ALTER TABLE satisfactions RENAME COLUMN effort_quantity_number TO effort_quantity;
+
+ALTER TABLE satisfactions ADD COLUMN resource_quantity_number INT;
+UPDATE satisfactions SET resource_quantity_number = resource_quantity WHERE resource_quantity IS NOT NULL;
+ALTER TABLE satisfactions DROP COLUMN resource_quantity;
+ALTER TABLE satisfactions RENAME COLUMN resource_quantity_number TO resource_quantity;
+
+ALTER TABLE intents ADD COLUMN resource_quantity_number INT;
+UPDATE intents SET resource_quantity_number = resource_quantity WHERE resource_quantity IS NOT NULL;
+ALTER TABLE intents DROP COLUMN resource_quantity;
+ALTER TABLE intents RENAME COLUMN resource_quantity_number TO resource_quantity;
+
+ALTER TABLE fulfillments ADD COLUMN effort_quantity_number INT;
+UPDATE fulfillments SET effort_quantity_number = effort_quantity WHERE effort_quantity IS NOT NULL;
+ALTER TABLE fulfillments DROP COLUMN effort_quantity;
+ALTER TABLE fulfillments RENAME COLUMN effort_quantity_number TO effort_quantity;
+
+ALTER TABLE fulfillments ADD COLUMN resource_quantity_number INT;
+UPDATE fulfillments SET resource_quantity_number = resource_quantity WHERE resource_quantity IS NOT NULL;
+ALTER TABLE fulfillments DROP COLUMN resource_quantity;
+ALTER TABLE fulfillments RENAME COLUMN resource_quantity_number TO resource_quantity;
+
+ALTER TABLE economic_resources ADD COLUMN onhand_quantity_number INT DEFAULT 0 NOT NULL;
+UPDATE economic_resources SET onhand_quantity_number = onhand_quantity WHERE onhand_quantity IS NOT NULL;
+ALTER TABLE economic_resources DROP COLUMN onhand_quantity;
+ALTER TABLE economic_resources RENAME COLUMN onhand_quantity_number TO onhand_quantity;
+
+ALTER TABLE economic_resources ADD COLUMN accounting_quantity_number INT DEFAULT 0 NOT NULL;
+UPDATE economic_resources SET accounting_quantity_number = accounting_quantity WHERE accounting_quantity IS NOT NULL;
+ALTER TABLE economic_resources DROP COLUMN accounting_quantity;
+ALTER TABLE economic_resources RENAME COLUMN accounting_quantity_number TO accounting_quantity;
+
+ALTER TABLE economic_events ADD COLUMN resource_quantity_number INT;
+UPDATE economic_events SET resource_quantity_number = resource_quantity WHERE resource_quantity IS NOT NULL;
+ALTER TABLE economic_events DROP COLUMN resource_quantity;
+ALTER TABLE economic_events RENAME COLUMN resource_quantity_number TO resource_quantity;
+
+ALTER TABLE commitments ADD COLUMN quantity_number INT;
+UPDATE commitments SET quantity_number = quantity WHERE quantity IS NOT NULL;
+ALTER TABLE commitments DROP COLUMN quantity;
+ALTER TABLE commitments RENAME COLUMN quantity_number TO quantity;
@@ -0,0 +1,62 @@
+-- Add up migration script here
+-- Decimals are kept as text, which SQLite doesn't round, in columns of TEXT affinity.
+
+ALTER TABLE commitments ADD COLUMN quantity_text TEXT;
+UPDATE commitments SET quantity_text = CAST(quantity AS TEXT) WHERE quantity IS NOT NULL;
+ALTER TABLE commitments DROP COLUMN quantity;
+ALTER TABLE commitments RENAME COLUMN quantity_text TO quantity;
+
+ALTER TABLE economic_events ADD COLUMN resource_quantity_text TEXT;
+UPDATE economic_events SET resource_quantity_text = CAST(resource_quantity AS TEXT) WHERE resource_quantity IS NOT NULL;
+ALTER TABLE economic_events DROP COLUMN resource_quantity;
+ALTER TABLE economic_events RENAME COLUMN resource_quantity_text TO resource_quantity;
+
+ALTER TABLE economic_resources ADD COLUMN accounting_quantity_text TEXT DEFAULT '0' NOT NULL;
+UPDATE economic_resources SET accounting_quantity_text = CAST(accounting_quantity AS TEXT) WHERE accounting_quantity IS NOT NULL;
+ALTER TABLE economic_resources DROP COLUMN accounting_quantity;
+ALTER TABLE economic_resources RENAME COLUMN accounting_quantity_text TO accounting_quantity;
+
+ALTER TABLE economic_resources ADD COLUMN onhand_quantity_text TEXT DEFAULT '0' NOT NULL;
+UPDATE economic_resources SET onhand_quantity_text = CAST(onhand_quantity AS TEXT) WHERE onhand_quantity IS NOT NULL;
+ALTER TABLE economic_resources DROP COLUMN onhand_quantity;
+ALTER TABLE economic_resources RENAME COLUMN onhand_quantity_text TO onhand_quantity;
+
+ALTER TABLE fulfillments ADD COLUMN resource_quantity_text TEXT;
+UPDATE fulfillments SET resource_quantity_text = CAST(resource_quantity AS TEXT) WHERE resource_quantity IS NOT NULL;
+ALTER TABLE fulfillments DROP COLUMN resource_quantity;
+ALTER TABLE fulfillments RENAME COLUMN resource_quantity_text TO resource_quantity;
+
+ALTER TABLE fulfillments ADD COLUMN effort_quantity_text TEXT;
+UPDATE fulfillments SET effort_quantity_text = CAST(effort_quantity AS TEXT) WHERE effort_quantity IS NOT NULL;
+ALTER TABLE fulfillments DROP COLUMN effort_quantity;
+ALTER TABLE fulfillments RENAME COLUMN effort_quantity_text TO effort_quantity;
+
+ALTER TABLE intents ADD COLUMN resource_quantity_text TEXT;
+UPDATE intents SET resource_quantity_text = CAST(resource_quantity AS TEXT) WHERE resource_quantity IS NOT NULL;
+ALTER TABLE intents DROP COLUMN resource_quantity;
+ALTER TABLE intents RENAME COLUMN resource_quantity_text TO resource_quantity;
+
+ALTER TABLE satisfactions ADD COLUMN resource_quantity_text TEXT;
+UPDATE satisfactions SET resource_quantity_text = CAST(resource_quantity AS TEXT) WHERE resource_quantity IS NOT NULL;
+ALTER TABLE satisfactions DROP COLUMN resource_quantity;
+ALTER TABLE satisfactions RENAME COLUMN resource_quantity_text TO resource_quantity;
+
+ALTER TABLE satisfactions ADD COLUMN effort_quantity_text TEXT;
+UPDATE satisfactions SET effort_quantity_text = CAST(effort_quantity AS TEXT) WHERE effort_quantity IS NOT NULL;
+ALTER TABLE satisfactions DROP COLUMN effort_quantity;
+ALTER TABLE satisfactions RENAME COLUMN effort_quantity_text TO effort_quantity;
+
+ALTER TABLE recipe_flows ADD COLUMN resource_quantity_text TEXT;
+UPDATE recipe_flows SET resource_quantity_text = CAST(resource_quantity AS TEXT) WHERE resource_quantity IS NOT NULL;
+ALTER TABLE recipe_flows DROP COLUMN resource_quantity;
+ALTER TABLE recipe_flows RENAME COLUMN resource_quantity_text TO resource_quantity;
+
+ALTER TABLE value_equation_weights ADD COLUMN weight_text TEXT DEFAULT '0' NOT NULL;
+UPDATE value_equation_weights SET weight_text = CAST(weight AS TEXT) WHERE weight IS NOT NULL;
+ALTER TABLE value_equation_weights DROP COLUMN weight;
+ALTER TABLE value_equation_weights RENAME COLUMN weight_text TO weight;
+
+ALTER TABLE units ADD COLUMN conversion_factor_text TEXT;
+UPDATE units SET conversion_factor_text = CAST(conversion_factor AS TEXT) WHERE conversion_factor IS NOT NULL;
+ALTER TABLE units DROP COLUMN conversion_factor;
+ALTER TABLE units RENAME COLUMN conversion_factor_text TO conversion_factor;
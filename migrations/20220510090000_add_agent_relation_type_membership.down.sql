@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE agent_relation_types DROP COLUMN membership;
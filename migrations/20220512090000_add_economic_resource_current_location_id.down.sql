@@ -0,0 +1,6 @@
+-- Add down migration script here
+ALTER TABLE economic_resources ADD COLUMN current_location TEXT;
+UPDATE economic_resources
+SET current_location = (SELECT name FROM locations WHERE locations.id = economic_resources.current_location_id)
+WHERE current_location_id IS NOT NULL;
+ALTER TABLE economic_resources DROP COLUMN current_location_id;
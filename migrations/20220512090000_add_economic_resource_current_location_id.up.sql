@@ -0,0 +1,15 @@
+-- Add up migration script here
+-- The current location of a resource becomes a location. Free text naming a location
+-- is linked to it, other text is kept in the note of the resource.
+
+ALTER TABLE economic_resources ADD COLUMN current_location_id VARCHAR(26) REFERENCES locations(id);
+UPDATE economic_resources
+SET current_location_id = (
+    SELECT id FROM locations WHERE locations.name = economic_resources.current_location
+    ORDER BY inserted_at, id LIMIT 1
+)
+WHERE current_location IS NOT NULL;
+UPDATE economic_resources
+SET note = COALESCE(note || char(10), '') || 'Location: ' || current_location
+WHERE current_location IS NOT NULL AND current_location_id IS NULL;
+ALTER TABLE economic_resources DROP COLUMN current_location;
@@ -47,6 +47,16 @@ in
               local port on which to run the server
             '';
           };
+          environmentFile = mkOption {
+            type = types.nullOr types.str;
+            default = null;
+            example = "/run/secrets/vf-backend";
+            description = ''
+              file setting BOOTSTRAP_EMAIL and BOOTSTRAP_PASSWORD, to give the agent with
+              this email its first password, kept out of the nix store; the agent is created,
+              named BOOTSTRAP_NAME if set, when there is none with this email yet
+            '';
+          };
         };
       });
     };
@@ -93,6 +103,8 @@ in
             ProtectSystem = "full";
             NoNewPrivileges = true;
             ReadWritePaths = "${serviceConfig.stateDir}";
+          } // lib.optionalAttrs (instanceConfig.environmentFile != null) {
+            EnvironmentFile = instanceConfig.environmentFile;
           };
           environment = {
             DATABASE_URL = "sqlite:${serviceConfig.stateDir}/${instanceConfig.dbName}.db";
@@ -5,8 +5,12 @@ INSERT INTO actions (id, name, input_output) VALUES ('01FBXZHHA2YHTAP902Y6FKZ885
 INSERT INTO actions (id, name, input_output) VALUES ('01FBXZHY6SD354TSP6G41W0XWY', 'accept', 'Input');
 INSERT INTO actions (id, name, input_output) VALUES ('01FBXZJ9JDCZ6YXJY6BVR7DAWT', 'modify', 'Output');
 INSERT INTO actions (id, name, input_output) VALUES ('01FBZMRP9708ZKQ64RV3A09CSD', 'use', 'Input');
-INSERT INTO units (id, label) VALUES ('01FBXZS196WG2YSN1YKYDSG0W8', 'hour');
-INSERT INTO units (id, label) VALUES ('01FBXZSF3CVKKA8JBAWD7VDG7G', 'each');
+INSERT INTO actions (id, name, input_output) VALUES ('01FBZN2Q0V3T8M9K4XRJ6W5HYD', 'transfer', NULL);
+INSERT INTO units (id, label, symbol, dimension, conversion_factor) VALUES ('01FBXZS196WG2YSN1YKYDSG0W8', 'hour', 'h', 'time', 3600);
+INSERT INTO units (id, label, symbol, dimension, conversion_factor) VALUES ('01FBXZSF3CVKKA8JBAWD7VDG7G', 'each', 'ea', 'count', 1);
+INSERT INTO units (id, label, symbol, dimension, conversion_factor) VALUES ('01G0V4F2N8Q6C9XDM2W7H3KZ1A', 'minute', 'min', 'time', 60);
+INSERT INTO units (id, label, symbol, dimension, conversion_factor) VALUES ('01G0V4F8T4J1R5YBN6E3P9SV2B', 'gram', 'g', 'mass', 0.001);
+INSERT INTO units (id, label, symbol, dimension, conversion_factor) VALUES ('01G0V4FE1M7W2K8ZQ4T6G5HX3C', 'kilogram', 'kg', 'mass', 1);
 INSERT INTO labels (id, name, unique_name, color) VALUES ('01FMDPZGNMEQ2APXMDTXVDJ1S8', 'livelihood', 'livelihood', 'green');
 INSERT INTO labels (id, name, unique_name, color) VALUES ('01FMDPZGNMX93K6WG0R4VQQ1BY', 'love', 'love', 'pink');
 INSERT INTO labels (id, name, unique_name, color) VALUES ('01FMDPZGNMMTA1N6X3FMD8K729', 'care', 'care', 'red');
@@ -25,10 +29,10 @@ INSERT INTO agents (id, unique_name, name, email, agent_type) VALUES ('01FM8AV9G
 INSERT INTO agents (id, unique_name, name, email, agent_type) VALUES ('01FM8AWJJ71DNYKTZ8MBF90XG1', 'disco_sto_doc_circle', 'STO.DOC Circle', 'sto-doc-circle@disco.coop', 'Organization');
 INSERT INTO agents (id, unique_name, name, email, agent_type) VALUES ('01FM8AYBS82K63N9QERXFZMHTT', 'disco_research_circle', 'RESEARCH Circle', 'research-circle@disco.coop', 'Organization');
 INSERT INTO agents (id, unique_name, name, email, agent_type) VALUES ('01FM8B05GFDCPAWGPR0Y7DCKTX', 'disco_community_circle', 'COMMUNITY Circle', 'community-circle@disco.coop', 'Organization');
-INSERT INTO agent_relation_types (id, name) VALUES ('01FMCQ5D5KCRD5H02VZCZR2SFT', 'Member of');
-INSERT INTO agent_relation_types (id, name) VALUES ('01FMB7SJ3K9CTCJG3NRQWXV37D', 'Commited member of');
-INSERT INTO agent_relation_types (id, name) VALUES ('01FMCPZ190W5XB4F5A3Q9GFTSF', 'Dating member of');
-INSERT INTO agent_relation_types (id, name) VALUES ('01FMCPZBZB76J486AQYSJD2RDN', 'Steward of');
+INSERT INTO agent_relation_types (id, name, membership) VALUES ('01FMCQ5D5KCRD5H02VZCZR2SFT', 'Member of', TRUE);
+INSERT INTO agent_relation_types (id, name, membership) VALUES ('01FMB7SJ3K9CTCJG3NRQWXV37D', 'Commited member of', TRUE);
+INSERT INTO agent_relation_types (id, name, membership) VALUES ('01FMCPZ190W5XB4F5A3Q9GFTSF', 'Dating member of', TRUE);
+INSERT INTO agent_relation_types (id, name, admin, membership) VALUES ('01FMCPZBZB76J486AQYSJD2RDN', 'Steward of', TRUE, TRUE);
 INSERT INTO agent_relation_types (id, name) VALUES ('01FMCQ3GTVY58JE1XAVEQBSQPM', 'Part of');
 /* disco mothership relations */
 INSERT INTO agent_relations (id, subject_id, object_id, agent_relation_type_id) VALUES ('01FMAD6R3FSDQ2GB5HZDESR21Q', '01FM8A9QQBV77N1B1YP7R89RR0', '01FM8AH141E8512ZMD23D91NMG', '01FMB7SJ3K9CTCJG3NRQWXV37D');
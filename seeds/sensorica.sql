@@ -5,8 +5,12 @@ INSERT INTO actions (id, name, input_output) VALUES ('01FBXZHHA2YHTAP902Y6FKZ885
 INSERT INTO actions (id, name, input_output) VALUES ('01FBXZHY6SD354TSP6G41W0XWY', 'accept', 'Input');
 INSERT INTO actions (id, name, input_output) VALUES ('01FBXZJ9JDCZ6YXJY6BVR7DAWT', 'modify', 'Output');
 INSERT INTO actions (id, name, input_output) VALUES ('01FBZMRP9708ZKQ64RV3A09CSD', 'use', 'Input');
-INSERT INTO units (id, label) VALUES ('01FBXZS196WG2YSN1YKYDSG0W8', 'hour');
-INSERT INTO units (id, label) VALUES ('01FBXZSF3CVKKA8JBAWD7VDG7G', 'each');
+INSERT INTO actions (id, name, input_output) VALUES ('01FBZN2Q0V3T8M9K4XRJ6W5HYD', 'transfer', NULL);
+INSERT INTO units (id, label, symbol, dimension, conversion_factor) VALUES ('01FBXZS196WG2YSN1YKYDSG0W8', 'hour', 'h', 'time', 3600);
+INSERT INTO units (id, label, symbol, dimension, conversion_factor) VALUES ('01FBXZSF3CVKKA8JBAWD7VDG7G', 'each', 'ea', 'count', 1);
+INSERT INTO units (id, label, symbol, dimension, conversion_factor) VALUES ('01G0V4F2N8Q6C9XDM2W7H3KZ1A', 'minute', 'min', 'time', 60);
+INSERT INTO units (id, label, symbol, dimension, conversion_factor) VALUES ('01G0V4F8T4J1R5YBN6E3P9SV2B', 'gram', 'g', 'mass', 0.001);
+INSERT INTO units (id, label, symbol, dimension, conversion_factor) VALUES ('01G0V4FE1M7W2K8ZQ4T6G5HX3C', 'kilogram', 'kg', 'mass', 1);
 INSERT INTO agents (id, unique_name, name, email, agent_type) VALUES ('01FMSZT0Y1A4VQS6839Y12GGYT', 'tibuerius_brastaviceanu', 'Tiberius Brastaviceanu', 'tiberius.brastaviceanu@gmail.com', 'Individual');
 INSERT INTO agents (id, unique_name, name, email, agent_type) VALUES ('01FMSZT99637VPN57REMAZWTTE', 'alex_dicu', 'Alex Dicu', 'alex8dicu@gmail.com', 'Individual');
 INSERT INTO agents (id, unique_name, name, email, agent_type) VALUES ('01FMT8S0WZ5HHS1BFTYFPGBNFR', 'dounia_saeme', 'Dounia Saeme', 'dsaeme@gmail.com', 'Individual');
@@ -24,8 +28,8 @@ INSERT INTO agents (id, unique_name, name, email, agent_type) VALUES ('01FMT07SP
 INSERT INTO agents (id, unique_name, name, email, agent_type) VALUES ('01FMT0GQ6TQ2EEWKH1A3Y33WNC', 'greens_for_good', 'Greens for good', 'greens.for.good@sensorica.co', 'Project');
 INSERT INTO agents (id, unique_name, name, email, agent_type) VALUES ('01FMT8VXWAWQ2PXWB2K908W2RR', 'rope_maker', 'Rope maker', 'rope.maker@sensorica.co', 'Project');
 INSERT INTO agents (id, unique_name, name, email, agent_type) VALUES ('01FMT9SXZZKAT6SV39XEED9Z96', 'joshua_m_pearce_michigan_tech_university', 'Joshua M. Pearce - Michigan Tech University', '', 'Organization');
-INSERT INTO agent_relation_types (id, name) VALUES ('01FMT02BYT2TSXH3CPG5EWHSWW', 'Affiliate');
-INSERT INTO agent_relation_types (id, name) VALUES ('01FMT0AW37CPHYBJ184YWDTNRX', 'Representative');
+INSERT INTO agent_relation_types (id, name, membership) VALUES ('01FMT02BYT2TSXH3CPG5EWHSWW', 'Affiliate', TRUE);
+INSERT INTO agent_relation_types (id, name, admin, membership) VALUES ('01FMT0AW37CPHYBJ184YWDTNRX', 'Representative', TRUE, TRUE);
 INSERT INTO agent_relation_types (id, name) VALUES ('01FMT02J76M7FB4RCX3QSRN1EX', 'Part of');
 /* sensorica relations */
 INSERT INTO agent_relations (id, subject_id, object_id, agent_relation_type_id) VALUES ('01FMT096N710E7Z8Q695GGAFFK', '01FMSZT0Y1A4VQS6839Y12GGYT', '01FMT07SPZZA3D1XXGPNPXV28M', '01FMT02BYT2TSXH3CPG5EWHSWW');
@@ -1,10 +1,12 @@
-use async_graphql::http::{playground_source, GraphQLPlaygroundConfig};
-use async_graphql::{EmptySubscription, Schema};
-use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use async_graphql::dataloader::DataLoader;
+use async_graphql::http::{playground_source, GraphQLPlaygroundConfig, ALL_WEBSOCKET_PROTOCOLS};
+use async_graphql::{Data, Schema};
+use async_graphql_axum::{GraphQLProtocol, GraphQLRequest, GraphQLResponse, GraphQLWebSocket};
 use axum::{
-    extract::Extension,
+    async_trait,
+    extract::{ws::WebSocketUpgrade, Extension, FromRequest, RequestParts},
     handler::Handler,
-    http::{Method, StatusCode},
+    http::{header, Method, StatusCode},
     response::{Html, IntoResponse},
     routing::get,
     Router,
@@ -14,20 +16,106 @@ use std::net::SocketAddr;
 use tower_http::cors::{Any, CorsLayer};
 
 mod schema;
-use crate::schema::{MutationRoot, QueryRoot, VfSchema};
+use crate::schema::{
+    authenticate, bootstrap_password, AuthenticationError, ChangeBus, CurrentAgent, MutationRoot,
+    QueryRoot, SqliteLoader, SubscriptionRoot, VfSchema,
+};
 
 const GRAPHQL_URL: &str = "/graphql";
+const SUBSCRIPTION_URL: &str = "/ws";
+
+/// The agent a request is made on behalf of, from the bearer token in its `Authorization`
+/// header. Requests without the header are anonymous, those with a token that is not valid
+/// are refused.
+struct Authentication(Option<CurrentAgent>);
+
+#[async_trait]
+impl<B: Send> FromRequest<B> for Authentication {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let Extension(pool) = Extension::<SqlitePool>::from_request(req)
+            .await
+            .expect("failed to get connection pool");
+        let authorization = match req
+            .headers()
+            .and_then(|headers| headers.get(header::AUTHORIZATION))
+        {
+            Some(authorization) => authorization,
+            None => return Ok(Authentication(None)),
+        };
+        let authorization = authorization.to_str().map_err(|_| {
+            (
+                StatusCode::UNAUTHORIZED,
+                AuthenticationError::Malformed.to_string(),
+            )
+        })?;
+        match authenticate(&pool, authorization).await {
+            Ok(current_agent) => Ok(Authentication(Some(current_agent))),
+            Err(AuthenticationError::Database(error)) => {
+                Err((StatusCode::INTERNAL_SERVER_ERROR, error.to_string()))
+            }
+            Err(error) => Err((StatusCode::UNAUTHORIZED, error.to_string())),
+        }
+    }
+}
+
+async fn graphql_handler(
+    schema: Extension<VfSchema>,
+    Authentication(current_agent): Authentication,
+    req: GraphQLRequest,
+) -> GraphQLResponse {
+    let mut request = req.into_inner();
+    if let Some(current_agent) = current_agent {
+        request = request.data(current_agent);
+    }
+    schema.execute(request).await.into()
+}
 
-async fn graphql_handler(schema: Extension<VfSchema>, req: GraphQLRequest) -> GraphQLResponse {
-    schema.execute(req.into_inner()).await.into()
+/// Serves subscriptions. Clients authenticate with an `Authorization` field holding a bearer
+/// token in the payload of the message that initializes the connection.
+async fn graphql_subscription(
+    Extension(schema): Extension<VfSchema>,
+    Extension(pool): Extension<SqlitePool>,
+    protocol: GraphQLProtocol,
+    upgrade: WebSocketUpgrade,
+) -> impl IntoResponse {
+    upgrade
+        .protocols(ALL_WEBSOCKET_PROTOCOLS)
+        .on_upgrade(move |stream| {
+            GraphQLWebSocket::new(stream, schema, protocol)
+                .on_connection_init(move |payload| {
+                    let pool = pool.clone();
+                    async move {
+                        let mut data = Data::default();
+                        let authorization = payload
+                            .get("Authorization")
+                            .or_else(|| payload.get("authorization"))
+                            .and_then(|authorization| authorization.as_str());
+                        if let Some(authorization) = authorization {
+                            data.insert(authenticate(&pool, authorization).await?);
+                        }
+                        Ok(data)
+                    }
+                })
+                .serve()
+        })
 }
 
 async fn graphql_playground() -> impl IntoResponse {
     Html(playground_source(
-        GraphQLPlaygroundConfig::new(GRAPHQL_URL).subscription_endpoint("/ws"),
+        GraphQLPlaygroundConfig::new(GRAPHQL_URL).subscription_endpoint(SUBSCRIPTION_URL),
     ))
 }
 
+fn schema(db: SqlitePool) -> VfSchema {
+    Schema::build(QueryRoot, MutationRoot, SubscriptionRoot)
+        .data(DataLoader::new(SqliteLoader::new(db.clone()), tokio::spawn))
+        .data(ChangeBus::new())
+        .data(db)
+        .finish()
+}
+
 #[tokio::main]
 async fn main() {
     // initialize tracing
@@ -37,20 +125,33 @@ async fn main() {
         .await
         .expect("failed to get a db connection");
 
-    let schema = Schema::build(QueryRoot, MutationRoot, EmptySubscription)
-        .data(db)
-        .finish();
+    // the first agent gets its password from the configuration, and sets those of the others
+    if let Ok(email) = std::env::var("BOOTSTRAP_EMAIL") {
+        let password = std::env::var("BOOTSTRAP_PASSWORD").expect("BOOTSTRAP_PASSWORD is not set");
+        let name = std::env::var("BOOTSTRAP_NAME").ok();
+        match bootstrap_password(&db, &email, name.as_deref(), &password).await {
+            Ok(true) => tracing::info!("set the password of {}", email),
+            Ok(false) => {}
+            Err(error) => tracing::error!("failed to bootstrap {}: {}", email, error.message),
+        }
+    }
+
+    let schema = schema(db.clone());
 
     let cors = CorsLayer::new()
         // allow `GET` and `POST` when accessing the resource
         .allow_methods(vec![Method::GET, Method::POST])
+        // allow the json body and the token of the agent
+        .allow_headers(vec![header::CONTENT_TYPE, header::AUTHORIZATION])
         // allow requests from any origin
         .allow_origin(Any);
     // build our application with a route
     let app = Router::new()
         .route(GRAPHQL_URL, get(graphql_playground).post(graphql_handler))
+        .route(SUBSCRIPTION_URL, get(graphql_subscription))
         .layer(cors)
         .layer(Extension(schema))
+        .layer(Extension(db))
         .fallback(not_found.into_service());
 
     let port = std::env::var("HTTP_PORT")
@@ -71,20 +172,20 @@ async fn not_found() -> impl IntoResponse {
     (StatusCode::NOT_FOUND, "nothing to see here")
 }
 
-#[cfg(all(test, feature = "with-db"))]
+#[cfg(test)]
 mod tests {
-    use super::application;
-
-    #[test]
-    fn graphql_agents() {
-        let mut application = application();
-        init(&mut application);
-        assert_response!(
-            post("/graphql")
-                .with_request_body(r#"{"query": "{ agents {id, name, email}}"}"#)
-                .on(&application),
-            Status::Ok,
-            r#"{"data":{"agents":[]}}"#,
+    use super::schema;
+    use crate::schema::test_pool;
+
+    #[tokio::test]
+    async fn graphql_agents() {
+        let response = schema(test_pool("").await)
+            .execute("{ agents { edges { node { id name email } } } }")
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(
+            response.data.into_json().expect("invalid response data"),
+            serde_json::json!({ "agents": { "edges": [] } }),
         );
     }
 }
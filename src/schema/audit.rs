@@ -0,0 +1,144 @@
+use super::auth::CurrentAgent;
+use super::AuditEntity;
+use async_graphql::Context;
+use sqlx::sqlite::SqliteConnection;
+
+/// Columns that only go into the log as whether they are set.
+const SECRET_COLUMNS: [&str; 2] = ["password_hash", "token_hash"];
+
+impl AuditEntity {
+    pub(super) fn table(self) -> &'static str {
+        match self {
+            AuditEntity::Agent => "agents",
+            AuditEntity::Label => "labels",
+            AuditEntity::Location => "locations",
+            AuditEntity::Unit => "units",
+            AuditEntity::Plan => "plans",
+            AuditEntity::Process => "processes",
+            AuditEntity::ResourceSpecification => "resource_specifications",
+            AuditEntity::Commitment => "commitments",
+            AuditEntity::EconomicResource => "economic_resources",
+            AuditEntity::EconomicEvent => "economic_events",
+            AuditEntity::Fulfillment => "fulfillments",
+            AuditEntity::Intent => "intents",
+            AuditEntity::Proposal => "proposals",
+            AuditEntity::Satisfaction => "satisfactions",
+            AuditEntity::Agreement => "agreements",
+            AuditEntity::ProcessSpecification => "process_specifications",
+            AuditEntity::RecipeResource => "recipe_resources",
+            AuditEntity::Recipe => "recipes",
+            AuditEntity::RecipeProcess => "recipe_processes",
+            AuditEntity::RecipeExchange => "recipe_exchanges",
+            AuditEntity::RecipeFlow => "recipe_flows",
+            AuditEntity::ValueEquation => "value_equations",
+            AuditEntity::AgentRelation => "agent_relations",
+            AuditEntity::AccessToken => "access_tokens",
+        }
+    }
+
+    /// Fields holding the records linked to a record through join tables, by the queries
+    /// listing their ids.
+    fn links(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            AuditEntity::Plan => &[(
+                "agent_ids",
+                "SELECT json_group_array(agent_id) FROM plan_agents WHERE plan_id = plans.id",
+            )],
+            AuditEntity::Process => &[
+                (
+                    "label_ids",
+                    "SELECT json_group_array(label_id) FROM process_labels WHERE process_id = processes.id",
+                ),
+                (
+                    "agent_ids",
+                    "SELECT json_group_array(agent_id) FROM process_agents WHERE process_id = processes.id",
+                ),
+            ],
+            _ => &[],
+        }
+    }
+}
+
+/// The record as a JSON object of its columns, if it exists.
+pub(super) async fn snapshot(
+    connection: &mut SqliteConnection,
+    entity: AuditEntity,
+    id: &str,
+) -> sqlx::Result<Option<String>> {
+    let columns: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info(?)")
+        .bind(entity.table())
+        .fetch_all(&mut *connection)
+        .await?;
+    let fields = columns
+        .iter()
+        .map(|column| {
+            if SECRET_COLUMNS.contains(&column.as_str()) {
+                format!("'{0}', json(iif(\"{0}\" IS NULL, 'false', 'true'))", column)
+            } else {
+                format!("'{0}', \"{0}\"", column)
+            }
+        })
+        .chain(
+            entity
+                .links()
+                .iter()
+                .map(|(field, query)| format!("'{}', json(({}))", field, query)),
+        )
+        .collect::<Vec<_>>()
+        .join(", ");
+    let sql = format!(
+        "SELECT json_object({}) FROM {} WHERE id = ?",
+        fields,
+        entity.table()
+    );
+    sqlx::query_scalar(&sql)
+        .bind(id)
+        .fetch_optional(&mut *connection)
+        .await
+}
+
+/// Appends the change the current mutation made to a record to the audit log: the record as
+/// it was `before`, taken with `snapshot`, and as it is now. Records the mutation left as they
+/// were are left out.
+pub(super) async fn record(
+    connection: &mut SqliteConnection,
+    context: &Context<'_>,
+    entity: AuditEntity,
+    id: &str,
+    before: Option<String>,
+) -> sqlx::Result<()> {
+    let agent_id = context
+        .data_opt::<CurrentAgent>()
+        .map(|current_agent| current_agent.agent_id.as_str());
+    record_by(connection, context, agent_id, entity, id, before).await
+}
+
+/// Like `record`, for mutations made by an agent that is not logged in yet.
+pub(super) async fn record_by(
+    connection: &mut SqliteConnection,
+    context: &Context<'_>,
+    agent_id: Option<&str>,
+    entity: AuditEntity,
+    id: &str,
+    before: Option<String>,
+) -> sqlx::Result<()> {
+    let after = snapshot(connection, entity, id).await?;
+    if before == after {
+        return Ok(());
+    }
+    sqlx::query(
+        "
+        INSERT INTO audit_log (agent_id, mutation, entity_type, entity_id, before, after)
+        VALUES (?, ?, ?, ?, ?, ?)
+        ",
+    )
+    .bind(agent_id)
+    .bind(context.field().name())
+    .bind(entity)
+    .bind(id)
+    .bind(before)
+    .bind(after)
+    .execute(&mut *connection)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,221 @@
+use super::error::DomainError;
+use super::mutation::unique_name;
+use super::time::DateTime;
+use super::{AgentType, ApiToken};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use async_graphql::async_trait::async_trait;
+use async_graphql::{Context, Error, Guard, Result};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::{SqliteConnection, SqlitePool};
+use sqlx::Row;
+use std::fmt;
+use ulid::Ulid;
+
+/// How long a login lasts.
+const SESSION_DAYS: i64 = 30;
+
+/// Shortest password accepted.
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// The agent a request is made on behalf of, and the token it was made with.
+#[derive(Clone, Debug)]
+pub struct CurrentAgent {
+    pub(super) agent_id: String,
+    pub(super) token_id: String,
+}
+
+#[derive(sqlx::Type, Copy, Clone, Debug, Eq, PartialEq)]
+pub(super) enum TokenKind {
+    /// Handed out by `login`, expires after `SESSION_DAYS`.
+    Session,
+    /// Made by an agent for its scripts, lasts until revoked.
+    Api,
+}
+
+/// Why the credentials of a request were refused.
+#[derive(Debug)]
+pub enum AuthenticationError {
+    /// The credentials are not a bearer token.
+    Malformed,
+    /// The token is unknown, expired or revoked.
+    Invalid,
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for AuthenticationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthenticationError::Malformed => write!(f, "expected a bearer token"),
+            AuthenticationError::Invalid => write!(f, "invalid or expired token"),
+            AuthenticationError::Database(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl From<sqlx::Error> for AuthenticationError {
+    fn from(error: sqlx::Error) -> Self {
+        AuthenticationError::Database(error)
+    }
+}
+
+/// Resolves the agent of a `Bearer <token>` authorization and records that the token was used.
+pub async fn authenticate(
+    pool: &SqlitePool,
+    authorization: &str,
+) -> Result<CurrentAgent, AuthenticationError> {
+    let token = match authorization.trim().split_once(' ') {
+        Some((scheme, token)) if scheme.eq_ignore_ascii_case("bearer") => token.trim(),
+        _ => return Err(AuthenticationError::Malformed),
+    };
+    let now = DateTime::now();
+    let current_agent = sqlx::query(
+        "
+        SELECT access_tokens.id, agent_id FROM access_tokens
+        JOIN agents ON agents.id = access_tokens.agent_id
+        WHERE token_hash = ? AND (expires_at IS NULL OR expires_at > ?)
+        AND agents.deleted_at IS NULL
+        ",
+    )
+    .bind(hash_token(token))
+    .bind(now)
+    .map(|row| CurrentAgent {
+        token_id: row.get("id"),
+        agent_id: row.get("agent_id"),
+    })
+    .fetch_optional(pool)
+    .await?
+    .ok_or(AuthenticationError::Invalid)?;
+    sqlx::query("UPDATE access_tokens SET last_used_at = ? WHERE id = ?")
+        .bind(now)
+        .bind(&current_agent.token_id)
+        .execute(pool)
+        .await?;
+    Ok(current_agent)
+}
+
+/// The agent the request is made on behalf of, if it was made with a token.
+pub(super) fn current_agent<'a>(context: &'a Context<'_>) -> Result<&'a CurrentAgent> {
+    context
+        .data_opt::<CurrentAgent>()
+        .ok_or_else(|| DomainError::unauthorized("you must be logged in").into())
+}
+
+/// Lets through requests made on behalf of an agent.
+pub(super) struct LoggedIn;
+
+#[async_trait]
+impl Guard for LoggedIn {
+    async fn check(&self, context: &Context<'_>) -> Result<()> {
+        current_agent(context).map(|_| ())
+    }
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Stores a new token for an agent and returns its secret, which is only kept hashed.
+pub(super) async fn issue_token(
+    connection: &mut SqliteConnection,
+    agent_id: &str,
+    kind: TokenKind,
+    name: Option<String>,
+) -> Result<(String, ApiToken)> {
+    let mut secret = [0u8; 32];
+    OsRng.fill_bytes(&mut secret);
+    let token = hex::encode(secret);
+    let expires_at = match kind {
+        TokenKind::Session => Some(DateTime::now() + chrono::Duration::days(SESSION_DAYS)),
+        TokenKind::Api => None,
+    };
+    let api_token = sqlx::query_as::<_, ApiToken>(
+        "
+        INSERT INTO access_tokens (id, agent_id, kind, name, token_hash, expires_at)
+        VALUES (?, ?, ?, ?, ?, ?)
+        RETURNING id, name, expires_at, last_used_at, inserted_at
+        ",
+    )
+    .bind(Ulid::new().to_string())
+    .bind(agent_id)
+    .bind(kind)
+    .bind(name)
+    .bind(hash_token(&token))
+    .bind(expires_at)
+    .fetch_one(connection)
+    .await?;
+    Ok((token, api_token))
+}
+
+/// Gives the agent with `email` its first password, so that a new instance has an agent to
+/// log in with and set the passwords of the others. An agent that already has a password
+/// keeps it, and an individual named `name`, or after its email, is created when there is
+/// none with that email yet. Returns whether the password was set.
+pub async fn bootstrap_password(
+    pool: &SqlitePool,
+    email: &str,
+    name: Option<&str>,
+    password: &str,
+) -> Result<bool> {
+    let password_hash: Option<Option<String>> = sqlx::query_scalar(
+        "SELECT password_hash FROM agents WHERE email = ? COLLATE NOCASE AND deleted_at IS NULL",
+    )
+    .bind(email)
+    .fetch_optional(pool)
+    .await?;
+    match password_hash {
+        Some(Some(_)) => return Ok(false),
+        Some(None) => {}
+        None => {
+            let name = name.unwrap_or_else(|| email.split('@').next().unwrap_or(email));
+            sqlx::query(
+                "
+                INSERT INTO agents (id, name, unique_name, email, agent_type, password_hash)
+                VALUES (?, ?, ?, ?, ?, ?)
+                ",
+            )
+            .bind(Ulid::new().to_string())
+            .bind(name)
+            .bind(unique_name(name))
+            .bind(email)
+            .bind(AgentType::Individual)
+            .bind(hash_password(password)?)
+            .execute(pool)
+            .await?;
+            return Ok(true);
+        }
+    }
+    sqlx::query("UPDATE agents SET password_hash = ? WHERE email = ? COLLATE NOCASE")
+        .bind(hash_password(password)?)
+        .bind(email)
+        .execute(pool)
+        .await?;
+    Ok(true)
+}
+
+pub(super) fn hash_password(password: &str) -> Result<String> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(DomainError::validation(format!(
+            "password must be at least {} characters",
+            MIN_PASSWORD_LENGTH
+        ))
+        .into());
+    }
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|error| Error::new(format!("failed to hash password: {}", error)))
+}
+
+pub(super) fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .map(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
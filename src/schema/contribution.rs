@@ -0,0 +1,306 @@
+use super::error::{DomainError, Result};
+use super::measure::{self, Decimal};
+use super::{Agent, EconomicEvent, IncomeDistribution, IncomeShare, ValueEquation};
+use sqlx::{
+    sqlite::{SqlitePool, SqliteRow},
+    Row,
+};
+use std::collections::HashMap;
+
+/// A `work`, `use` or `cite` event of a plan, credited to its provider.
+struct Contribution {
+    provider_id: String,
+    action_id: String,
+    resource_specification_id: Option<String>,
+    quantity: Decimal,
+    unit_id: Option<String>,
+}
+
+/// The plan whose contributions share the income of an event: the plan of the process the
+/// event flows into or out of, or else of the process that produced the resource it moves.
+async fn income_plan_id(
+    pool: &SqlitePool,
+    income_event: &EconomicEvent,
+) -> sqlx::Result<Option<String>> {
+    let plan_id = sqlx::query(
+        "
+        SELECT plan_id FROM processes
+        WHERE plan_id IS NOT NULL
+        AND (
+            id IN (?1, ?2)
+            OR id IN (
+                SELECT output_of_id FROM economic_events
+                WHERE resource_inventoried_as_id = ?3
+            )
+        )
+        ORDER BY id = ?1 DESC, id = ?2 DESC, inserted_at DESC
+        LIMIT 1
+        ",
+    )
+    .bind(&income_event.input_of_id)
+    .bind(&income_event.output_of_id)
+    .bind(&income_event.resource_inventoried_as_id)
+    .map(|row: SqliteRow| row.get("plan_id"))
+    .fetch_optional(pool)
+    .await?;
+    Ok(plan_id)
+}
+
+/// Splits the quantity of `income_event` between the agents who contributed to a plan.
+///
+/// Each contribution scores its quantity times the weight of its action, of its resource
+/// specification and of the strongest weighted role its provider holds in the context agent
+/// of the equation. Agents receive income in proportion to their total score.
+pub(super) async fn distribute_income(
+    pool: &SqlitePool,
+    income_event: &EconomicEvent,
+    value_equation: &ValueEquation,
+    plan_id: Option<String>,
+) -> Result<IncomeDistribution> {
+    let plan_id = match plan_id {
+        Some(plan_id) => plan_id,
+        None => income_plan_id(pool, income_event).await?.ok_or_else(|| {
+            DomainError::validation(format!(
+                "no plan found for event {}, planId is required",
+                income_event.id
+            ))
+        })?,
+    };
+    let contributions = sqlx::query(
+        "
+        SELECT economic_events.provider_id, economic_events.action_id, economic_events.resource_specification_id, economic_events.resource_quantity, economic_events.unit_id
+        FROM economic_events
+        JOIN actions
+        ON actions.id = economic_events.action_id
+        JOIN processes
+        ON processes.id = COALESCE(economic_events.input_of_id, economic_events.output_of_id)
+        WHERE processes.plan_id = ?
+        AND actions.name IN ('work', 'use', 'cite')
+        ",
+    )
+    .bind(&plan_id)
+    .map(|row: SqliteRow| Contribution {
+        provider_id: row.get("provider_id"),
+        action_id: row.get("action_id"),
+        resource_specification_id: row.get("resource_specification_id"),
+        quantity: row
+            .get::<Option<Decimal>, _>("resource_quantity")
+            .unwrap_or_default(),
+        unit_id: row.get("unit_id"),
+    })
+    .fetch_all(pool)
+    .await?;
+    let roles = sqlx::query(
+        "
+        SELECT subject_id, agent_relation_type_id
+        FROM agent_relations
+        WHERE object_id = ?
+        AND end_at IS NULL
+        ",
+    )
+    .bind(&value_equation.context_agent_id)
+    .map(|row: SqliteRow| {
+        (
+            row.get::<String, _>("subject_id"),
+            row.get::<String, _>("agent_relation_type_id"),
+        )
+    })
+    .fetch_all(pool)
+    .await?;
+
+    let weights = &value_equation.weights;
+    let action_weights: HashMap<&String, Decimal> = weights
+        .iter()
+        .filter_map(|w| w.action_id.as_ref().map(|id| (id, w.weight)))
+        .collect();
+    let resource_specification_weights: HashMap<&String, Decimal> = weights
+        .iter()
+        .filter_map(|w| {
+            w.resource_specification_id
+                .as_ref()
+                .map(|id| (id, w.weight))
+        })
+        .collect();
+    let role_weights: HashMap<&String, Decimal> = weights
+        .iter()
+        .filter_map(|w| w.agent_relation_type_id.as_ref().map(|id| (id, w.weight)))
+        .collect();
+    let agent_role_weights = roles.iter().fold(
+        HashMap::<&String, Decimal>::new(),
+        |mut acc, (agent_id, agent_relation_type_id)| {
+            if let Some(weight) = role_weights.get(agent_relation_type_id) {
+                let agent_weight = acc.entry(agent_id).or_insert(*weight);
+                *agent_weight = (*agent_weight).max(*weight);
+            }
+            acc
+        },
+    );
+
+    let units = measure::units_by_id(pool).await?;
+    let mut scores = contributions
+        .iter()
+        .filter_map(|c| {
+            let action_weight = action_weights.get(&c.action_id)?;
+            // hours and minutes of work count the same once in the base unit of their dimension
+            let quantity = match c.unit_id.as_ref().and_then(|id| units.get(id)) {
+                Some(unit) => c.quantity * unit.conversion_factor.unwrap_or(Decimal::ONE),
+                None => c.quantity,
+            };
+            let resource_specification_weight = c
+                .resource_specification_id
+                .as_ref()
+                .and_then(|id| resource_specification_weights.get(id))
+                .unwrap_or(&Decimal::ONE);
+            let role_weight = agent_role_weights
+                .get(&c.provider_id)
+                .unwrap_or(&Decimal::ONE);
+            let score = quantity * *action_weight * *resource_specification_weight * *role_weight;
+            Some((&c.provider_id, score))
+        })
+        .fold(
+            HashMap::<&String, Decimal>::new(),
+            |mut acc, (agent_id, score)| {
+                let agent_score = acc.entry(agent_id).or_default();
+                *agent_score = *agent_score + score;
+                acc
+            },
+        )
+        .into_iter()
+        .filter(|(_, score)| score.is_positive())
+        .collect::<Vec<_>>();
+    scores.sort_by(|(a_id, a), (b_id, b)| b.cmp(a).then(a_id.cmp(b_id)));
+
+    let agent_ids =
+        serde_json::json!(scores.iter().map(|(id, _)| id).collect::<Vec<_>>()).to_string();
+    let agents = sqlx::query("SELECT * FROM agents WHERE id IN (SELECT value FROM json_each(?))")
+        .bind(&agent_ids)
+        .map(|row| {
+            let agent = Agent::from_row(row);
+            (agent.id.clone(), agent)
+        })
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect::<HashMap<String, Agent>>();
+    let quantity = income_event.resource_quantity.unwrap_or_default();
+    let total_score: Decimal = scores.iter().map(|(_, score)| *score).sum();
+    let shares = scores
+        .into_iter()
+        .map(|(agent_id, score)| {
+            // every score is positive, and so is their total
+            let fraction = score.checked_div(total_score).unwrap_or_default();
+            IncomeShare {
+                agent_id: agent_id.clone(),
+                agent: agents.get(agent_id).cloned(),
+                score,
+                fraction,
+                quantity: fraction * quantity,
+            }
+        })
+        .collect();
+    Ok(IncomeDistribution {
+        income_event_id: income_event.id.clone(),
+        value_equation_id: value_equation.id.clone(),
+        plan_id,
+        quantity,
+        shares,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_pool;
+    use super::super::ValueEquationWeight;
+    use super::*;
+
+    /// Ann works two hours and Bob half an hour in the bakery plan, which Ann cites a
+    /// document for.
+    const CONTRIBUTIONS: &str = "
+        INSERT INTO actions (id, name, input_output) VALUES ('work', 'work', 'Input');
+        INSERT INTO actions (id, name, input_output) VALUES ('cite', 'cite', 'Input');
+        INSERT INTO units (id, label, dimension, conversion_factor) VALUES ('hour', 'hour', 'time', 3600);
+        INSERT INTO units (id, label, dimension, conversion_factor) VALUES ('minute', 'minute', 'time', 60);
+        INSERT INTO agents (id, unique_name, name, agent_type) VALUES ('ann', 'ann', 'Ann', 'Individual');
+        INSERT INTO agents (id, unique_name, name, agent_type) VALUES ('bob', 'bob', 'Bob', 'Individual');
+        INSERT INTO plans (id, title) VALUES ('bakery', 'Bakery');
+        INSERT INTO processes (id, title, plan_id) VALUES ('bake', 'Bake', 'bakery');
+        INSERT INTO economic_events (id, action_id, provider_id, receiver_id, resource_quantity, unit_id, input_of_id)
+        VALUES ('ann-works', 'work', 'ann', 'ann', 2, 'hour', 'bake');
+        INSERT INTO economic_events (id, action_id, provider_id, receiver_id, resource_quantity, unit_id, input_of_id)
+        VALUES ('bob-works', 'work', 'bob', 'bob', 30, 'minute', 'bake');
+        INSERT INTO economic_events (id, action_id, provider_id, receiver_id, resource_quantity, input_of_id)
+        VALUES ('ann-cites', 'cite', 'ann', 'ann', 1, 'bake');
+    ";
+
+    fn income() -> EconomicEvent {
+        EconomicEvent {
+            id: "sale".to_string(),
+            resource_quantity: Some(Decimal::from(100)),
+            ..Default::default()
+        }
+    }
+
+    fn value_equation(action_weights: &[(&str, i32)]) -> ValueEquation {
+        ValueEquation {
+            id: "equation".to_string(),
+            weights: action_weights
+                .iter()
+                .map(|(action_id, weight)| ValueEquationWeight {
+                    action_id: Some(action_id.to_string()),
+                    weight: Decimal::from(*weight),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn income_is_shared_in_proportion_to_scores() {
+        let pool = test_pool(CONTRIBUTIONS).await;
+        let distribution = distribute_income(
+            &pool,
+            &income(),
+            &value_equation(&[("work", 1)]),
+            Some("bakery".to_string()),
+        )
+        .await
+        .unwrap();
+        let shares: Vec<(&str, Decimal, Decimal)> = distribution
+            .shares
+            .iter()
+            .map(|share| (share.agent_id.as_str(), share.score, share.quantity))
+            .collect();
+        // hours and minutes are compared in seconds
+        assert_eq!(
+            shares,
+            [
+                ("ann", Decimal::from(7200), Decimal::from(80)),
+                ("bob", Decimal::from(1800), Decimal::from(20)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn nothing_is_shared_when_no_contribution_scores() {
+        let pool = test_pool(CONTRIBUTIONS).await;
+        let distribution = distribute_income(
+            &pool,
+            &income(),
+            &value_equation(&[("work", 0), ("cite", 0)]),
+            Some("bakery".to_string()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(distribution.quantity, Decimal::from(100));
+        assert!(distribution.shares.is_empty());
+    }
+
+    #[tokio::test]
+    async fn an_income_outside_any_plan_needs_a_plan_id() {
+        let pool = test_pool(CONTRIBUTIONS).await;
+        let distribution =
+            distribute_income(&pool, &income(), &value_equation(&[("work", 1)]), None).await;
+        assert!(distribution.is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use super::validation::InvalidField;
+use async_graphql::{Error, ErrorExtensions, Value};
+use std::fmt::Display;
+use std::sync::Arc;
+
+/// Extended result codes of the constraint errors sqlite reports.
+const SQLITE_CONSTRAINT_CHECK: &str = "275";
+const SQLITE_CONSTRAINT_FOREIGNKEY: &str = "787";
+const SQLITE_CONSTRAINT_NOTNULL: &str = "1299";
+const SQLITE_CONSTRAINT_PRIMARYKEY: &str = "1555";
+const SQLITE_CONSTRAINT_UNIQUE: &str = "2067";
+
+pub(super) type Result<T, E = DomainError> = std::result::Result<T, E>;
+
+/// Why a request failed. Each kind reaches clients as the `code` extension of the error, so
+/// they don't have to parse messages.
+#[derive(Clone, Debug)]
+pub(super) enum DomainError {
+    /// A record the request is about doesn't exist.
+    NotFound(String),
+    /// A value that has to be unique is already taken.
+    Conflict(String),
+    /// A record refers to one that doesn't exist.
+    ForeignKey(String),
+    /// An argument is invalid.
+    Validation(String),
+    /// Fields of the input of a mutation are invalid. Reported with the `fields` extension,
+    /// listing the `path` and the `message` of each.
+    InvalidFields(Vec<InvalidField>),
+    /// The request needs another agent, or one at all.
+    Unauthorized(String),
+    /// Anything else, as it was raised.
+    Other(Error),
+}
+
+impl DomainError {
+    /// That `what`, such as `plan 01FMT8VXWAWQ2PXWB2K908W2RR`, doesn't exist.
+    pub(super) fn not_found(what: impl Display) -> Self {
+        DomainError::NotFound(format!("{} not found", what))
+    }
+
+    pub(super) fn conflict(message: impl Display) -> Self {
+        DomainError::Conflict(message.to_string())
+    }
+
+    pub(super) fn validation(message: impl Display) -> Self {
+        DomainError::Validation(message.to_string())
+    }
+
+    pub(super) fn unauthorized(message: impl Display) -> Self {
+        DomainError::Unauthorized(message.to_string())
+    }
+
+    fn code(&self) -> Option<&'static str> {
+        match self {
+            DomainError::NotFound(_) => Some("NOT_FOUND"),
+            DomainError::Conflict(_) => Some("CONFLICT"),
+            DomainError::ForeignKey(_) => Some("FOREIGN_KEY"),
+            DomainError::Validation(_) | DomainError::InvalidFields(_) => Some("VALIDATION"),
+            DomainError::Unauthorized(_) => Some("UNAUTHORIZED"),
+            DomainError::Other(_) => None,
+        }
+    }
+}
+
+impl From<DomainError> for Error {
+    fn from(error: DomainError) -> Self {
+        let code = error.code();
+        let error = match error {
+            DomainError::NotFound(message)
+            | DomainError::Conflict(message)
+            | DomainError::ForeignKey(message)
+            | DomainError::Validation(message)
+            | DomainError::Unauthorized(message) => Error::new(message),
+            DomainError::InvalidFields(invalid_fields) => {
+                let message = invalid_fields
+                    .iter()
+                    .map(|field| format!("{}: {}", field.path, field.message))
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                let fields = Value::List(
+                    invalid_fields
+                        .into_iter()
+                        .map(|field| {
+                            Value::from_json(serde_json::json!({
+                                "path": field.path,
+                                "message": field.message,
+                            }))
+                            .unwrap_or_default()
+                        })
+                        .collect(),
+                );
+                Error::new(message).extend_with(|_, extensions| extensions.set("fields", fields))
+            }
+            DomainError::Other(error) => return error,
+        };
+        match code {
+            Some(code) => error.extend_with(|_, extensions| extensions.set("code", code)),
+            None => error,
+        }
+    }
+}
+
+impl From<Error> for DomainError {
+    fn from(error: Error) -> Self {
+        DomainError::Other(error)
+    }
+}
+
+impl From<&sqlx::Error> for DomainError {
+    fn from(error: &sqlx::Error) -> Self {
+        match error {
+            sqlx::Error::RowNotFound => DomainError::NotFound("record not found".to_string()),
+            sqlx::Error::Database(database_error) => {
+                let message = database_error.message().to_string();
+                match database_error.code().as_deref() {
+                    Some(SQLITE_CONSTRAINT_UNIQUE) | Some(SQLITE_CONSTRAINT_PRIMARYKEY) => {
+                        DomainError::Conflict(message)
+                    }
+                    Some(SQLITE_CONSTRAINT_FOREIGNKEY) => DomainError::ForeignKey(message),
+                    Some(SQLITE_CONSTRAINT_CHECK) | Some(SQLITE_CONSTRAINT_NOTNULL) => {
+                        DomainError::Validation(message)
+                    }
+                    _ => DomainError::Other(Error::new(message)),
+                }
+            }
+            _ => DomainError::Other(Error::new(error.to_string())),
+        }
+    }
+}
+
+impl From<sqlx::Error> for DomainError {
+    fn from(error: sqlx::Error) -> Self {
+        DomainError::from(&error)
+    }
+}
+
+/// The errors of the data loaders.
+impl From<Arc<sqlx::Error>> for DomainError {
+    fn from(error: Arc<sqlx::Error>) -> Self {
+        DomainError::from(error.as_ref())
+    }
+}
@@ -0,0 +1,215 @@
+use super::pagination::List;
+use super::time::DateTime;
+use super::AgentType;
+use async_graphql::{Enum, InputObject};
+
+fn json_ids(ids: &[String]) -> String {
+    serde_json::json!(ids).to_string()
+}
+
+#[derive(InputObject, Default)]
+pub(super) struct AgentFilter {
+    agent_type: Option<AgentType>,
+    /// Case-insensitive part of the name.
+    name_contains: Option<String>,
+    /// Case-insensitive email address.
+    email: Option<String>,
+    inserted_after: Option<DateTime>,
+}
+
+impl AgentFilter {
+    pub(super) fn agent_type(agent_type: AgentType) -> Self {
+        AgentFilter {
+            agent_type: Some(agent_type),
+            ..Default::default()
+        }
+    }
+
+    pub(super) fn apply(self, list: &mut List) {
+        if let Some(agent_type) = self.agent_type {
+            let agent_type = match agent_type {
+                AgentType::Individual => "Individual",
+                AgentType::Organization => "Organization",
+                AgentType::Project => "Project",
+            };
+            list.filter("agents.agent_type = ?", [agent_type.to_string()]);
+        }
+        if let Some(name) = self.name_contains {
+            list.filter("instr(lower(agents.name), lower(?)) > 0", [name]);
+        }
+        if let Some(email) = self.email {
+            list.filter("agents.email = ? COLLATE NOCASE", [email]);
+        }
+        if let Some(inserted_after) = self.inserted_after {
+            list.filter("agents.inserted_at > ?", [inserted_after.stored()]);
+        }
+    }
+}
+
+#[derive(InputObject, Default)]
+pub(super) struct ProcessFilter {
+    plan_id: Option<String>,
+    /// Processes with any of these labels.
+    label_ids: Option<Vec<String>>,
+    /// Processes with any of these agents.
+    agent_ids: Option<Vec<String>>,
+    due_before: Option<DateTime>,
+    /// Whether every commitment of the process is finished. Processes without commitments
+    /// are never finished.
+    finished: Option<bool>,
+}
+
+impl ProcessFilter {
+    pub(super) fn apply(self, list: &mut List) {
+        if let Some(plan_id) = self.plan_id {
+            list.filter("processes.plan_id = ?", [plan_id]);
+        }
+        if let Some(label_ids) = self.label_ids {
+            list.filter(
+                "processes.id IN (SELECT process_id FROM process_labels WHERE label_id IN (SELECT value FROM json_each(?)))",
+                [json_ids(&label_ids)],
+            );
+        }
+        if let Some(agent_ids) = self.agent_ids {
+            list.filter(
+                "processes.id IN (SELECT process_id FROM process_agents WHERE agent_id IN (SELECT value FROM json_each(?)))",
+                [json_ids(&agent_ids)],
+            );
+        }
+        if let Some(due_before) = self.due_before {
+            list.filter("processes.due_at < ?", [due_before.stored()]);
+        }
+        if let Some(finished) = self.finished {
+            let finished_condition = "
+                EXISTS (
+                    SELECT 1 FROM commitments
+                    WHERE commitments.process_id = processes.id AND commitments.deleted_at IS NULL
+                )
+                AND NOT EXISTS (
+                    SELECT 1 FROM commitments
+                    WHERE commitments.process_id = processes.id AND commitments.deleted_at IS NULL
+                    AND NOT commitments.finished
+                )";
+            if finished {
+                list.filter(finished_condition, []);
+            } else {
+                list.filter(&format!("NOT ({})", finished_condition), []);
+            }
+        }
+    }
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub(super) enum AgentOrderBy {
+    InsertedAtDesc,
+    InsertedAtAsc,
+    NameAsc,
+    NameDesc,
+}
+
+impl Default for AgentOrderBy {
+    fn default() -> Self {
+        AgentOrderBy::InsertedAtDesc
+    }
+}
+
+impl AgentOrderBy {
+    pub(super) fn apply<'a>(self, list: List<'a>) -> List<'a> {
+        match self {
+            AgentOrderBy::InsertedAtDesc => list.order_by("agents.id", true),
+            AgentOrderBy::InsertedAtAsc => list.order_by("agents.id", false),
+            AgentOrderBy::NameAsc => list.order_by("agents.name", false),
+            AgentOrderBy::NameDesc => list.order_by("agents.name", true),
+        }
+    }
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub(super) enum ProcessOrderBy {
+    InsertedAtDesc,
+    InsertedAtAsc,
+    TitleAsc,
+    TitleDesc,
+    /// Processes without a start come first.
+    StartAtAsc,
+    StartAtDesc,
+    /// Processes without a due date come first.
+    DueAtAsc,
+    DueAtDesc,
+}
+
+impl Default for ProcessOrderBy {
+    fn default() -> Self {
+        ProcessOrderBy::InsertedAtDesc
+    }
+}
+
+impl ProcessOrderBy {
+    pub(super) fn apply<'a>(self, list: List<'a>) -> List<'a> {
+        match self {
+            ProcessOrderBy::InsertedAtDesc => list.order_by("processes.id", true),
+            ProcessOrderBy::InsertedAtAsc => list.order_by("processes.id", false),
+            ProcessOrderBy::TitleAsc => list.order_by("processes.title", false),
+            ProcessOrderBy::TitleDesc => list.order_by("processes.title", true),
+            ProcessOrderBy::StartAtAsc => list.order_by("COALESCE(processes.start_at, '')", false),
+            ProcessOrderBy::StartAtDesc => list.order_by("COALESCE(processes.start_at, '')", true),
+            ProcessOrderBy::DueAtAsc => list.order_by("COALESCE(processes.due_at, '')", false),
+            ProcessOrderBy::DueAtDesc => list.order_by("COALESCE(processes.due_at, '')", true),
+        }
+    }
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub(super) enum PlanOrderBy {
+    InsertedAtDesc,
+    InsertedAtAsc,
+    TitleAsc,
+    TitleDesc,
+}
+
+impl Default for PlanOrderBy {
+    fn default() -> Self {
+        PlanOrderBy::InsertedAtDesc
+    }
+}
+
+impl PlanOrderBy {
+    pub(super) fn apply<'a>(self, list: List<'a>) -> List<'a> {
+        match self {
+            PlanOrderBy::InsertedAtDesc => list.order_by("plans.id", true),
+            PlanOrderBy::InsertedAtAsc => list.order_by("plans.id", false),
+            PlanOrderBy::TitleAsc => list.order_by("plans.title", false),
+            PlanOrderBy::TitleDesc => list.order_by("plans.title", true),
+        }
+    }
+}
+
+/// Order of lists of named things: labels, actions, units and resource specifications.
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub(super) enum NameOrderBy {
+    InsertedAtDesc,
+    InsertedAtAsc,
+    NameAsc,
+    NameDesc,
+}
+
+impl Default for NameOrderBy {
+    fn default() -> Self {
+        NameOrderBy::InsertedAtDesc
+    }
+}
+
+impl NameOrderBy {
+    /// Lists `table`, sorted on `name_column` for the name orders.
+    pub(super) fn list<'a>(self, table: &'a str, name_column: &str) -> List<'a> {
+        let list = List::table(table);
+        let id = format!("{}.id", table);
+        let name = format!("{}.{}", table, name_column);
+        match self {
+            NameOrderBy::InsertedAtDesc => list.order_by(&id, true),
+            NameOrderBy::InsertedAtAsc => list.order_by(&id, false),
+            NameOrderBy::NameAsc => list.order_by(&name, false),
+            NameOrderBy::NameDesc => list.order_by(&name, true),
+        }
+    }
+}
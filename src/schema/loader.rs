@@ -0,0 +1,362 @@
+use super::{
+    Action, Agent, AuditEntry, Commitment, Fulfillment, Label, Location, Plan, Process,
+    ResourceSpecification, Unit,
+};
+use async_graphql::async_trait::async_trait;
+use async_graphql::dataloader::Loader;
+use sqlx::{sqlite::SqlitePool, Row};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+
+/// Reads the records related to the ones a query resolves. The reads made for all of them
+/// are batched into one query per relation, so nested fields cost a query per level rather
+/// than one per parent.
+pub struct SqliteLoader {
+    pool: SqlitePool,
+}
+
+impl SqliteLoader {
+    pub fn new(pool: SqlitePool) -> Self {
+        SqliteLoader { pool }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(super) struct ActionId(pub(super) String);
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(super) struct AgentId(pub(super) String);
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(super) struct UnitId(pub(super) String);
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(super) struct ResourceSpecificationId(pub(super) String);
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(super) struct LocationId(pub(super) String);
+
+/// The labels of a process.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(super) struct ProcessLabels(pub(super) String);
+
+/// The agents of a process.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(super) struct ProcessAgents(pub(super) String);
+
+/// The commitments of a process.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(super) struct ProcessCommitments(pub(super) String);
+
+/// The processes of a plan.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(super) struct PlanProcesses(pub(super) String);
+
+/// The plans of an agent.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(super) struct AgentPlans(pub(super) String);
+
+/// The fulfillments of a commitment.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(super) struct CommitmentFulfillments(pub(super) String);
+
+/// The changes made to a record.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(super) struct EntityHistory(pub(super) String);
+
+fn json_ids<'a>(ids: impl Iterator<Item = &'a String>) -> String {
+    serde_json::json!(ids.collect::<Vec<_>>()).to_string()
+}
+
+/// Groups records by the id of the record they are related to.
+fn group<K, T>(pairs: Vec<(String, T)>, key: fn(String) -> K) -> HashMap<K, Vec<T>>
+where
+    K: Eq + Hash,
+{
+    pairs
+        .into_iter()
+        .fold(HashMap::new(), |mut acc, (id, record)| {
+            acc.entry(key(id)).or_insert_with(Vec::new).push(record);
+            acc
+        })
+}
+
+#[async_trait]
+impl Loader<ActionId> for SqliteLoader {
+    type Value = Action;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, keys: &[ActionId]) -> Result<HashMap<ActionId, Action>, Self::Error> {
+        let actions = sqlx::query_as::<_, Action>(
+            "SELECT * FROM actions WHERE id IN (SELECT value FROM json_each(?))",
+        )
+        .bind(json_ids(keys.iter().map(|key| &key.0)))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(actions
+            .into_iter()
+            .map(|action| (ActionId(action.id.clone()), action))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl Loader<AgentId> for SqliteLoader {
+    type Value = Agent;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, keys: &[AgentId]) -> Result<HashMap<AgentId, Agent>, Self::Error> {
+        let agents =
+            sqlx::query("SELECT * FROM agents WHERE id IN (SELECT value FROM json_each(?))")
+                .bind(json_ids(keys.iter().map(|key| &key.0)))
+                .map(Agent::from_row)
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(agents
+            .into_iter()
+            .map(|agent| (AgentId(agent.id.clone()), agent))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl Loader<UnitId> for SqliteLoader {
+    type Value = Unit;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, keys: &[UnitId]) -> Result<HashMap<UnitId, Unit>, Self::Error> {
+        let units = sqlx::query_as::<_, Unit>(
+            "SELECT * FROM units WHERE id IN (SELECT value FROM json_each(?))",
+        )
+        .bind(json_ids(keys.iter().map(|key| &key.0)))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(units
+            .into_iter()
+            .map(|unit| (UnitId(unit.id.clone()), unit))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl Loader<ResourceSpecificationId> for SqliteLoader {
+    type Value = ResourceSpecification;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(
+        &self,
+        keys: &[ResourceSpecificationId],
+    ) -> Result<HashMap<ResourceSpecificationId, ResourceSpecification>, Self::Error> {
+        let resource_specifications = sqlx::query(
+            "SELECT * FROM resource_specifications WHERE id IN (SELECT value FROM json_each(?))",
+        )
+        .bind(json_ids(keys.iter().map(|key| &key.0)))
+        .map(ResourceSpecification::from_row)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(resource_specifications
+            .into_iter()
+            .map(|resource_specification| {
+                (
+                    ResourceSpecificationId(resource_specification.id.clone()),
+                    resource_specification,
+                )
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl Loader<LocationId> for SqliteLoader {
+    type Value = Location;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(
+        &self,
+        keys: &[LocationId],
+    ) -> Result<HashMap<LocationId, Location>, Self::Error> {
+        let locations =
+            sqlx::query("SELECT * FROM locations WHERE id IN (SELECT value FROM json_each(?))")
+                .bind(json_ids(keys.iter().map(|key| &key.0)))
+                .map(Location::from_row)
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(locations
+            .into_iter()
+            .map(|location| (LocationId(location.id.clone()), location))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl Loader<ProcessLabels> for SqliteLoader {
+    type Value = Vec<Label>;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(
+        &self,
+        keys: &[ProcessLabels],
+    ) -> Result<HashMap<ProcessLabels, Vec<Label>>, Self::Error> {
+        let process_id_labels_tuples = sqlx::query(
+            "
+            SELECT labels.*, process_id FROM labels
+            JOIN process_labels ON process_labels.label_id = labels.id
+            WHERE process_id IN (SELECT value FROM json_each(?))
+            ",
+        )
+        .bind(json_ids(keys.iter().map(|key| &key.0)))
+        .map(|row| (row.get("process_id"), Label::from_row(row)))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(group(process_id_labels_tuples, ProcessLabels))
+    }
+}
+
+#[async_trait]
+impl Loader<ProcessAgents> for SqliteLoader {
+    type Value = Vec<Agent>;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(
+        &self,
+        keys: &[ProcessAgents],
+    ) -> Result<HashMap<ProcessAgents, Vec<Agent>>, Self::Error> {
+        let process_id_agents_tuples = sqlx::query(
+            "
+            SELECT agents.*, process_id FROM agents
+            JOIN process_agents ON process_agents.agent_id = agents.id
+            WHERE process_id IN (SELECT value FROM json_each(?)) AND agents.deleted_at IS NULL
+            ",
+        )
+        .bind(json_ids(keys.iter().map(|key| &key.0)))
+        .map(|row| (row.get("process_id"), Agent::from_row(row)))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(group(process_id_agents_tuples, ProcessAgents))
+    }
+}
+
+#[async_trait]
+impl Loader<ProcessCommitments> for SqliteLoader {
+    type Value = Vec<Commitment>;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(
+        &self,
+        keys: &[ProcessCommitments],
+    ) -> Result<HashMap<ProcessCommitments, Vec<Commitment>>, Self::Error> {
+        let process_id_commitments_tuples = sqlx::query(
+            "
+            SELECT * FROM commitments
+            WHERE process_id IN (SELECT value FROM json_each(?)) AND deleted_at IS NULL
+            ORDER BY id
+            ",
+        )
+        .bind(json_ids(keys.iter().map(|key| &key.0)))
+        .map(|row| (row.get("process_id"), Commitment::from_row(row)))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(group(process_id_commitments_tuples, ProcessCommitments))
+    }
+}
+
+#[async_trait]
+impl Loader<PlanProcesses> for SqliteLoader {
+    type Value = Vec<Process>;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(
+        &self,
+        keys: &[PlanProcesses],
+    ) -> Result<HashMap<PlanProcesses, Vec<Process>>, Self::Error> {
+        let plan_id_processes_tuples = sqlx::query(
+            "
+            SELECT * FROM processes
+            WHERE plan_id IN (SELECT value FROM json_each(?)) AND deleted_at IS NULL
+            ORDER BY id
+            ",
+        )
+        .bind(json_ids(keys.iter().map(|key| &key.0)))
+        .map(|row| (row.get("plan_id"), Process::from_row(row)))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(group(plan_id_processes_tuples, PlanProcesses))
+    }
+}
+
+#[async_trait]
+impl Loader<AgentPlans> for SqliteLoader {
+    type Value = Vec<Plan>;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(
+        &self,
+        keys: &[AgentPlans],
+    ) -> Result<HashMap<AgentPlans, Vec<Plan>>, Self::Error> {
+        let agent_id_plans_tuples = sqlx::query(
+            "
+            SELECT plans.*, agent_id FROM plans
+            JOIN plan_agents ON plan_agents.plan_id = plans.id
+            WHERE agent_id IN (SELECT value FROM json_each(?)) AND plans.deleted_at IS NULL
+            ORDER BY plans.id DESC
+            ",
+        )
+        .bind(json_ids(keys.iter().map(|key| &key.0)))
+        .map(|row| (row.get("agent_id"), Plan::from_row(row)))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(group(agent_id_plans_tuples, AgentPlans))
+    }
+}
+
+#[async_trait]
+impl Loader<CommitmentFulfillments> for SqliteLoader {
+    type Value = Vec<Fulfillment>;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(
+        &self,
+        keys: &[CommitmentFulfillments],
+    ) -> Result<HashMap<CommitmentFulfillments, Vec<Fulfillment>>, Self::Error> {
+        let commitment_id_fulfillments_tuples = sqlx::query(
+            "
+            SELECT * FROM fulfillments
+            WHERE fulfills_id IN (SELECT value FROM json_each(?))
+            ORDER BY inserted_at
+            ",
+        )
+        .bind(json_ids(keys.iter().map(|key| &key.0)))
+        .map(|row| (row.get("fulfills_id"), Fulfillment::from_row(row)))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(group(
+            commitment_id_fulfillments_tuples,
+            CommitmentFulfillments,
+        ))
+    }
+}
+
+#[async_trait]
+impl Loader<EntityHistory> for SqliteLoader {
+    type Value = Vec<AuditEntry>;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(
+        &self,
+        keys: &[EntityHistory],
+    ) -> Result<HashMap<EntityHistory, Vec<AuditEntry>>, Self::Error> {
+        let entity_id_entries_tuples = sqlx::query(
+            "
+            SELECT * FROM audit_log
+            WHERE entity_id IN (SELECT value FROM json_each(?))
+            ORDER BY id
+            ",
+        )
+        .bind(json_ids(keys.iter().map(|key| &key.0)))
+        .map(|row| (row.get("entity_id"), AuditEntry::from_row(row)))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(group(entity_id_entries_tuples, EntityHistory))
+    }
+}
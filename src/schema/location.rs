@@ -0,0 +1,88 @@
+use super::error::{DomainError, Result};
+use super::loader::{LocationId, SqliteLoader};
+use super::{Intent, Location, NearbyLocation};
+use async_graphql::dataloader::DataLoader;
+use async_graphql::{ComplexObject, Context};
+use sqlx::{
+    sqlite::{SqlitePool, SqliteRow},
+    Row,
+};
+
+/// Length of a degree of latitude, and of longitude at the equator, on the mean earth sphere.
+const KM_PER_DEGREE: f64 = 111.195;
+
+/// The location with an id, read along with the other locations resolved at the same time.
+pub(super) async fn load_location(
+    context: &Context<'_>,
+    id: &Option<String>,
+) -> Result<Option<Location>> {
+    let loader = context.data::<DataLoader<SqliteLoader>>()?;
+    match id {
+        Some(id) => Ok(loader.load_one(LocationId(id.clone())).await?),
+        None => Ok(None),
+    }
+}
+
+pub(super) fn validate_coordinates(lat: f64, long: f64) -> Result<()> {
+    if (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&long) {
+        Ok(())
+    } else {
+        Err(DomainError::validation(
+            "lat must be within ±90 and long within ±180",
+        ))
+    }
+}
+
+/// Locations within `radius_km` of a point, nearest first.
+///
+/// SQLite is built without trigonometric functions, so distances are measured on the
+/// equirectangular projection centered on the point: degrees of longitude are shortened by
+/// the cosine of its latitude. This is precise to well under a percent at the scale of a
+/// region, which is what planning logistics between sites needs.
+pub(super) async fn nearby(
+    pool: &SqlitePool,
+    lat: f64,
+    long: f64,
+    radius_km: f64,
+) -> Result<Vec<NearbyLocation>> {
+    validate_coordinates(lat, long)?;
+    if !(radius_km >= 0.0 && radius_km.is_finite()) {
+        return Err(DomainError::validation(
+            "radiusKm must be a non-negative number",
+        ));
+    }
+    let radius_degrees = radius_km / KM_PER_DEGREE;
+    let locations = sqlx::query(
+        "
+        SELECT *,
+            (lat - ?1) * (lat - ?1)
+            + (MIN(ABS(long - ?2), 360 - ABS(long - ?2)) * ?3)
+            * (MIN(ABS(long - ?2), 360 - ABS(long - ?2)) * ?3) AS squared_degrees
+        FROM locations
+        WHERE lat BETWEEN ?1 - ?4 AND ?1 + ?4
+        AND squared_degrees <= ?4 * ?4
+        ORDER BY squared_degrees, id
+        ",
+    )
+    .bind(lat)
+    .bind(long)
+    .bind(lat.to_radians().cos())
+    .bind(radius_degrees)
+    .map(|row: SqliteRow| {
+        let squared_degrees: f64 = row.get("squared_degrees");
+        NearbyLocation {
+            distance_km: squared_degrees.sqrt() * KM_PER_DEGREE,
+            location: Location::from_row(row),
+        }
+    })
+    .fetch_all(pool)
+    .await?;
+    Ok(locations)
+}
+
+#[ComplexObject]
+impl Intent {
+    async fn at_location<'ctx>(&self, context: &Context<'ctx>) -> Result<Option<Location>> {
+        load_location(context, &self.at_location_id).await
+    }
+}
@@ -0,0 +1,254 @@
+use super::error::{DomainError, Result};
+use super::time::DateTime;
+use super::Unit;
+use async_graphql::{InputValueError, InputValueResult, Number, Scalar, ScalarType, Value};
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use sqlx::{
+    decode::Decode,
+    encode::{Encode, IsNull},
+    error::BoxDynError,
+    sqlite::{Sqlite, SqliteArgumentValue, SqliteExecutor, SqliteTypeInfo, SqliteValueRef},
+    Type,
+};
+use std::collections::HashMap;
+use std::iter::Sum;
+use std::ops::{Add, Mul, Neg, Sub};
+use std::str::FromStr;
+
+/// An exact decimal quantity. Stored as TEXT, since a REAL would round it, and added up in
+/// Rust rather than in SQL for the same reason.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Decimal(rust_decimal::Decimal);
+
+impl Decimal {
+    pub const ZERO: Decimal = Decimal(rust_decimal::Decimal::ZERO);
+    pub const ONE: Decimal = Decimal(rust_decimal::Decimal::ONE);
+
+    pub fn is_positive(&self) -> bool {
+        self.0 > rust_decimal::Decimal::ZERO
+    }
+
+    /// Divides, or gives `None` when dividing by zero.
+    pub fn checked_div(self, other: Decimal) -> Option<Decimal> {
+        self.0.checked_div(other.0).map(Decimal)
+    }
+}
+
+impl From<i32> for Decimal {
+    fn from(value: i32) -> Self {
+        Decimal(value.into())
+    }
+}
+
+impl Add for Decimal {
+    type Output = Decimal;
+
+    fn add(self, other: Decimal) -> Decimal {
+        Decimal(self.0 + other.0)
+    }
+}
+
+impl Sub for Decimal {
+    type Output = Decimal;
+
+    fn sub(self, other: Decimal) -> Decimal {
+        Decimal(self.0 - other.0)
+    }
+}
+
+impl Mul for Decimal {
+    type Output = Decimal;
+
+    fn mul(self, other: Decimal) -> Decimal {
+        Decimal(self.0 * other.0)
+    }
+}
+
+impl Neg for Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        Decimal(-self.0)
+    }
+}
+
+impl Sum for Decimal {
+    fn sum<I: Iterator<Item = Decimal>>(iter: I) -> Decimal {
+        iter.fold(Decimal::ZERO, Add::add)
+    }
+}
+
+/// Decimals are read from numbers or strings and written as numbers.
+#[Scalar]
+impl ScalarType for Decimal {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        let decimal = match &value {
+            Value::Number(n) => match n.as_i64() {
+                Some(i) => Some(rust_decimal::Decimal::from(i)),
+                None => n.as_f64().and_then(rust_decimal::Decimal::from_f64),
+            },
+            Value::String(s) => rust_decimal::Decimal::from_str(s).ok(),
+            _ => return Err(InputValueError::expected_type(value)),
+        };
+        decimal
+            .map(|d| Decimal(d.normalize()))
+            .ok_or_else(|| InputValueError::custom("invalid decimal"))
+    }
+
+    fn to_value(&self) -> Value {
+        let decimal = self.0.normalize();
+        match decimal.to_i64() {
+            Some(i) if decimal.scale() == 0 => Value::Number(i.into()),
+            _ => decimal
+                .to_f64()
+                .and_then(Number::from_f64)
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+        }
+    }
+}
+
+impl Type<Sqlite> for Decimal {
+    fn type_info() -> SqliteTypeInfo {
+        <str as Type<Sqlite>>::type_info()
+    }
+
+    fn compatible(ty: &SqliteTypeInfo) -> bool {
+        <str as Type<Sqlite>>::compatible(ty)
+            || <f64 as Type<Sqlite>>::compatible(ty)
+            || <i64 as Type<Sqlite>>::compatible(ty)
+    }
+}
+
+impl<'q> Encode<'q, Sqlite> for Decimal {
+    fn encode_by_ref(&self, args: &mut Vec<SqliteArgumentValue<'q>>) -> IsNull {
+        args.push(SqliteArgumentValue::Text(
+            self.0.normalize().to_string().into(),
+        ));
+        IsNull::No
+    }
+}
+
+/// Numbers are read as SQLite writes them as text, so that numbers not stored as text yet,
+/// such as those of seeds, still decode.
+impl<'r> Decode<'r, Sqlite> for Decimal {
+    fn decode(value: SqliteValueRef<'r>) -> Result<Decimal, BoxDynError> {
+        let text = <&str as Decode<Sqlite>>::decode(value)?;
+        rust_decimal::Decimal::from_str(text)
+            .or_else(|_| rust_decimal::Decimal::from_scientific(text))
+            .map(|d| Decimal(d.normalize()))
+            .map_err(|_| format!("{} is not a decimal", text).into())
+    }
+}
+
+/// Converts `quantity` from one unit to another of the same dimension. Quantities without a
+/// unit, or already in the target unit, are returned as they are.
+pub(super) fn convert(
+    quantity: Decimal,
+    from: Option<&Unit>,
+    to: Option<&Unit>,
+) -> Result<Decimal> {
+    let (from, to) = match (from, to) {
+        (Some(from), Some(to)) if from.id != to.id => (from, to),
+        _ => return Ok(quantity),
+    };
+    match (
+        &from.dimension,
+        from.conversion_factor,
+        &to.dimension,
+        to.conversion_factor,
+    ) {
+        (Some(from_dimension), Some(from_factor), Some(to_dimension), Some(to_factor))
+            if from_dimension == to_dimension && to_factor.is_positive() =>
+        {
+            (quantity * from_factor)
+                .checked_div(to_factor)
+                .ok_or_else(|| DomainError::validation("division by zero"))
+        }
+        _ => Err(DomainError::validation(format!(
+            "cannot convert {} to {}",
+            from.label, to.label
+        ))),
+    }
+}
+
+/// All units by id, to convert quantities without a query per conversion.
+pub(super) async fn units_by_id<'c>(
+    executor: impl SqliteExecutor<'c>,
+) -> sqlx::Result<HashMap<String, Unit>> {
+    let units = sqlx::query_as!(
+        Unit,
+        r#"
+        SELECT id, label, symbol, dimension,
+            conversion_factor AS "conversion_factor: Decimal",
+            inserted_at AS "inserted_at: DateTime"
+        FROM units
+        "#
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(units.into_iter().map(|u| (u.id.clone(), u)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(id: &str, dimension: &str, conversion_factor: &str) -> Unit {
+        Unit {
+            id: id.to_string(),
+            label: id.to_string(),
+            dimension: Some(dimension.to_string()),
+            conversion_factor: Some(decimal(conversion_factor)),
+            ..Default::default()
+        }
+    }
+
+    fn decimal(value: &str) -> Decimal {
+        Decimal(rust_decimal::Decimal::from_str(value).unwrap())
+    }
+
+    #[test]
+    fn quantities_convert_between_units_of_a_dimension() {
+        let hour = unit("hour", "time", "3600");
+        let minute = unit("minute", "time", "60");
+        let converted = convert(decimal("1.5"), Some(&hour), Some(&minute)).unwrap();
+        assert_eq!(converted, Decimal::from(90));
+        let converted = convert(Decimal::from(90), Some(&minute), Some(&hour)).unwrap();
+        assert_eq!(converted, decimal("1.5"));
+    }
+
+    #[test]
+    fn conversions_are_exact() {
+        let gram = unit("gram", "mass", "0.001");
+        let kilogram = unit("kilogram", "mass", "1");
+        let converted = convert(decimal("0.3"), Some(&kilogram), Some(&gram)).unwrap();
+        assert_eq!(converted, Decimal::from(300));
+    }
+
+    #[test]
+    fn quantities_without_a_unit_are_kept() {
+        let hour = unit("hour", "time", "3600");
+        let converted = convert(Decimal::from(2), None, Some(&hour)).unwrap();
+        assert_eq!(converted, Decimal::from(2));
+    }
+
+    #[test]
+    fn units_of_other_dimensions_do_not_convert() {
+        let hour = unit("hour", "time", "3600");
+        let gram = unit("gram", "mass", "0.001");
+        assert!(convert(Decimal::from(2), Some(&hour), Some(&gram)).is_err());
+    }
+
+    #[test]
+    fn a_zero_conversion_factor_does_not_convert() {
+        let hour = unit("hour", "time", "3600");
+        let broken = unit("broken", "time", "0");
+        assert!(convert(Decimal::from(2), Some(&hour), Some(&broken)).is_err());
+    }
+
+    #[test]
+    fn dividing_by_zero_gives_nothing() {
+        assert_eq!(Decimal::ONE.checked_div(Decimal::ZERO), None);
+    }
+}
@@ -1,20 +1,70 @@
-use async_graphql::{EmptySubscription, Enum, Schema, SimpleObject};
-use sqlx::{sqlite::SqliteRow, FromRow, Row};
+use async_graphql::{Enum, Json, Schema, SimpleObject, Union};
+use measure::Decimal;
+use sqlx::{
+    sqlite::{SqlitePool, SqliteRow},
+    FromRow, Row,
+};
+use std::collections::HashMap;
 use std::default::Default;
+use time::DateTime;
+mod audit;
+mod auth;
+mod contribution;
+mod error;
+mod filter;
+mod loader;
+mod location;
+mod measure;
 pub mod mutation;
+mod pagination;
+mod permission;
+mod planning;
+mod provenance;
 pub mod query;
+mod relations;
+mod search;
+pub mod subscription;
+mod time;
+mod timeline;
+mod validation;
+pub use auth::{authenticate, bootstrap_password, AuthenticationError, CurrentAgent};
+pub use loader::SqliteLoader;
 pub use mutation::MutationRoot;
 pub use query::QueryRoot;
+pub use subscription::{ChangeBus, SubscriptionRoot};
 
-pub type VfSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+pub type VfSchema = Schema<QueryRoot, MutationRoot, SubscriptionRoot>;
+
+/// A migrated in memory database holding `fixture`, for tests. Each connection to an in
+/// memory database gets a database of its own, so the pool keeps a single one.
+#[cfg(test)]
+pub async fn test_pool(fixture: &str) -> SqlitePool {
+    use sqlx::{sqlite::SqlitePoolOptions, Executor};
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .expect("failed to open an in memory database");
+    sqlx::migrate!()
+        .run(&pool)
+        .await
+        .expect("failed to run migrations");
+    pool.execute(fixture)
+        .await
+        .expect("failed to load the fixture");
+    pool
+}
 
 #[derive(Clone, SimpleObject, Default)]
+#[graphql(complex)]
 struct Plan {
     id: String,
     title: String,
     description: Option<String>,
-    processes: Vec<Process>,
-    inserted_at: String,
+    inserted_at: DateTime,
+    /// When the plan was deleted. Deleted plans can be restored.
+    deleted_at: Option<DateTime>,
 }
 
 impl Plan {
@@ -24,7 +74,7 @@ impl Plan {
             title: row.get("title"),
             description: row.get("description"),
             inserted_at: row.get("inserted_at"),
-            ..Default::default()
+            deleted_at: row.get("deleted_at"),
         }
     }
 }
@@ -42,13 +92,17 @@ impl Default for AgentType {
 }
 
 #[derive(Clone, SimpleObject, FromRow, Debug, Default)]
+#[graphql(complex)]
 struct Agent {
     id: String,
     name: String,
     unique_name: String,
     email: Option<String>,
     agent_type: AgentType,
-    inserted_at: i64,
+    primary_location_id: Option<String>,
+    inserted_at: DateTime,
+    /// When the agent was deleted. Deleted agents can't log in.
+    deleted_at: Option<DateTime>,
 }
 
 impl Agent {
@@ -58,11 +112,39 @@ impl Agent {
             name: row.get("name"),
             unique_name: row.get("unique_name"),
             agent_type: row.get("agent_type"),
+            primary_location_id: row.get("primary_location_id"),
+            deleted_at: row.get("deleted_at"),
             ..Default::default()
         }
     }
 }
 
+/// A token an agent can make requests with. Only its hash is stored.
+#[derive(Clone, SimpleObject, FromRow, Debug, Default)]
+struct ApiToken {
+    id: String,
+    name: Option<String>,
+    /// When the token starts being refused. API tokens never expire.
+    expires_at: Option<DateTime>,
+    last_used_at: Option<DateTime>,
+    inserted_at: DateTime,
+}
+
+/// A token returned once, when it is created. It goes in the `Authorization: Bearer` header
+/// of requests, or in the `Authorization` field of the websocket connection payload.
+#[derive(Clone, SimpleObject)]
+struct NewApiToken {
+    token: String,
+    api_token: ApiToken,
+}
+
+#[derive(Clone, SimpleObject)]
+struct Session {
+    token: String,
+    expires_at: DateTime,
+    agent: Agent,
+}
+
 #[derive(Clone, SimpleObject, FromRow, Debug, Default)]
 struct AgentRelationship {
     id: String,
@@ -72,7 +154,7 @@ struct AgentRelationship {
     object: Agent,
     agent_relation_type_id: String,
     agent_relation_type: String,
-    inserted_at: String,
+    inserted_at: DateTime,
 }
 
 impl AgentRelationship {
@@ -93,7 +175,7 @@ struct Label {
     name: String,
     unique_name: String,
     color: Option<String>,
-    inserted_at: i64,
+    inserted_at: DateTime,
 }
 
 impl Label {
@@ -108,18 +190,20 @@ impl Label {
 }
 
 #[derive(Clone, SimpleObject, Default, Debug)]
+#[graphql(complex)]
 struct Process {
     id: String,
     title: String,
     description: Option<String>,
-    labels: Vec<Label>,
-    agents: Vec<Agent>,
-    inserted_at: String,
-    start_at: String,
-    due_at: String,
+    inserted_at: DateTime,
+    start_at: Option<DateTime>,
+    due_at: Option<DateTime>,
     plan_id: String,
     agent_id: String,
-    commitments: Vec<Commitment>,
+    based_on_id: Option<String>,
+    at_location_id: Option<String>,
+    /// When the process was deleted.
+    deleted_at: Option<DateTime>,
 }
 
 impl Process {
@@ -130,7 +214,11 @@ impl Process {
             description: row.get("description"),
             inserted_at: row.get("inserted_at"),
             start_at: row.get("start_at"),
+            due_at: row.get("due_at"),
             plan_id: row.get("plan_id"),
+            based_on_id: row.get("based_on_id"),
+            at_location_id: row.get("at_location_id"),
+            deleted_at: row.get("deleted_at"),
             ..Default::default()
         }
     }
@@ -151,8 +239,8 @@ impl Default for InputOutput {
 struct Action {
     id: String,
     name: String,
-    input_output: InputOutput,
-    inserted_at: String,
+    input_output: Option<InputOutput>,
+    inserted_at: DateTime,
 }
 
 impl Action {
@@ -164,13 +252,56 @@ impl Action {
             inserted_at: row.get("inserted_at"),
         }
     }
+
+    /// Whether flows of the action are measured in effort, the time worked, rather than in
+    /// resources.
+    fn is_effort(&self) -> bool {
+        self.name == "work"
+    }
+}
+
+/// A place, with coordinates in decimal degrees and altitude in meters.
+#[derive(Clone, SimpleObject, Debug, Default)]
+struct Location {
+    id: String,
+    name: String,
+    mappable_address: Option<String>,
+    lat: Option<f64>,
+    long: Option<f64>,
+    alt: Option<f64>,
+    note: Option<String>,
+    inserted_at: DateTime,
+}
+
+impl Location {
+    fn from_row(row: SqliteRow) -> Self {
+        Location {
+            id: row.get("id"),
+            name: row.get("name"),
+            mappable_address: row.get("mappable_address"),
+            lat: row.get("lat"),
+            long: row.get("long"),
+            alt: row.get("alt"),
+            note: row.get("note"),
+            inserted_at: row.get("inserted_at"),
+        }
+    }
+}
+
+#[derive(Clone, SimpleObject, Debug)]
+struct NearbyLocation {
+    location: Location,
+    distance_km: f64,
 }
 
 #[derive(Clone, SimpleObject, Debug, Default, FromRow)]
 struct Unit {
     id: String,
     label: String,
-    inserted_at: i64,
+    symbol: Option<String>,
+    dimension: Option<String>,
+    conversion_factor: Option<Decimal>,
+    inserted_at: DateTime,
 }
 
 impl Unit {
@@ -178,6 +309,9 @@ impl Unit {
         Unit {
             id: row.get("id"),
             label: row.get("label"),
+            symbol: row.get("symbol"),
+            dimension: row.get("dimension"),
+            conversion_factor: row.get("conversion_factor"),
             inserted_at: row.get("inserted_at"),
         }
     }
@@ -188,7 +322,7 @@ struct ResourceSpecification {
     id: String,
     name: String,
     unique_name: String,
-    inserted_at: i64,
+    inserted_at: DateTime,
 }
 
 impl ResourceSpecification {
@@ -203,20 +337,25 @@ impl ResourceSpecification {
 }
 
 #[derive(Clone, SimpleObject, Debug, Default, FromRow)]
+#[graphql(complex)]
 struct Commitment {
     id: String,
     description: String,
-    process_id: String,
+    process_id: Option<String>,
     action_id: String,
-    action: Option<Action>,
     assigned_agent_id: Option<String>,
-    assigned_agent: Option<Agent>,
-    quantity: i32,
-    unit_id: String,
-    unit: Option<Unit>,
-    resource_specification_id: String,
-    resource_specification: Option<ResourceSpecification>,
-    inserted_at: String,
+    provider_id: Option<String>,
+    receiver_id: Option<String>,
+    clause_of_id: Option<String>,
+    quantity: Decimal,
+    unit_id: Option<String>,
+    resource_specification_id: Option<String>,
+    finished: bool,
+    due_at: Option<DateTime>,
+    at_location_id: Option<String>,
+    inserted_at: DateTime,
+    /// When the commitment was deleted.
+    deleted_at: Option<DateTime>,
 }
 
 impl Commitment {
@@ -228,10 +367,1021 @@ impl Commitment {
             process_id: row.get("process_id"),
             action_id: row.get("action_id"),
             assigned_agent_id: row.get("assigned_agent_id"),
+            provider_id: row.get("provider_id"),
+            receiver_id: row.get("receiver_id"),
+            clause_of_id: row.get("clause_of_id"),
             quantity: row.get("quantity"),
             unit_id: row.get("unit_id"),
             resource_specification_id: row.get("resource_specification_id"),
+            finished: row.get("finished"),
+            due_at: row.get("due_at"),
+            at_location_id: row.get("at_location_id"),
+            deleted_at: row.get("deleted_at"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Enum)]
+enum ChangeKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// A commitment as it is after a change, or as it was before being deleted.
+#[derive(Clone, SimpleObject, Debug)]
+struct CommitmentChange {
+    kind: ChangeKind,
+    commitment: Commitment,
+}
+
+#[derive(Clone, SimpleObject, Debug, Default)]
+struct Fulfillment {
+    id: String,
+    fulfilled_by_id: String,
+    fulfills_id: String,
+    resource_quantity: Option<Decimal>,
+    effort_quantity: Option<Decimal>,
+    note: Option<String>,
+    inserted_at: DateTime,
+}
+
+impl Fulfillment {
+    fn from_row(row: SqliteRow) -> Self {
+        Fulfillment {
+            id: row.get("id"),
+            fulfilled_by_id: row.get("fulfilled_by_id"),
+            fulfills_id: row.get("fulfills_id"),
+            resource_quantity: row.get("resource_quantity"),
+            effort_quantity: row.get("effort_quantity"),
+            note: row.get("note"),
+            inserted_at: row.get("inserted_at"),
+        }
+    }
+
+    /// The part of the commitment this fulfillment covers, in effort for work commitments and
+    /// in resources for the others.
+    fn quantity(&self, effort: bool) -> Decimal {
+        if effort {
+            self.effort_quantity.unwrap_or_default()
+        } else {
+            self.resource_quantity.unwrap_or_default()
+        }
+    }
+}
+
+#[derive(Clone, SimpleObject, Debug, Default)]
+#[graphql(complex)]
+struct EconomicEvent {
+    id: String,
+    note: Option<String>,
+    action_id: String,
+    action: Option<Action>,
+    provider_id: String,
+    provider: Option<Agent>,
+    receiver_id: String,
+    receiver: Option<Agent>,
+    resource_quantity: Option<Decimal>,
+    unit_id: Option<String>,
+    unit: Option<Unit>,
+    resource_specification_id: Option<String>,
+    resource_specification: Option<ResourceSpecification>,
+    input_of_id: Option<String>,
+    output_of_id: Option<String>,
+    realization_of_id: Option<String>,
+    resource_inventoried_as_id: Option<String>,
+    resource_inventoried_as: Option<EconomicResource>,
+    to_resource_inventoried_as_id: Option<String>,
+    to_resource_inventoried_as: Option<EconomicResource>,
+    has_point_in_time: Option<DateTime>,
+    has_beginning: Option<DateTime>,
+    has_end: Option<DateTime>,
+    at_location_id: Option<String>,
+    inserted_at: DateTime,
+}
+
+impl EconomicEvent {
+    fn from_row(row: SqliteRow) -> Self {
+        EconomicEvent {
+            id: row.get("id"),
+            note: row.get("note"),
+            action_id: row.get("action_id"),
+            provider_id: row.get("provider_id"),
+            receiver_id: row.get("receiver_id"),
+            resource_quantity: row.get("resource_quantity"),
+            unit_id: row.get("unit_id"),
+            resource_specification_id: row.get("resource_specification_id"),
+            input_of_id: row.get("input_of_id"),
+            output_of_id: row.get("output_of_id"),
+            realization_of_id: row.get("realization_of_id"),
+            resource_inventoried_as_id: row.get("resource_inventoried_as_id"),
+            to_resource_inventoried_as_id: row.get("to_resource_inventoried_as_id"),
+            has_point_in_time: row.get("has_point_in_time"),
+            has_beginning: row.get("has_beginning"),
+            has_end: row.get("has_end"),
+            at_location_id: row.get("at_location_id"),
+            inserted_at: row.get("inserted_at"),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, SimpleObject, Debug, Default)]
+struct Measure {
+    has_numerical_value: Decimal,
+    has_unit: Option<Unit>,
+}
+
+#[derive(Clone, SimpleObject, Debug, Default)]
+#[graphql(complex)]
+struct EconomicResource {
+    id: String,
+    name: String,
+    note: Option<String>,
+    tracking_identifier: Option<String>,
+    conforms_to_id: String,
+    conforms_to: Option<ResourceSpecification>,
+    primary_accountable_id: Option<String>,
+    primary_accountable: Option<Agent>,
+    current_location_id: Option<String>,
+    unit_id: Option<String>,
+    accounting_quantity: Measure,
+    onhand_quantity: Measure,
+    inserted_at: DateTime,
+}
+
+impl EconomicResource {
+    fn from_row(row: SqliteRow) -> Self {
+        EconomicResource {
+            id: row.get("id"),
+            name: row.get("name"),
+            note: row.get("note"),
+            tracking_identifier: row.get("tracking_identifier"),
+            conforms_to_id: row.get("conforms_to_id"),
+            primary_accountable_id: row.get("primary_accountable_id"),
+            current_location_id: row.get("current_location_id"),
+            unit_id: row.get("unit_id"),
+            accounting_quantity: Measure {
+                has_numerical_value: row.get("accounting_quantity"),
+                has_unit: None,
+            },
+            onhand_quantity: Measure {
+                has_numerical_value: row.get("onhand_quantity"),
+                has_unit: None,
+            },
+            inserted_at: row.get("inserted_at"),
+            ..Default::default()
+        }
+    }
+
+    fn set_unit(&mut self, unit: Option<Unit>) {
+        self.accounting_quantity.has_unit = unit.clone();
+        self.onhand_quantity.has_unit = unit;
+    }
+}
+
+#[derive(Clone, SimpleObject, Debug, Default)]
+#[graphql(complex)]
+struct Intent {
+    id: String,
+    name: Option<String>,
+    note: Option<String>,
+    action_id: String,
+    action: Option<Action>,
+    provider_id: Option<String>,
+    provider: Option<Agent>,
+    receiver_id: Option<String>,
+    receiver: Option<Agent>,
+    resource_specification_id: Option<String>,
+    resource_specification: Option<ResourceSpecification>,
+    resource_quantity: Option<Decimal>,
+    unit_id: Option<String>,
+    unit: Option<Unit>,
+    due_at: Option<DateTime>,
+    at_location_id: Option<String>,
+    finished: bool,
+    inserted_at: DateTime,
+}
+
+impl Intent {
+    fn from_row(row: SqliteRow) -> Self {
+        Intent {
+            id: row.get("id"),
+            name: row.get("name"),
+            note: row.get("note"),
+            action_id: row.get("action_id"),
+            provider_id: row.get("provider_id"),
+            receiver_id: row.get("receiver_id"),
+            resource_specification_id: row.get("resource_specification_id"),
+            resource_quantity: row.get("resource_quantity"),
+            unit_id: row.get("unit_id"),
+            due_at: row.get("due_at"),
+            at_location_id: row.get("at_location_id"),
+            finished: row.get("finished"),
+            inserted_at: row.get("inserted_at"),
+            ..Default::default()
+        }
+    }
+}
+
+/// Loads the action, agents, resource specification and unit of each intent.
+async fn load_intent_relations(pool: &SqlitePool, intents: &mut [Intent]) -> sqlx::Result<()> {
+    let intent_ids =
+        serde_json::json!(intents.iter().map(|i| &i.id).collect::<Vec<_>>()).to_string();
+    let actions = sqlx::query_as::<_, Action>(
+        "
+        SELECT * FROM actions
+        WHERE id IN (
+            SELECT action_id FROM intents
+            WHERE id IN (SELECT value FROM json_each(?))
+        )",
+    )
+    .bind(&intent_ids)
+    .fetch_all(pool)
+    .await?;
+    let actions_hashmap: HashMap<&String, &Action> =
+        actions.iter().map(|action| (&action.id, action)).collect();
+    let agents = sqlx::query(
+        "
+        SELECT * FROM agents
+        WHERE id IN (
+            SELECT provider_id FROM intents
+            WHERE id IN (SELECT value FROM json_each(?))
+            UNION
+            SELECT receiver_id FROM intents
+            WHERE id IN (SELECT value FROM json_each(?))
+        )",
+    )
+    .bind(&intent_ids)
+    .bind(&intent_ids)
+    .map(Agent::from_row)
+    .fetch_all(pool)
+    .await?;
+    let agents_hashmap: HashMap<&String, &Agent> =
+        agents.iter().map(|agent| (&agent.id, agent)).collect();
+    let resource_specifications = sqlx::query(
+        "
+        SELECT * FROM resource_specifications
+        WHERE id IN (
+            SELECT resource_specification_id FROM intents
+            WHERE id IN (SELECT value FROM json_each(?))
+        )",
+    )
+    .bind(&intent_ids)
+    .map(ResourceSpecification::from_row)
+    .fetch_all(pool)
+    .await?;
+    let resource_specifications_hashmap: HashMap<&String, &ResourceSpecification> =
+        resource_specifications
+            .iter()
+            .map(|resource_specification| (&resource_specification.id, resource_specification))
+            .collect();
+    let units = sqlx::query_as::<_, Unit>(
+        "
+        SELECT * FROM units
+        WHERE id IN (
+            SELECT unit_id FROM intents
+            WHERE id IN (SELECT value FROM json_each(?))
+        )",
+    )
+    .bind(&intent_ids)
+    .fetch_all(pool)
+    .await?;
+    let units_hashmap: HashMap<&String, &Unit> =
+        units.iter().map(|unit| (&unit.id, unit)).collect();
+    intents.iter_mut().for_each(|i| {
+        i.action = actions_hashmap.get(&i.action_id).cloned().cloned();
+        i.provider = i
+            .provider_id
+            .as_ref()
+            .and_then(|id| agents_hashmap.get(id).cloned().cloned());
+        i.receiver = i
+            .receiver_id
+            .as_ref()
+            .and_then(|id| agents_hashmap.get(id).cloned().cloned());
+        i.resource_specification = i
+            .resource_specification_id
+            .as_ref()
+            .and_then(|id| resource_specifications_hashmap.get(id).cloned().cloned());
+        i.unit = i
+            .unit_id
+            .as_ref()
+            .and_then(|id| units_hashmap.get(id).cloned().cloned());
+    });
+    Ok(())
+}
+
+#[derive(Clone, SimpleObject, Debug, Default)]
+struct Proposal {
+    id: String,
+    name: String,
+    note: Option<String>,
+    unit_based: bool,
+    has_beginning: Option<DateTime>,
+    has_end: Option<DateTime>,
+    publishes: Vec<Intent>,
+    reciprocal: Vec<Intent>,
+    proposed_to: Vec<Agent>,
+    inserted_at: DateTime,
+}
+
+impl Proposal {
+    fn from_row(row: SqliteRow) -> Self {
+        Proposal {
+            id: row.get("id"),
+            name: row.get("name"),
+            note: row.get("note"),
+            unit_based: row.get("unit_based"),
+            has_beginning: row.get("has_beginning"),
+            has_end: row.get("has_end"),
+            inserted_at: row.get("inserted_at"),
+            ..Default::default()
+        }
+    }
+}
+
+/// Loads the published and reciprocal intents of each proposal and who it is proposed to.
+async fn load_proposal_relations(
+    pool: &SqlitePool,
+    proposals: &mut [Proposal],
+) -> sqlx::Result<()> {
+    let proposal_ids =
+        serde_json::json!(proposals.iter().map(|p| &p.id).collect::<Vec<_>>()).to_string();
+    let mut proposal_id_intent_tuples = sqlx::query(
+        "
+        SELECT intents.*, proposed_intents.proposal_id, proposed_intents.reciprocal
+        FROM intents
+        JOIN proposed_intents
+        ON proposed_intents.intent_id = intents.id
+        WHERE proposed_intents.proposal_id IN (SELECT value FROM json_each(?))
+        ORDER BY proposed_intents.id
+        ",
+    )
+    .bind(&proposal_ids)
+    .map(|row| {
+        (
+            row.get("proposal_id"),
+            row.get("reciprocal"),
+            Intent::from_row(row),
+        )
+    })
+    .fetch_all(pool)
+    .await?;
+    let mut intents = proposal_id_intent_tuples
+        .iter()
+        .map(|(_, _, intent)| intent.clone())
+        .collect::<Vec<_>>();
+    load_intent_relations(pool, &mut intents).await?;
+    proposal_id_intent_tuples
+        .iter_mut()
+        .zip(intents)
+        .for_each(|((_, _, intent), loaded_intent)| *intent = loaded_intent);
+    let proposal_id_agent_tuples = sqlx::query(
+        "
+        SELECT agents.*, proposed_to.proposal_id
+        FROM agents
+        JOIN proposed_to
+        ON proposed_to.agent_id = agents.id
+        WHERE proposed_to.proposal_id IN (SELECT value FROM json_each(?))
+        AND agents.deleted_at IS NULL
+        ",
+    )
+    .bind(&proposal_ids)
+    .map(|row| (row.get("proposal_id"), Agent::from_row(row)))
+    .fetch_all(pool)
+    .await?;
+    proposals.iter_mut().for_each(|p| {
+        let (reciprocal, publishes): (Vec<_>, Vec<_>) = proposal_id_intent_tuples
+            .iter()
+            .filter(|(proposal_id, _, _): &&(String, bool, Intent)| proposal_id == &p.id)
+            .partition(|(_, reciprocal, _)| *reciprocal);
+        p.publishes = publishes.into_iter().map(|(_, _, i)| i.clone()).collect();
+        p.reciprocal = reciprocal.into_iter().map(|(_, _, i)| i.clone()).collect();
+        p.proposed_to = proposal_id_agent_tuples
+            .iter()
+            .filter(|(proposal_id, _): &&(String, Agent)| proposal_id == &p.id)
+            .map(|(_, agent)| agent.clone())
+            .collect();
+    });
+    Ok(())
+}
+
+#[derive(Clone, SimpleObject, Debug, Default)]
+struct Satisfaction {
+    id: String,
+    satisfies_id: String,
+    satisfied_by_id: String,
+    resource_quantity: Option<Decimal>,
+    effort_quantity: Option<Decimal>,
+    note: Option<String>,
+    inserted_at: DateTime,
+}
+
+impl Satisfaction {
+    fn from_row(row: SqliteRow) -> Self {
+        Satisfaction {
+            id: row.get("id"),
+            satisfies_id: row.get("satisfies_id"),
+            satisfied_by_id: row.get("satisfied_by_id"),
+            resource_quantity: row.get("resource_quantity"),
+            effort_quantity: row.get("effort_quantity"),
+            note: row.get("note"),
+            inserted_at: row.get("inserted_at"),
+        }
+    }
+}
+
+#[derive(Clone, SimpleObject, Debug, Default)]
+struct Agreement {
+    id: String,
+    name: String,
+    note: Option<String>,
+    clauses: Vec<Commitment>,
+    realizations: Vec<EconomicEvent>,
+    inserted_at: DateTime,
+}
+
+impl Agreement {
+    fn from_row(row: SqliteRow) -> Self {
+        Agreement {
+            id: row.get("id"),
+            name: row.get("name"),
+            note: row.get("note"),
+            inserted_at: row.get("inserted_at"),
             ..Default::default()
         }
     }
 }
+
+/// Loads the clauses and realizations of each agreement.
+async fn load_agreement_relations(
+    pool: &SqlitePool,
+    agreements: &mut [Agreement],
+) -> sqlx::Result<()> {
+    let agreement_ids =
+        serde_json::json!(agreements.iter().map(|a| &a.id).collect::<Vec<_>>()).to_string();
+    let clauses = sqlx::query(
+        "
+        SELECT * FROM commitments
+        WHERE clause_of_id IN (SELECT value FROM json_each(?)) AND deleted_at IS NULL
+        ORDER BY inserted_at
+        ",
+    )
+    .bind(&agreement_ids)
+    .map(Commitment::from_row)
+    .fetch_all(pool)
+    .await?;
+    let realizations = sqlx::query(
+        "
+        SELECT * FROM economic_events
+        WHERE realization_of_id IN (SELECT value FROM json_each(?))
+        ORDER BY inserted_at
+        ",
+    )
+    .bind(&agreement_ids)
+    .map(EconomicEvent::from_row)
+    .fetch_all(pool)
+    .await?;
+    agreements.iter_mut().for_each(|a| {
+        a.clauses = clauses
+            .iter()
+            .filter(|c| c.clause_of_id.as_ref() == Some(&a.id))
+            .cloned()
+            .collect();
+        a.realizations = realizations
+            .iter()
+            .filter(|e| e.realization_of_id.as_ref() == Some(&a.id))
+            .cloned()
+            .collect();
+    });
+    Ok(())
+}
+
+#[derive(Clone, SimpleObject, Debug, Default)]
+struct ProcessSpecification {
+    id: String,
+    name: String,
+    note: Option<String>,
+    inserted_at: DateTime,
+}
+
+impl ProcessSpecification {
+    fn from_row(row: SqliteRow) -> Self {
+        ProcessSpecification {
+            id: row.get("id"),
+            name: row.get("name"),
+            note: row.get("note"),
+            inserted_at: row.get("inserted_at"),
+        }
+    }
+}
+
+#[derive(Clone, SimpleObject, Debug, Default)]
+struct RecipeResource {
+    id: String,
+    name: String,
+    note: Option<String>,
+    resource_specification_id: Option<String>,
+    unit_id: Option<String>,
+    inserted_at: DateTime,
+}
+
+impl RecipeResource {
+    fn from_row(row: SqliteRow) -> Self {
+        RecipeResource {
+            id: row.get("id"),
+            name: row.get("name"),
+            note: row.get("note"),
+            resource_specification_id: row.get("resource_specification_id"),
+            unit_id: row.get("unit_id"),
+            inserted_at: row.get("inserted_at"),
+        }
+    }
+}
+
+#[derive(Clone, SimpleObject, Debug, Default)]
+struct RecipeFlow {
+    id: String,
+    note: Option<String>,
+    action_id: String,
+    action: Option<Action>,
+    recipe_flow_resource_id: String,
+    recipe_flow_resource: Option<RecipeResource>,
+    resource_quantity: Option<Decimal>,
+    unit_id: Option<String>,
+    recipe_input_of_id: Option<String>,
+    recipe_output_of_id: Option<String>,
+    recipe_clause_of_id: Option<String>,
+    inserted_at: DateTime,
+}
+
+impl RecipeFlow {
+    fn from_row(row: SqliteRow) -> Self {
+        RecipeFlow {
+            id: row.get("id"),
+            note: row.get("note"),
+            action_id: row.get("action_id"),
+            recipe_flow_resource_id: row.get("recipe_flow_resource_id"),
+            resource_quantity: row.get("resource_quantity"),
+            unit_id: row.get("unit_id"),
+            recipe_input_of_id: row.get("recipe_input_of_id"),
+            recipe_output_of_id: row.get("recipe_output_of_id"),
+            recipe_clause_of_id: row.get("recipe_clause_of_id"),
+            inserted_at: row.get("inserted_at"),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, SimpleObject, Debug, Default)]
+struct RecipeProcess {
+    id: String,
+    recipe_id: String,
+    name: String,
+    note: Option<String>,
+    process_specification_id: Option<String>,
+    process_specification: Option<ProcessSpecification>,
+    has_duration_seconds: Option<i32>,
+    inputs: Vec<RecipeFlow>,
+    outputs: Vec<RecipeFlow>,
+    inserted_at: DateTime,
+}
+
+impl RecipeProcess {
+    fn from_row(row: SqliteRow) -> Self {
+        RecipeProcess {
+            id: row.get("id"),
+            recipe_id: row.get("recipe_id"),
+            name: row.get("name"),
+            note: row.get("note"),
+            process_specification_id: row.get("process_specification_id"),
+            has_duration_seconds: row.get("has_duration_seconds"),
+            inserted_at: row.get("inserted_at"),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, SimpleObject, Debug, Default)]
+struct RecipeExchange {
+    id: String,
+    recipe_id: String,
+    name: String,
+    note: Option<String>,
+    clauses: Vec<RecipeFlow>,
+    inserted_at: DateTime,
+}
+
+impl RecipeExchange {
+    fn from_row(row: SqliteRow) -> Self {
+        RecipeExchange {
+            id: row.get("id"),
+            recipe_id: row.get("recipe_id"),
+            name: row.get("name"),
+            note: row.get("note"),
+            inserted_at: row.get("inserted_at"),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, SimpleObject, Debug, Default)]
+struct Recipe {
+    id: String,
+    name: String,
+    note: Option<String>,
+    primary_output_id: String,
+    primary_output: Option<RecipeResource>,
+    processes: Vec<RecipeProcess>,
+    exchanges: Vec<RecipeExchange>,
+    inserted_at: DateTime,
+}
+
+impl Recipe {
+    fn from_row(row: SqliteRow) -> Self {
+        Recipe {
+            id: row.get("id"),
+            name: row.get("name"),
+            note: row.get("note"),
+            primary_output_id: row.get("primary_output_id"),
+            inserted_at: row.get("inserted_at"),
+            ..Default::default()
+        }
+    }
+}
+
+/// Loads the primary output, processes, exchanges and flows of each recipe.
+async fn load_recipe_relations(pool: &SqlitePool, recipes: &mut [Recipe]) -> sqlx::Result<()> {
+    let recipe_ids =
+        serde_json::json!(recipes.iter().map(|r| &r.id).collect::<Vec<_>>()).to_string();
+    let mut processes = sqlx::query(
+        "
+        SELECT * FROM recipe_processes
+        WHERE recipe_id IN (SELECT value FROM json_each(?))
+        ORDER BY inserted_at, id
+        ",
+    )
+    .bind(&recipe_ids)
+    .map(RecipeProcess::from_row)
+    .fetch_all(pool)
+    .await?;
+    let mut exchanges = sqlx::query(
+        "
+        SELECT * FROM recipe_exchanges
+        WHERE recipe_id IN (SELECT value FROM json_each(?))
+        ORDER BY inserted_at, id
+        ",
+    )
+    .bind(&recipe_ids)
+    .map(RecipeExchange::from_row)
+    .fetch_all(pool)
+    .await?;
+    let process_specifications = sqlx::query(
+        "
+        SELECT * FROM process_specifications
+        WHERE id IN (
+            SELECT process_specification_id FROM recipe_processes
+            WHERE recipe_id IN (SELECT value FROM json_each(?))
+        )
+        ",
+    )
+    .bind(&recipe_ids)
+    .map(|row| {
+        let process_specification = ProcessSpecification::from_row(row);
+        (process_specification.id.clone(), process_specification)
+    })
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .collect::<HashMap<String, ProcessSpecification>>();
+    let mut flows = sqlx::query(
+        "
+        SELECT * FROM recipe_flows
+        WHERE recipe_input_of_id IN (
+            SELECT id FROM recipe_processes
+            WHERE recipe_id IN (SELECT value FROM json_each(?1))
+        )
+        OR recipe_output_of_id IN (
+            SELECT id FROM recipe_processes
+            WHERE recipe_id IN (SELECT value FROM json_each(?1))
+        )
+        OR recipe_clause_of_id IN (
+            SELECT id FROM recipe_exchanges
+            WHERE recipe_id IN (SELECT value FROM json_each(?1))
+        )
+        ORDER BY inserted_at, id
+        ",
+    )
+    .bind(&recipe_ids)
+    .map(RecipeFlow::from_row)
+    .fetch_all(pool)
+    .await?;
+    let actions = sqlx::query("SELECT * FROM actions")
+        .map(|row| {
+            let action = Action::from_row(row);
+            (action.id.clone(), action)
+        })
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect::<HashMap<String, Action>>();
+    let resource_ids = serde_json::json!(flows
+        .iter()
+        .map(|f| &f.recipe_flow_resource_id)
+        .chain(recipes.iter().map(|r| &r.primary_output_id))
+        .collect::<Vec<_>>())
+    .to_string();
+    let resources = sqlx::query(
+        "
+        SELECT * FROM recipe_resources
+        WHERE id IN (SELECT value FROM json_each(?))
+        ",
+    )
+    .bind(&resource_ids)
+    .map(|row| {
+        let resource = RecipeResource::from_row(row);
+        (resource.id.clone(), resource)
+    })
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .collect::<HashMap<String, RecipeResource>>();
+    flows.iter_mut().for_each(|f| {
+        f.action = actions.get(&f.action_id).cloned();
+        f.recipe_flow_resource = resources.get(&f.recipe_flow_resource_id).cloned();
+    });
+    processes.iter_mut().for_each(|p| {
+        p.process_specification = p
+            .process_specification_id
+            .as_ref()
+            .and_then(|id| process_specifications.get(id))
+            .cloned();
+        p.inputs = flows
+            .iter()
+            .filter(|f| f.recipe_input_of_id.as_ref() == Some(&p.id))
+            .cloned()
+            .collect();
+        p.outputs = flows
+            .iter()
+            .filter(|f| f.recipe_output_of_id.as_ref() == Some(&p.id))
+            .cloned()
+            .collect();
+    });
+    exchanges.iter_mut().for_each(|e| {
+        e.clauses = flows
+            .iter()
+            .filter(|f| f.recipe_clause_of_id.as_ref() == Some(&e.id))
+            .cloned()
+            .collect();
+    });
+    recipes.iter_mut().for_each(|r| {
+        r.primary_output = resources.get(&r.primary_output_id).cloned();
+        r.processes = processes
+            .iter()
+            .filter(|p| p.recipe_id == r.id)
+            .cloned()
+            .collect();
+        r.exchanges = exchanges
+            .iter()
+            .filter(|e| e.recipe_id == r.id)
+            .cloned()
+            .collect();
+    });
+    Ok(())
+}
+
+/// Gross and net quantity of a resource specification required by a demand explosion.
+/// Requirements without a recipe and with a positive net quantity have to be sourced.
+#[derive(Clone, SimpleObject, Debug, Default)]
+struct Requirement {
+    resource_specification_id: String,
+    quantity: Decimal,
+    on_hand_quantity: Decimal,
+    net_quantity: Decimal,
+    due_at: DateTime,
+    recipe_id: Option<String>,
+}
+
+#[derive(Clone, SimpleObject, Default)]
+struct DemandExplosion {
+    plan: Option<Plan>,
+    requirements: Vec<Requirement>,
+}
+
+/// A step of a provenance chain walked by `trace` and `track`.
+#[derive(Clone, Union)]
+#[allow(clippy::large_enum_variant)]
+enum ProvenanceNode {
+    EconomicEvent(EconomicEvent),
+    EconomicResource(EconomicResource),
+    Process(Process),
+}
+
+/// A process of a plan scheduled after the processes it depends on, the ones producing what it
+/// takes as input.
+#[derive(Clone, SimpleObject)]
+struct TimelineProcess {
+    process: Process,
+    /// The processes that have to be done before this one starts.
+    depends_on_ids: Vec<String>,
+    duration_seconds: i64,
+    /// When the process can start at the earliest. Empty, like the other dates, when no process
+    /// of the plan has a date.
+    earliest_start_at: Option<DateTime>,
+    earliest_due_at: Option<DateTime>,
+    /// When the process has to start at the latest not to delay the end of the plan.
+    latest_start_at: Option<DateTime>,
+    latest_due_at: Option<DateTime>,
+    /// How long the process can be delayed without delaying the end of the plan.
+    slack_seconds: i64,
+    /// Whether the process is on the critical path, any delay of it delaying the plan.
+    critical: bool,
+}
+
+/// Kinds of records found by `search`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Enum)]
+enum SearchType {
+    Agent,
+    Plan,
+    Process,
+    Commitment,
+}
+
+#[derive(Clone, Union)]
+#[allow(clippy::large_enum_variant)]
+enum SearchNode {
+    Agent(Agent),
+    Plan(Plan),
+    Process(Process),
+    Commitment(Commitment),
+}
+
+#[derive(Clone, SimpleObject)]
+struct SearchResult {
+    node: SearchNode,
+    /// Matching text around the terms, which are wrapped in `<mark>` tags.
+    snippet: String,
+}
+
+/// The kinds of records mutations change.
+#[derive(sqlx::Type, Clone, Copy, Debug, Eq, PartialEq, Enum)]
+enum AuditEntity {
+    Agent,
+    Label,
+    Location,
+    Unit,
+    Plan,
+    Process,
+    ResourceSpecification,
+    Commitment,
+    EconomicResource,
+    EconomicEvent,
+    Fulfillment,
+    Intent,
+    Proposal,
+    Satisfaction,
+    Agreement,
+    ProcessSpecification,
+    RecipeResource,
+    Recipe,
+    RecipeProcess,
+    RecipeExchange,
+    RecipeFlow,
+    ValueEquation,
+    AgentRelation,
+    AccessToken,
+}
+
+/// A change a mutation made to a record.
+#[derive(Clone, SimpleObject)]
+#[graphql(complex)]
+struct AuditEntry {
+    id: i64,
+    /// The agent who made the change.
+    agent_id: Option<String>,
+    /// The name of the mutation, such as `updateCommitment`.
+    mutation: String,
+    entity_type: AuditEntity,
+    entity_id: String,
+    /// The columns of the record before the change, missing when it created the record.
+    before: Option<Json<serde_json::Value>>,
+    /// The columns of the record after the change, missing when it deleted the record.
+    after: Option<Json<serde_json::Value>>,
+    inserted_at: DateTime,
+}
+
+impl AuditEntry {
+    fn from_row(row: SqliteRow) -> Self {
+        let json = |column| {
+            row.get::<Option<String>, _>(column)
+                .and_then(|text| serde_json::from_str(&text).ok())
+                .map(Json)
+        };
+        AuditEntry {
+            id: row.get("id"),
+            agent_id: row.get("agent_id"),
+            mutation: row.get("mutation"),
+            entity_type: row.get("entity_type"),
+            entity_id: row.get("entity_id"),
+            before: json("before"),
+            after: json("after"),
+            inserted_at: row.get("inserted_at"),
+        }
+    }
+}
+
+#[derive(Clone, SimpleObject, Debug, Default)]
+struct ValueEquationWeight {
+    id: String,
+    value_equation_id: String,
+    action_id: Option<String>,
+    resource_specification_id: Option<String>,
+    agent_relation_type_id: Option<String>,
+    weight: Decimal,
+    inserted_at: DateTime,
+}
+
+impl ValueEquationWeight {
+    fn from_row(row: SqliteRow) -> Self {
+        ValueEquationWeight {
+            id: row.get("id"),
+            value_equation_id: row.get("value_equation_id"),
+            action_id: row.get("action_id"),
+            resource_specification_id: row.get("resource_specification_id"),
+            agent_relation_type_id: row.get("agent_relation_type_id"),
+            weight: row.get("weight"),
+            inserted_at: row.get("inserted_at"),
+        }
+    }
+}
+
+/// How contributions are valued when distributing income. Only events whose action is
+/// weighted count; resource specification and agent role weights default to 1.
+#[derive(Clone, SimpleObject, Debug, Default)]
+struct ValueEquation {
+    id: String,
+    name: String,
+    note: Option<String>,
+    context_agent_id: Option<String>,
+    weights: Vec<ValueEquationWeight>,
+    inserted_at: DateTime,
+}
+
+impl ValueEquation {
+    fn from_row(row: SqliteRow) -> Self {
+        ValueEquation {
+            id: row.get("id"),
+            name: row.get("name"),
+            note: row.get("note"),
+            context_agent_id: row.get("context_agent_id"),
+            inserted_at: row.get("inserted_at"),
+            ..Default::default()
+        }
+    }
+}
+
+/// Loads the weights of each value equation.
+async fn load_value_equation_relations(
+    pool: &SqlitePool,
+    value_equations: &mut [ValueEquation],
+) -> sqlx::Result<()> {
+    let value_equation_ids =
+        serde_json::json!(value_equations.iter().map(|v| &v.id).collect::<Vec<_>>()).to_string();
+    let weights = sqlx::query(
+        "
+        SELECT * FROM value_equation_weights
+        WHERE value_equation_id IN (SELECT value FROM json_each(?))
+        ORDER BY inserted_at, id
+        ",
+    )
+    .bind(&value_equation_ids)
+    .map(ValueEquationWeight::from_row)
+    .fetch_all(pool)
+    .await?;
+    value_equations.iter_mut().for_each(|v| {
+        v.weights = weights
+            .iter()
+            .filter(|w| w.value_equation_id == v.id)
+            .cloned()
+            .collect();
+    });
+    Ok(())
+}
+
+#[derive(Clone, SimpleObject, Debug, Default)]
+struct IncomeShare {
+    agent_id: String,
+    agent: Option<Agent>,
+    score: Decimal,
+    fraction: Decimal,
+    quantity: Decimal,
+}
+
+#[derive(Clone, SimpleObject, Debug, Default)]
+struct IncomeDistribution {
+    income_event_id: String,
+    value_equation_id: String,
+    plan_id: String,
+    quantity: Decimal,
+    shares: Vec<IncomeShare>,
+}
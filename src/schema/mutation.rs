@@ -1,12 +1,29 @@
+use super::audit;
+use super::auth::{self, LoggedIn, TokenKind};
+use super::error::{DomainError, Result};
+use super::measure::{self, Decimal};
+use super::permission::{AllOf, AnyOf, CanEdit, IfGiven};
+use super::subscription::{Change, ChangeBus};
+use super::time::DateTime;
+use super::validation::{validate, validate_in, Validate, Validator};
 use super::{
-    Action, Agent, AgentType, Commitment, Label, Plan, Process, ResourceSpecification, Unit,
+    load_agreement_relations, load_intent_relations, load_proposal_relations,
+    load_recipe_relations, load_value_equation_relations, planning, Action, Agent, AgentType,
+    Agreement, AuditEntity, ChangeKind, Commitment, DemandExplosion, EconomicEvent,
+    EconomicResource, Fulfillment, Intent, Label, Location, NewApiToken, Plan, Process,
+    ProcessSpecification, Proposal, Recipe, RecipeExchange, RecipeFlow, RecipeProcess,
+    RecipeResource, Requirement, ResourceSpecification, Satisfaction, Session, Unit, ValueEquation,
 };
-use async_graphql::{Context, InputObject, Object, Result};
-use futures::future::join_all;
-use sqlx::sqlite::SqlitePool;
+use async_graphql::async_trait::async_trait;
+use async_graphql::{Context, Guard, GuardExt, InputObject, Object};
+use sqlx::{
+    sqlite::{Sqlite, SqlitePool, SqliteRow},
+    Row, Transaction,
+};
+use std::collections::{HashMap, HashSet};
 use ulid::Ulid;
 
-fn unique_name(name: &str) -> String {
+pub(super) fn unique_name(name: &str) -> String {
     name.to_string().to_lowercase().replace(" ", "_")
 }
 
@@ -15,6 +32,23 @@ struct NewAgent {
     name: String,
     email: Option<String>,
     agent_type: AgentType,
+    primary_location_id: Option<String>,
+    /// Lets the agent log in, until it sets its own.
+    password: Option<String>,
+}
+
+#[async_trait]
+impl Validate for NewAgent {
+    async fn validate(&self, validator: &mut Validator<'_>) -> Result<()> {
+        validator.email("email", self.email.as_deref());
+        validator
+            .exists(
+                "primaryLocationId",
+                "locations",
+                self.primary_location_id.as_deref(),
+            )
+            .await
+    }
 }
 
 #[derive(InputObject, Debug)]
@@ -24,6 +58,15 @@ struct NewPlan {
     description: Option<String>,
 }
 
+#[async_trait]
+impl Validate for NewPlan {
+    async fn validate(&self, validator: &mut Validator<'_>) -> Result<()> {
+        validator
+            .exists("agentId", "agents", Some(&self.agent_id))
+            .await
+    }
+}
+
 #[derive(InputObject, Debug)]
 struct UpdatePlan {
     id: String,
@@ -37,15 +80,100 @@ struct NewLabel {
     color: String,
 }
 
+#[async_trait]
+impl Validate for NewLabel {
+    async fn validate(&self, validator: &mut Validator<'_>) -> Result<()> {
+        validator.color("color", &self.color);
+        Ok(())
+    }
+}
+
+#[derive(InputObject, Debug)]
+struct NewUnit {
+    label: String,
+    symbol: Option<String>,
+    dimension: Option<String>,
+    conversion_factor: Option<Decimal>,
+}
+
+#[async_trait]
+impl Validate for NewUnit {
+    async fn validate(&self, validator: &mut Validator<'_>) -> Result<()> {
+        if self.conversion_factor.is_some() && self.dimension.is_none() {
+            validator.invalid("conversionFactor", "needs a dimension");
+        }
+        validator.positive("conversionFactor", self.conversion_factor);
+        Ok(())
+    }
+}
+
+#[derive(InputObject, Debug)]
+struct NewLocation {
+    name: String,
+    mappable_address: Option<String>,
+    lat: Option<f64>,
+    long: Option<f64>,
+    alt: Option<f64>,
+    note: Option<String>,
+}
+
+#[async_trait]
+impl Validate for NewLocation {
+    async fn validate(&self, validator: &mut Validator<'_>) -> Result<()> {
+        match (self.lat, self.long) {
+            (Some(lat), Some(long)) => {
+                if !(-90.0..=90.0).contains(&lat) {
+                    validator.invalid("lat", "must be within ±90");
+                }
+                if !(-180.0..=180.0).contains(&long) {
+                    validator.invalid("long", "must be within ±180");
+                }
+            }
+            (None, None) => {}
+            (None, Some(_)) => validator.invalid("lat", "must be given with long"),
+            (Some(_), None) => validator.invalid("long", "must be given with lat"),
+        }
+        Ok(())
+    }
+}
+
 #[derive(InputObject, Debug)]
 struct NewProcess {
     title: String,
     description: Option<String>,
     plan_id: Option<String>,
-    start_date: Option<String>,
-    due_date: Option<String>,
+    start_date: Option<DateTime>,
+    due_date: Option<DateTime>,
     labels: Option<Vec<String>>,
     agents: Option<Vec<String>>,
+    at_location_id: Option<String>,
+}
+
+#[async_trait]
+impl Validate for NewProcess {
+    async fn validate(&self, validator: &mut Validator<'_>) -> Result<()> {
+        validator.period(("startDate", self.start_date), ("dueDate", self.due_date));
+        validator
+            .exists("planId", "plans", self.plan_id.as_deref())
+            .await?;
+        validator
+            .exists("atLocationId", "locations", self.at_location_id.as_deref())
+            .await?;
+        validator
+            .all_exist(
+                "labels",
+                "labels",
+                self.labels.as_deref().unwrap_or_default(),
+            )
+            .await?;
+        validator
+            .all_exist(
+                "agents",
+                "agents",
+                self.agents.as_deref().unwrap_or_default(),
+            )
+            .await
+    }
 }
 
 #[derive(InputObject, Debug)]
@@ -53,8 +181,39 @@ struct UpdateProcess {
     id: String,
     title: String,
     description: Option<String>,
+    /// The labels of the process, left as they are when not given.
     labels: Option<Vec<String>>,
+    /// The agents of the process, left as they are when not given.
     agents: Option<Vec<String>>,
+    at_location_id: Option<String>,
+    /// When the process starts, left as it is when not given.
+    start_at: Option<DateTime>,
+    /// When the process is due, left as it is when not given.
+    due_at: Option<DateTime>,
+}
+
+#[async_trait]
+impl Validate for UpdateProcess {
+    async fn validate(&self, validator: &mut Validator<'_>) -> Result<()> {
+        validator.period(("startAt", self.start_at), ("dueAt", self.due_at));
+        validator
+            .exists("atLocationId", "locations", self.at_location_id.as_deref())
+            .await?;
+        validator
+            .all_exist(
+                "labels",
+                "labels",
+                self.labels.as_deref().unwrap_or_default(),
+            )
+            .await?;
+        validator
+            .all_exist(
+                "agents",
+                "agents",
+                self.agents.as_deref().unwrap_or_default(),
+            )
+            .await
+    }
 }
 
 #[derive(InputObject, Debug)]
@@ -65,13 +224,59 @@ struct NewResourceSpecification {
 #[derive(InputObject, Debug)]
 struct NewCommitment {
     description: String,
-    process_id: String,
+    process_id: Option<String>,
     action_id: String,
     assigned_agent_id: Option<String>,
+    provider_id: Option<String>,
+    receiver_id: Option<String>,
+    clause_of_id: Option<String>,
     resource_specification_id: String,
-    quantity: i32,
+    quantity: Decimal,
     unit_id: String,
-    due_at: Option<String>,
+    due_at: Option<DateTime>,
+    at_location_id: Option<String>,
+}
+
+#[async_trait]
+impl Validate for NewCommitment {
+    async fn validate(&self, validator: &mut Validator<'_>) -> Result<()> {
+        validator.positive("quantity", Some(self.quantity));
+        validator
+            .exists("processId", "processes", self.process_id.as_deref())
+            .await?;
+        validator
+            .exists("actionId", "actions", Some(&self.action_id))
+            .await?;
+        validator
+            .exists(
+                "assignedAgentId",
+                "agents",
+                self.assigned_agent_id.as_deref(),
+            )
+            .await?;
+        validator
+            .exists("providerId", "agents", self.provider_id.as_deref())
+            .await?;
+        validator
+            .exists("receiverId", "agents", self.receiver_id.as_deref())
+            .await?;
+        validator
+            .exists("clauseOfId", "agreements", self.clause_of_id.as_deref())
+            .await?;
+        validator
+            .exists(
+                "resourceSpecificationId",
+                "resource_specifications",
+                Some(&self.resource_specification_id),
+            )
+            .await?;
+        validator
+            .exists("unitId", "units", Some(&self.unit_id))
+            .await?;
+        validator
+            .exists("atLocationId", "locations", self.at_location_id.as_deref())
+            .await
+    }
 }
 
 #[derive(InputObject, Debug)]
@@ -79,481 +284,3139 @@ struct UpdateCommitment {
     id: String,
     description: Option<String>,
     action_id: Option<String>,
-    quantity: Option<i32>,
+    quantity: Option<Decimal>,
     unit_id: Option<String>,
     resource_specification_id: Option<String>,
     assigned_agent_id: Option<String>,
-    due_at: Option<String>,
+    due_at: Option<DateTime>,
 }
 
-pub struct MutationRoot;
+#[async_trait]
+impl Validate for UpdateCommitment {
+    async fn validate(&self, validator: &mut Validator<'_>) -> Result<()> {
+        validator.positive("quantity", self.quantity);
+        validator
+            .exists("actionId", "actions", self.action_id.as_deref())
+            .await?;
+        validator
+            .exists("unitId", "units", self.unit_id.as_deref())
+            .await?;
+        validator
+            .exists(
+                "resourceSpecificationId",
+                "resource_specifications",
+                self.resource_specification_id.as_deref(),
+            )
+            .await?;
+        validator
+            .exists(
+                "assignedAgentId",
+                "agents",
+                self.assigned_agent_id.as_deref(),
+            )
+            .await
+    }
+}
 
-#[Object]
-impl MutationRoot {
-    async fn create_agent<'ctx>(
-        &self,
-        context: &Context<'ctx>,
-        new_agent: NewAgent,
-    ) -> Result<Agent> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
-        let ulid = Ulid::new().to_string();
-        let unique_name: String = unique_name(&new_agent.name);
-        let inserted_agent = sqlx::query_as::<_, Agent>(
-            "
-            INSERT INTO agents (id, name, unique_name, email, agent_type)
-            VALUES (?, ?, ?, ?, ?)
-            RETURNING *
-        ",
-        )
-        .bind(&ulid)
-        .bind(new_agent.name)
-        .bind(unique_name)
-        .bind(new_agent.email)
-        .bind(new_agent.agent_type)
-        .fetch_one(pool)
-        .await?;
-        Ok(inserted_agent)
+#[derive(InputObject, Debug)]
+struct NewEconomicEvent {
+    note: Option<String>,
+    action_id: String,
+    provider_id: String,
+    receiver_id: String,
+    resource_quantity: Option<Decimal>,
+    unit_id: Option<String>,
+    resource_specification_id: Option<String>,
+    input_of_id: Option<String>,
+    output_of_id: Option<String>,
+    realization_of_id: Option<String>,
+    resource_inventoried_as_id: Option<String>,
+    to_resource_inventoried_as_id: Option<String>,
+    has_point_in_time: Option<DateTime>,
+    has_beginning: Option<DateTime>,
+    has_end: Option<DateTime>,
+    at_location_id: Option<String>,
+}
+
+impl NewEconomicEvent {
+    /// Those who can record the event: the provider or the receiver, when they can also change
+    /// the resources the event affects and the processes it is an input or an output of.
+    fn editors(&self) -> impl Guard + '_ {
+        AnyOf(vec![
+            CanEdit::Agent(&self.provider_id),
+            CanEdit::Agent(&self.receiver_id),
+        ])
+        .and(IfGiven(
+            self.resource_inventoried_as_id
+                .as_deref()
+                .map(CanEdit::Resource),
+        ))
+        .and(IfGiven(
+            self.to_resource_inventoried_as_id
+                .as_deref()
+                .map(CanEdit::Resource),
+        ))
+        .and(IfGiven(self.input_of_id.as_deref().map(CanEdit::Process)))
+        .and(IfGiven(self.output_of_id.as_deref().map(CanEdit::Process)))
     }
+}
 
-    async fn delete_agent<'ctx>(
-        &self,
-        context: &Context<'ctx>,
-        unique_name: String,
-    ) -> Result<i32> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
-        let result = sqlx::query!("DELETE FROM agents WHERE unique_name = ?", unique_name)
-            .execute(pool)
+#[async_trait]
+impl Validate for NewEconomicEvent {
+    async fn validate(&self, validator: &mut Validator<'_>) -> Result<()> {
+        if self.has_point_in_time.is_none() && self.has_beginning.is_none() {
+            validator.invalid("hasPointInTime", "is required without hasBeginning");
+        }
+        validator.period(
+            ("hasBeginning", self.has_beginning),
+            ("hasEnd", self.has_end),
+        );
+        validator.positive("resourceQuantity", self.resource_quantity);
+        validator
+            .exists("actionId", "actions", Some(&self.action_id))
             .await?;
-        Ok(result.rows_affected() as i32)
-    }
-    async fn create_label<'ctx>(
-        &self,
-        context: &Context<'ctx>,
-        new_label: NewLabel,
-    ) -> Result<Label> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
-        let ulid = Ulid::new().to_string();
-        let unique_name: String = unique_name(&new_label.name);
-        let inserted_label = sqlx::query_as!(
-            Label,
-            "INSERT INTO labels (id, name, unique_name, color)
-            VALUES (?, ?, ?, ?)
-            RETURNING *",
-            ulid,
-            new_label.name,
-            unique_name,
-            new_label.color
-        )
-        .fetch_one(pool)
-        .await?;
-        Ok(inserted_label)
+        validator
+            .exists("providerId", "agents", Some(&self.provider_id))
+            .await?;
+        validator
+            .exists("receiverId", "agents", Some(&self.receiver_id))
+            .await?;
+        validator
+            .exists("unitId", "units", self.unit_id.as_deref())
+            .await?;
+        validator
+            .exists(
+                "resourceSpecificationId",
+                "resource_specifications",
+                self.resource_specification_id.as_deref(),
+            )
+            .await?;
+        validator
+            .exists("inputOfId", "processes", self.input_of_id.as_deref())
+            .await?;
+        validator
+            .exists("outputOfId", "processes", self.output_of_id.as_deref())
+            .await?;
+        validator
+            .exists(
+                "realizationOfId",
+                "agreements",
+                self.realization_of_id.as_deref(),
+            )
+            .await?;
+        validator
+            .exists(
+                "resourceInventoriedAsId",
+                "economic_resources",
+                self.resource_inventoried_as_id.as_deref(),
+            )
+            .await?;
+        validator
+            .exists(
+                "toResourceInventoriedAsId",
+                "economic_resources",
+                self.to_resource_inventoried_as_id.as_deref(),
+            )
+            .await?;
+        validator
+            .exists("atLocationId", "locations", self.at_location_id.as_deref())
+            .await
     }
+}
 
-    async fn delete_label<'ctx>(&self, context: &Context<'ctx>, id: String) -> Result<i32> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
-        let result = sqlx::query!("DELETE FROM labels WHERE id = ?", id)
-            .execute(pool)
+#[derive(InputObject, Debug)]
+struct NewEconomicResource {
+    name: String,
+    note: Option<String>,
+    tracking_identifier: Option<String>,
+    conforms_to_id: String,
+    primary_accountable_id: Option<String>,
+    current_location_id: Option<String>,
+    unit_id: Option<String>,
+}
+
+#[async_trait]
+impl Validate for NewEconomicResource {
+    async fn validate(&self, validator: &mut Validator<'_>) -> Result<()> {
+        validator
+            .exists(
+                "conformsToId",
+                "resource_specifications",
+                Some(&self.conforms_to_id),
+            )
             .await?;
-        Ok(result.rows_affected() as i32)
+        validator
+            .exists(
+                "primaryAccountableId",
+                "agents",
+                self.primary_accountable_id.as_deref(),
+            )
+            .await?;
+        validator
+            .exists(
+                "currentLocationId",
+                "locations",
+                self.current_location_id.as_deref(),
+            )
+            .await?;
+        validator
+            .exists("unitId", "units", self.unit_id.as_deref())
+            .await
     }
+}
 
-    async fn create_plan<'ctx>(&self, context: &Context<'ctx>, new_plan: NewPlan) -> Result<Plan> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
-        let ulid = Ulid::new().to_string();
-        let inserted_plan = sqlx::query(
-            "INSERT INTO plans (id, title)
-            VALUES (?, ?)
-            RETURNING *",
-        )
-        .bind(&ulid)
-        .bind(new_plan.title)
-        .map(Plan::from_row)
-        .fetch_one(pool)
-        .await?;
-        sqlx::query("INSERT INTO plan_agents (plan_id, agent_id) VALUES (?, ?)")
-            .bind(&ulid)
-            .bind(new_plan.agent_id)
-            .execute(pool)
+#[derive(InputObject, Debug)]
+struct NewFulfillment {
+    fulfilled_by_id: String,
+    fulfills_id: String,
+    resource_quantity: Option<Decimal>,
+    effort_quantity: Option<Decimal>,
+    note: Option<String>,
+}
+
+#[async_trait]
+impl Validate for NewFulfillment {
+    async fn validate(&self, validator: &mut Validator<'_>) -> Result<()> {
+        validator.positive("resourceQuantity", self.resource_quantity);
+        validator.positive("effortQuantity", self.effort_quantity);
+        validator
+            .exists(
+                "fulfilledById",
+                "economic_events",
+                Some(&self.fulfilled_by_id),
+            )
             .await?;
-        Ok(inserted_plan)
+        validator
+            .exists("fulfillsId", "commitments", Some(&self.fulfills_id))
+            .await
     }
+}
 
-    async fn update_plan<'ctx>(
-        &self,
-        context: &Context<'ctx>,
-        update_plan: UpdatePlan,
-    ) -> Result<i32> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
-        let UpdatePlan {
-            title,
-            description,
-            id,
-            ..
-        } = update_plan;
-        let result = sqlx::query!(
-            "UPDATE plans SET title = ?, description = ? WHERE id = ?",
-            title,
-            description,
-            id
-        )
-        .execute(pool)
-        .await?;
-        Ok(result.rows_affected() as i32)
-    }
+#[derive(InputObject, Debug)]
+struct NewIntent {
+    name: Option<String>,
+    note: Option<String>,
+    action_id: String,
+    provider_id: Option<String>,
+    receiver_id: Option<String>,
+    resource_specification_id: Option<String>,
+    resource_quantity: Option<Decimal>,
+    unit_id: Option<String>,
+    due_at: Option<DateTime>,
+    at_location_id: Option<String>,
+}
 
-    async fn create_process<'ctx>(
-        &self,
-        context: &Context<'ctx>,
-        new_process: NewProcess,
-    ) -> Result<Process> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
-        let ulid = Ulid::new().to_string();
-        // TODO put those in a transaction
-        let mut inserted_process = sqlx::query(
-            "
-            INSERT INTO processes (id, title, description, plan_id)
-            VALUES (?, ?, ?, ?)
-            RETURNING *
-            ",
-        )
-        .bind(&ulid)
-        .bind(new_process.title)
-        .bind(new_process.description)
-        .bind(new_process.plan_id)
-        .map(Process::from_row)
-        .fetch_one(pool)
-        .await?;
-        // TODO paralelize queries
-        let new_process_labels = new_process
-            .labels
-            .unwrap_or_else(Vec::new)
-            .iter()
-            .map(|label_id| {
-                sqlx::query("INSERT INTO process_labels (process_id, label_id) VALUES (?, ?)")
-                    .bind(&ulid)
-                    .bind(label_id.clone())
-                    .execute(pool)
-            })
-            .collect::<Vec<_>>();
+#[async_trait]
+impl Validate for NewIntent {
+    async fn validate(&self, validator: &mut Validator<'_>) -> Result<()> {
+        if self.provider_id.is_some() == self.receiver_id.is_some() {
+            validator.invalid(
+                "providerId",
+                "an intent needs either a provider (offer) or a receiver (request)",
+            );
+        }
+        validator.positive("resourceQuantity", self.resource_quantity);
+        validator
+            .exists("actionId", "actions", Some(&self.action_id))
+            .await?;
+        validator
+            .exists("providerId", "agents", self.provider_id.as_deref())
+            .await?;
+        validator
+            .exists("receiverId", "agents", self.receiver_id.as_deref())
+            .await?;
+        validator
+            .exists(
+                "resourceSpecificationId",
+                "resource_specifications",
+                self.resource_specification_id.as_deref(),
+            )
+            .await?;
+        validator
+            .exists("unitId", "units", self.unit_id.as_deref())
+            .await?;
+        validator
+            .exists("atLocationId", "locations", self.at_location_id.as_deref())
+            .await
+    }
+}
 
-        let new_process_agents = new_process
-            .agents
-            .unwrap_or_else(Vec::new)
-            .iter()
-            .map(|agent_id| {
-                sqlx::query("INSERT INTO process_agents (process_id, agent_id) VALUES (?, ?)")
-                    .bind(&ulid)
-                    .bind(agent_id.clone())
-                    .execute(pool)
-            })
-            .collect::<Vec<_>>();
-        // TODO parallelize
-        join_all(new_process_labels).await;
-        join_all(new_process_agents).await;
+#[derive(InputObject, Debug)]
+struct NewProposal {
+    name: String,
+    note: Option<String>,
+    unit_based: Option<bool>,
+    has_beginning: Option<DateTime>,
+    has_end: Option<DateTime>,
+    publishes: Vec<String>,
+    reciprocal: Option<Vec<String>>,
+    proposed_to: Option<Vec<String>>,
+}
 
-        let labels = sqlx::query(
-            "
-           SELECT labels.id, name, color
-           FROM labels
-           INNER JOIN process_labels
-           ON process_labels.label_id = labels.id
-           WHERE process_labels.process_id = ?
-           ",
-        )
-        .bind(&ulid)
-        .map(Label::from_row)
-        .fetch_all(pool)
-        .await?;
-        let agents = sqlx::query(
-            "
-            SELECT agents.id, name, unique_name, agent_type
-            FROM agents
-            INNER JOIN process_agents
-            ON process_agents.agent_id = agents.id
-            WHERE process_agents.process_id = ?
-            ",
+impl NewProposal {
+    /// Those who can propose the intents: the agents who can change every one of them.
+    fn editors(&self) -> AllOf<'_> {
+        AllOf(
+            self.publishes
+                .iter()
+                .chain(self.reciprocal.iter().flatten())
+                .map(|intent_id| CanEdit::Intent(intent_id))
+                .collect(),
         )
-        .bind(ulid)
-        .map(Agent::from_row)
-        .fetch_all(pool)
-        .await?;
-        inserted_process.labels = labels;
-        inserted_process.agents = agents;
-        Ok(inserted_process)
     }
+}
 
-    async fn update_process<'ctx>(
-        &self,
-        context: &Context<'ctx>,
-        update_process: UpdateProcess,
-    ) -> Result<i32> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
-        // TODO paralelize queries
-        let id: String = update_process.id;
-        sqlx::query!("DELETE FROM process_labels WHERE process_id = ?", id)
-            .execute(pool)
+#[async_trait]
+impl Validate for NewProposal {
+    async fn validate(&self, validator: &mut Validator<'_>) -> Result<()> {
+        validator.period(
+            ("hasBeginning", self.has_beginning),
+            ("hasEnd", self.has_end),
+        );
+        validator
+            .all_exist("publishes", "intents", &self.publishes)
             .await?;
-        sqlx::query!("DELETE FROM process_agents WHERE process_id = ?", id)
-            .execute(pool)
+        validator
+            .all_exist(
+                "reciprocal",
+                "intents",
+                self.reciprocal.as_deref().unwrap_or_default(),
+            )
             .await?;
-        let new_process_labels = update_process
-            .labels
-            .unwrap_or_else(Vec::new)
-            .iter()
-            .map(|label_id| {
-                sqlx::query("INSERT INTO process_labels (process_id, label_id) VALUES (?, ?)")
-                    .bind(&id)
-                    .bind(label_id.clone())
-                    .execute(pool)
-            })
-            .collect::<Vec<_>>();
-        let new_process_agents = update_process
-            .agents
-            .unwrap_or_else(Vec::new)
-            .iter()
-            .map(|agent_id| {
-                sqlx::query("INSERT INTO process_agents (process_id, agent_id) VALUES (?, ?)")
-                    .bind(&id)
-                    .bind(agent_id.clone())
-                    .execute(pool)
-            })
-            .collect::<Vec<_>>();
-        join_all(new_process_labels).await;
-        join_all(new_process_agents).await;
+        validator
+            .all_exist(
+                "proposedTo",
+                "agents",
+                self.proposed_to.as_deref().unwrap_or_default(),
+            )
+            .await
+    }
+}
 
-        let result = sqlx::query("UPDATE processes SET title = ?, description = ? WHERE id = ?")
-            .bind(update_process.title)
-            .bind(update_process.description)
-            .bind(&id)
+#[derive(InputObject, Debug)]
+struct NewSatisfaction {
+    satisfies_id: String,
+    satisfied_by_id: String,
+    resource_quantity: Option<Decimal>,
+    effort_quantity: Option<Decimal>,
+    note: Option<String>,
+}
+
+#[async_trait]
+impl Validate for NewSatisfaction {
+    async fn validate(&self, validator: &mut Validator<'_>) -> Result<()> {
+        validator.positive("resourceQuantity", self.resource_quantity);
+        validator.positive("effortQuantity", self.effort_quantity);
+        validator
+            .exists("satisfiesId", "intents", Some(&self.satisfies_id))
+            .await?;
+        validator
+            .exists("satisfiedById", "commitments", Some(&self.satisfied_by_id))
+            .await
+    }
+}
+
+#[derive(InputObject, Debug)]
+struct NewAgreement {
+    name: String,
+    note: Option<String>,
+}
+
+#[derive(InputObject, Debug)]
+struct NewProcessSpecification {
+    name: String,
+    note: Option<String>,
+}
+
+#[derive(InputObject, Debug)]
+struct NewRecipeResource {
+    name: String,
+    note: Option<String>,
+    resource_specification_id: Option<String>,
+    unit_id: Option<String>,
+}
+
+#[async_trait]
+impl Validate for NewRecipeResource {
+    async fn validate(&self, validator: &mut Validator<'_>) -> Result<()> {
+        validator
+            .exists(
+                "resourceSpecificationId",
+                "resource_specifications",
+                self.resource_specification_id.as_deref(),
+            )
+            .await?;
+        validator
+            .exists("unitId", "units", self.unit_id.as_deref())
+            .await
+    }
+}
+
+#[derive(InputObject, Debug)]
+struct NewRecipe {
+    name: String,
+    note: Option<String>,
+    primary_output_id: String,
+}
+
+#[async_trait]
+impl Validate for NewRecipe {
+    async fn validate(&self, validator: &mut Validator<'_>) -> Result<()> {
+        validator
+            .exists(
+                "primaryOutputId",
+                "recipe_resources",
+                Some(&self.primary_output_id),
+            )
+            .await
+    }
+}
+
+#[derive(InputObject, Debug)]
+struct NewRecipeProcess {
+    recipe_id: String,
+    name: String,
+    note: Option<String>,
+    process_specification_id: Option<String>,
+    has_duration_seconds: Option<i32>,
+}
+
+#[async_trait]
+impl Validate for NewRecipeProcess {
+    async fn validate(&self, validator: &mut Validator<'_>) -> Result<()> {
+        if self.has_duration_seconds.unwrap_or(0) < 0 {
+            validator.invalid("hasDurationSeconds", "must not be negative");
+        }
+        validator
+            .exists("recipeId", "recipes", Some(&self.recipe_id))
+            .await?;
+        validator
+            .exists(
+                "processSpecificationId",
+                "process_specifications",
+                self.process_specification_id.as_deref(),
+            )
+            .await
+    }
+}
+
+#[derive(InputObject, Debug)]
+struct NewRecipeExchange {
+    recipe_id: String,
+    name: String,
+    note: Option<String>,
+}
+
+#[async_trait]
+impl Validate for NewRecipeExchange {
+    async fn validate(&self, validator: &mut Validator<'_>) -> Result<()> {
+        validator
+            .exists("recipeId", "recipes", Some(&self.recipe_id))
+            .await
+    }
+}
+
+#[derive(InputObject, Debug)]
+struct NewRecipeFlow {
+    note: Option<String>,
+    action_id: String,
+    recipe_flow_resource_id: String,
+    resource_quantity: Option<Decimal>,
+    unit_id: Option<String>,
+    recipe_input_of_id: Option<String>,
+    recipe_output_of_id: Option<String>,
+    recipe_clause_of_id: Option<String>,
+}
+
+#[async_trait]
+impl Validate for NewRecipeFlow {
+    async fn validate(&self, validator: &mut Validator<'_>) -> Result<()> {
+        let owners = [
+            &self.recipe_input_of_id,
+            &self.recipe_output_of_id,
+            &self.recipe_clause_of_id,
+        ];
+        if owners.iter().filter(|owner| owner.is_some()).count() != 1 {
+            validator.invalid(
+                "recipeInputOfId",
+                "exactly one of recipeInputOfId, recipeOutputOfId or recipeClauseOfId is required",
+            );
+        }
+        validator.positive("resourceQuantity", self.resource_quantity);
+        validator
+            .exists("actionId", "actions", Some(&self.action_id))
+            .await?;
+        validator
+            .exists(
+                "recipeFlowResourceId",
+                "recipe_resources",
+                Some(&self.recipe_flow_resource_id),
+            )
+            .await?;
+        validator
+            .exists("unitId", "units", self.unit_id.as_deref())
+            .await?;
+        validator
+            .exists(
+                "recipeInputOfId",
+                "recipe_processes",
+                self.recipe_input_of_id.as_deref(),
+            )
+            .await?;
+        validator
+            .exists(
+                "recipeOutputOfId",
+                "recipe_processes",
+                self.recipe_output_of_id.as_deref(),
+            )
+            .await?;
+        validator
+            .exists(
+                "recipeClauseOfId",
+                "recipe_exchanges",
+                self.recipe_clause_of_id.as_deref(),
+            )
+            .await
+    }
+}
+
+/// Weights one of an action, a resource specification or an agent role.
+#[derive(InputObject, Debug)]
+struct NewValueEquationWeight {
+    action_id: Option<String>,
+    resource_specification_id: Option<String>,
+    agent_relation_type_id: Option<String>,
+    weight: Decimal,
+}
+
+#[derive(InputObject, Debug)]
+struct NewValueEquation {
+    name: String,
+    note: Option<String>,
+    context_agent_id: Option<String>,
+    weights: Vec<NewValueEquationWeight>,
+}
+
+#[async_trait]
+impl Validate for NewValueEquation {
+    async fn validate(&self, validator: &mut Validator<'_>) -> Result<()> {
+        validator
+            .exists("contextAgentId", "agents", self.context_agent_id.as_deref())
+            .await?;
+        for (index, weight) in self.weights.iter().enumerate() {
+            let field = |name: &str| format!("weights.{}.{}", index, name);
+            let dimensions = [
+                &weight.action_id,
+                &weight.resource_specification_id,
+                &weight.agent_relation_type_id,
+            ];
+            if dimensions.iter().filter(|d| d.is_some()).count() != 1 {
+                validator.invalid(
+                    &field("actionId"),
+                    "a weight needs exactly one of actionId, resourceSpecificationId or agentRelationTypeId",
+                );
+            }
+            validator.not_negative(&field("weight"), Some(weight.weight));
+            validator
+                .exists(&field("actionId"), "actions", weight.action_id.as_deref())
+                .await?;
+            validator
+                .exists(
+                    &field("resourceSpecificationId"),
+                    "resource_specifications",
+                    weight.resource_specification_id.as_deref(),
+                )
+                .await?;
+            validator
+                .exists(
+                    &field("agentRelationTypeId"),
+                    "agent_relation_types",
+                    weight.agent_relation_type_id.as_deref(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+/// How an event changes the quantities of the resources it affects,
+/// following the ValueFlows action definitions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ResourceEffect {
+    Increment,
+    Decrement,
+    DecrementIncrement,
+    NoEffect,
+}
+
+impl ResourceEffect {
+    fn from_action_name(name: &str) -> Self {
+        match name {
+            "produce" => ResourceEffect::Increment,
+            "consume" => ResourceEffect::Decrement,
+            "transfer" => ResourceEffect::DecrementIncrement,
+            // use, work, cite, accept and modify leave quantities untouched
+            _ => ResourceEffect::NoEffect,
+        }
+    }
+}
+
+async fn insert_economic_resource(
+    transaction: &mut Transaction<'_, Sqlite>,
+    new_economic_resource: NewEconomicResource,
+) -> Result<String> {
+    let ulid = Ulid::new().to_string();
+    sqlx::query(
+        "
+        INSERT INTO economic_resources (id, name, note, tracking_identifier, conforms_to_id, primary_accountable_id, current_location_id, unit_id)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        ",
+    )
+    .bind(&ulid)
+    .bind(new_economic_resource.name)
+    .bind(new_economic_resource.note)
+    .bind(new_economic_resource.tracking_identifier)
+    .bind(new_economic_resource.conforms_to_id)
+    .bind(new_economic_resource.primary_accountable_id)
+    .bind(new_economic_resource.current_location_id)
+    .bind(new_economic_resource.unit_id)
+    .execute(transaction)
+    .await?;
+    Ok(ulid)
+}
+
+async fn update_resource_quantities(
+    transaction: &mut Transaction<'_, Sqlite>,
+    resource_id: &str,
+    quantity: Decimal,
+    unit_id: &Option<String>,
+) -> Result<()> {
+    let (resource_unit_id, accounting_quantity, onhand_quantity) = sqlx::query(
+        "SELECT unit_id, accounting_quantity, onhand_quantity FROM economic_resources WHERE id = ?",
+    )
+    .bind(resource_id)
+    .map(|row: SqliteRow| {
+        (
+            row.get::<Option<String>, _>("unit_id"),
+            row.get::<Decimal, _>("accounting_quantity"),
+            row.get::<Decimal, _>("onhand_quantity"),
+        )
+    })
+    .fetch_one(&mut *transaction)
+    .await?;
+    // quantities in another unit of the same dimension are converted to the resource unit
+    let units = measure::units_by_id(&mut *transaction).await?;
+    let quantity = measure::convert(
+        quantity,
+        unit_id.as_ref().and_then(|id| units.get(id)),
+        resource_unit_id.as_ref().and_then(|id| units.get(id)),
+    )?;
+    sqlx::query(
+        "UPDATE economic_resources SET accounting_quantity = ?, onhand_quantity = ? WHERE id = ?",
+    )
+    .bind(accounting_quantity + quantity)
+    .bind(onhand_quantity + quantity)
+    .bind(resource_id)
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
+/// Whether the fulfillments of a commitment add up to its quantity.
+async fn fulfillments_cover(
+    transaction: &mut Transaction<'_, Sqlite>,
+    commitment_id: &str,
+) -> Result<bool> {
+    let (quantity, action) = sqlx::query(
+        "
+        SELECT commitments.quantity, actions.*
+        FROM commitments
+        JOIN actions ON actions.id = commitments.action_id
+        WHERE commitments.id = ?
+        ",
+    )
+    .bind(commitment_id)
+    .map(|row: SqliteRow| (row.get::<Decimal, _>("quantity"), Action::from_row(row)))
+    .fetch_one(&mut *transaction)
+    .await?;
+    let fulfillments = sqlx::query("SELECT * FROM fulfillments WHERE fulfills_id = ?")
+        .bind(commitment_id)
+        .map(Fulfillment::from_row)
+        .fetch_all(&mut *transaction)
+        .await?;
+    let fulfilled_quantity: Decimal = fulfillments
+        .iter()
+        .map(|fulfillment| fulfillment.quantity(action.is_effort()))
+        .sum();
+    Ok(quantity <= fulfilled_quantity)
+}
+
+/// Whether the satisfactions of an intent add up to its quantity.
+async fn satisfactions_cover(
+    transaction: &mut Transaction<'_, Sqlite>,
+    intent_id: &str,
+) -> Result<bool> {
+    let quantity: Option<Decimal> =
+        sqlx::query_scalar("SELECT resource_quantity FROM intents WHERE id = ?")
+            .bind(intent_id)
+            .fetch_one(&mut *transaction)
+            .await?;
+    let satisfactions = sqlx::query("SELECT * FROM satisfactions WHERE satisfies_id = ?")
+        .bind(intent_id)
+        .map(Satisfaction::from_row)
+        .fetch_all(&mut *transaction)
+        .await?;
+    let satisfied_quantity: Decimal = satisfactions
+        .iter()
+        .map(|satisfaction| {
+            satisfaction
+                .resource_quantity
+                .or(satisfaction.effort_quantity)
+                .unwrap_or_default()
+        })
+        .sum();
+    Ok(matches!(quantity, Some(quantity) if quantity <= satisfied_quantity))
+}
+
+async fn fetch_economic_resource(pool: &SqlitePool, id: &str) -> Result<EconomicResource> {
+    let mut economic_resource = sqlx::query("SELECT * FROM economic_resources WHERE id = ?")
+        .bind(id)
+        .map(EconomicResource::from_row)
+        .fetch_one(pool)
+        .await?;
+    let conforms_to = sqlx::query_as!(
+        ResourceSpecification,
+        r#"
+        SELECT id, name, unique_name, inserted_at AS "inserted_at: DateTime"
+        FROM resource_specifications
+        WHERE resource_specifications.id = ?
+        "#,
+        economic_resource.conforms_to_id
+    )
+    .fetch_one(pool)
+    .await?;
+    economic_resource.conforms_to = Some(conforms_to);
+    if let Some(primary_accountable_id) = &economic_resource.primary_accountable_id {
+        let primary_accountable = sqlx::query("SELECT * FROM agents WHERE agents.id = ?")
+            .bind(primary_accountable_id)
+            .map(Agent::from_row)
+            .fetch_one(pool)
+            .await?;
+        economic_resource.primary_accountable = Some(primary_accountable);
+    }
+    if let Some(unit_id) = &economic_resource.unit_id {
+        let unit = sqlx::query_as::<_, Unit>("SELECT * FROM units WHERE units.id = ?")
+            .bind(unit_id)
+            .fetch_one(pool)
+            .await?;
+        economic_resource.set_unit(Some(unit));
+    }
+    Ok(economic_resource)
+}
+
+/// Marks an agent, a plan, a process or a commitment as deleted, or restores it, and returns
+/// the number of records changed.
+/// Deleted records stay in their table and keep their links to other records.
+async fn set_deleted(
+    pool: &SqlitePool,
+    context: &Context<'_>,
+    entity: AuditEntity,
+    id: &str,
+    deleted: bool,
+) -> Result<i32> {
+    let mut transaction = pool.begin().await?;
+    let before = audit::snapshot(&mut transaction, entity, id).await?;
+    let result = match entity {
+        AuditEntity::Agent => {
+            sqlx::query!(
+                "
+            UPDATE agents SET deleted_at = CASE WHEN ? THEN CURRENT_TIMESTAMP END
+            WHERE id = ? AND (deleted_at IS NULL) = ?
+            ",
+                deleted,
+                id,
+                deleted
+            )
+            .execute(&mut transaction)
+            .await?
+        }
+        AuditEntity::Plan => {
+            sqlx::query!(
+                "
+            UPDATE plans SET deleted_at = CASE WHEN ? THEN CURRENT_TIMESTAMP END
+            WHERE id = ? AND (deleted_at IS NULL) = ?
+            ",
+                deleted,
+                id,
+                deleted
+            )
+            .execute(&mut transaction)
+            .await?
+        }
+        AuditEntity::Process => {
+            sqlx::query!(
+                "
+            UPDATE processes SET deleted_at = CASE WHEN ? THEN CURRENT_TIMESTAMP END
+            WHERE id = ? AND (deleted_at IS NULL) = ?
+            ",
+                deleted,
+                id,
+                deleted
+            )
+            .execute(&mut transaction)
+            .await?
+        }
+        AuditEntity::Commitment => {
+            sqlx::query!(
+                "
+            UPDATE commitments SET deleted_at = CASE WHEN ? THEN CURRENT_TIMESTAMP END
+            WHERE id = ? AND (deleted_at IS NULL) = ?
+            ",
+                deleted,
+                id,
+                deleted
+            )
+            .execute(&mut transaction)
+            .await?
+        }
+        _ => unreachable!("{:?} records are deleted for good", entity),
+    };
+    audit::record(&mut transaction, context, entity, id, before).await?;
+    transaction.commit().await?;
+    Ok(result.rows_affected() as i32)
+}
+
+/// Links a process to exactly the labels or agents with `ids`: links to other records are
+/// removed and the missing ones added. Fails with the first id that can't be linked.
+async fn set_process_links(
+    transaction: &mut Transaction<'_, Sqlite>,
+    process_id: &str,
+    linked: AuditEntity,
+    ids: &[String],
+) -> Result<()> {
+    let ids_json = serde_json::json!(ids).to_string();
+    let linked_ids = match linked {
+        AuditEntity::Label => {
+            sqlx::query!(
+                "
+                DELETE FROM process_labels
+                WHERE process_id = ? AND label_id NOT IN (SELECT value FROM json_each(?))
+                ",
+                process_id,
+                ids_json
+            )
+            .execute(&mut *transaction)
+            .await?;
+            sqlx::query_scalar!(
+                "SELECT label_id FROM process_labels WHERE process_id = ?",
+                process_id
+            )
+            .fetch_all(&mut *transaction)
+            .await?
+        }
+        AuditEntity::Agent => {
+            sqlx::query!(
+                "
+                DELETE FROM process_agents
+                WHERE process_id = ? AND agent_id NOT IN (SELECT value FROM json_each(?))
+                ",
+                process_id,
+                ids_json
+            )
+            .execute(&mut *transaction)
+            .await?;
+            sqlx::query_scalar!(
+                "SELECT agent_id FROM process_agents WHERE process_id = ?",
+                process_id
+            )
+            .fetch_all(&mut *transaction)
+            .await?
+        }
+        _ => unreachable!("processes are not linked to {:?} records", linked),
+    };
+    let mut linked_ids: HashSet<String> = linked_ids.into_iter().collect();
+    for id in ids {
+        if !linked_ids.insert(id.clone()) {
+            continue;
+        }
+        let (inserted, kind) = match linked {
+            AuditEntity::Label => (
+                sqlx::query!(
+                    "INSERT INTO process_labels (process_id, label_id) VALUES (?, ?)",
+                    process_id,
+                    id
+                )
+                .execute(&mut *transaction)
+                .await,
+                "label",
+            ),
+            _ => (
+                sqlx::query!(
+                    "INSERT INTO process_agents (process_id, agent_id) VALUES (?, ?)",
+                    process_id,
+                    id
+                )
+                .execute(&mut *transaction)
+                .await,
+                "agent",
+            ),
+        };
+        inserted.map_err(|error| match DomainError::from(error) {
+            DomainError::ForeignKey(_) => {
+                DomainError::ForeignKey(format!("{} {} does not exist", kind, id))
+            }
+            error => error,
+        })?;
+    }
+    Ok(())
+}
+
+/// Checks and inserts a process with its labels and agents, recording it in the audit log.
+/// The caller publishes the change once the transaction is committed.
+async fn insert_process(
+    transaction: &mut Transaction<'_, Sqlite>,
+    context: &Context<'_>,
+    argument: &str,
+    new_process: NewProcess,
+    based_on_id: Option<&str>,
+) -> Result<Process> {
+    validate_in(transaction, argument, &new_process).await?;
+    let ulid = Ulid::new().to_string();
+    let inserted_process = sqlx::query(
+        "
+        INSERT INTO processes (id, title, description, plan_id, start_at, due_at, at_location_id, based_on_id)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        RETURNING *
+        ",
+    )
+    .bind(&ulid)
+    .bind(new_process.title)
+    .bind(new_process.description)
+    .bind(new_process.plan_id)
+    .bind(new_process.start_date)
+    .bind(new_process.due_date)
+    .bind(new_process.at_location_id)
+    .bind(based_on_id)
+    .map(Process::from_row)
+    .fetch_one(&mut *transaction)
+    .await?;
+    set_process_links(
+        transaction,
+        &ulid,
+        AuditEntity::Label,
+        &new_process.labels.unwrap_or_default(),
+    )
+    .await?;
+    set_process_links(
+        transaction,
+        &ulid,
+        AuditEntity::Agent,
+        &new_process.agents.unwrap_or_default(),
+    )
+    .await?;
+    audit::record(transaction, context, AuditEntity::Process, &ulid, None).await?;
+    Ok(inserted_process)
+}
+
+/// Checks and inserts a commitment, recording it in the audit log. The caller publishes the
+/// change once the transaction is committed.
+async fn insert_commitment(
+    transaction: &mut Transaction<'_, Sqlite>,
+    context: &Context<'_>,
+    argument: &str,
+    new_commitment: NewCommitment,
+) -> Result<Commitment> {
+    validate_in(transaction, argument, &new_commitment).await?;
+    let ulid = Ulid::new().to_string();
+    let inserted_commitment = sqlx::query(
+        "
+        INSERT INTO commitments (id, description, process_id, action_id, assigned_agent_id, provider_id, receiver_id, clause_of_id, quantity, unit_id, resource_specification_id, due_at, at_location_id)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        RETURNING *
+        ",
+    )
+    .bind(&ulid)
+    .bind(new_commitment.description)
+    .bind(new_commitment.process_id)
+    .bind(new_commitment.action_id)
+    .bind(new_commitment.assigned_agent_id)
+    .bind(new_commitment.provider_id)
+    .bind(new_commitment.receiver_id)
+    .bind(new_commitment.clause_of_id)
+    .bind(new_commitment.quantity)
+    .bind(new_commitment.unit_id)
+    .bind(new_commitment.resource_specification_id)
+    .bind(new_commitment.due_at)
+    .bind(new_commitment.at_location_id)
+    .map(Commitment::from_row)
+    .fetch_one(&mut *transaction)
+    .await?;
+    audit::record(transaction, context, AuditEntity::Commitment, &ulid, None).await?;
+    Ok(inserted_commitment)
+}
+
+/// Inserts the scheduled processes of a recipe into a plan. Every process flow becomes a
+/// commitment, inputs due when their process starts and outputs when it ends. Returns the
+/// commitments it created, for the caller to publish once the transaction is committed.
+async fn insert_schedule(
+    transaction: &mut Transaction<'_, Sqlite>,
+    context: &Context<'_>,
+    plan_id: &str,
+    schedule: &[planning::ScheduledProcess<'_>],
+    factor: Decimal,
+    agent_id: &Option<String>,
+) -> Result<Vec<Commitment>> {
+    let mut commitments = Vec::new();
+    for scheduled in schedule {
+        let recipe_process = scheduled.recipe_process;
+        let new_process = NewProcess {
+            title: recipe_process.name.clone(),
+            description: recipe_process.note.clone(),
+            plan_id: Some(plan_id.to_string()),
+            start_date: Some(scheduled.start_at),
+            due_date: Some(scheduled.due_at),
+            labels: None,
+            agents: None,
+            at_location_id: None,
+        };
+        let process = insert_process(
+            transaction,
+            context,
+            "recipeProcess",
+            new_process,
+            recipe_process.process_specification_id.as_deref(),
+        )
+        .await?;
+        let flows = recipe_process
+            .inputs
+            .iter()
+            .map(|flow| (flow, scheduled.start_at))
+            .chain(
+                recipe_process
+                    .outputs
+                    .iter()
+                    .map(|flow| (flow, scheduled.due_at)),
+            );
+        for (flow, due_at) in flows {
+            let RecipeResource {
+                name,
+                resource_specification_id,
+                unit_id,
+                ..
+            } = flow.recipe_flow_resource.clone().unwrap_or_default();
+            let resource_specification_id = resource_specification_id.ok_or_else(|| {
+                DomainError::validation(format!(
+                    "recipe resource {} has no resource specification to commit to",
+                    name
+                ))
+            })?;
+            let unit_id = flow.unit_id.clone().or(unit_id).ok_or_else(|| {
+                DomainError::validation(format!(
+                    "recipe resource {} has no unit to commit to",
+                    name
+                ))
+            })?;
+            let new_commitment = NewCommitment {
+                description: flow.note.clone().unwrap_or(name),
+                process_id: Some(process.id.clone()),
+                action_id: flow.action_id.clone(),
+                assigned_agent_id: agent_id.clone(),
+                provider_id: None,
+                receiver_id: None,
+                clause_of_id: None,
+                resource_specification_id,
+                quantity: planning::scaled_quantity(flow, factor),
+                unit_id,
+                due_at: Some(due_at),
+                at_location_id: None,
+            };
+            commitments
+                .push(insert_commitment(transaction, context, "recipeFlow", new_commitment).await?);
+        }
+    }
+    Ok(commitments)
+}
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    #[graphql(guard = "LoggedIn")]
+    async fn create_agent<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        new_agent: NewAgent,
+    ) -> Result<Agent> {
+        let pool = context.data::<SqlitePool>()?;
+        validate(pool, "newAgent", &new_agent).await?;
+        let bus = context.data::<ChangeBus>()?;
+        let ulid = Ulid::new().to_string();
+        let unique_name: String = unique_name(&new_agent.name);
+        let password_hash = new_agent
+            .password
+            .as_deref()
+            .map(auth::hash_password)
+            .transpose()?;
+        let mut transaction = pool.begin().await?;
+        let inserted_agent = sqlx::query_as::<_, Agent>(
+            "
+            INSERT INTO agents (id, name, unique_name, email, agent_type, primary_location_id, password_hash)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            RETURNING *
+        ",
+        )
+        .bind(&ulid)
+        .bind(new_agent.name)
+        .bind(unique_name)
+        .bind(new_agent.email)
+        .bind(new_agent.agent_type)
+        .bind(new_agent.primary_location_id)
+        .bind(password_hash)
+        .fetch_one(&mut transaction)
+        .await?;
+        audit::record(&mut transaction, context, AuditEntity::Agent, &ulid, None).await?;
+        transaction.commit().await?;
+        bus.publish(Change::AgentCreated(inserted_agent.clone()));
+        Ok(inserted_agent)
+    }
+
+    /// Deletes an agent, which can no longer log in until `restoreAgent` brings it back. The
+    /// admins of its organizations can do both.
+    #[graphql(guard = "CanEdit::MemberNamed(&unique_name)")]
+    async fn delete_agent<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        unique_name: String,
+    ) -> Result<i32> {
+        let pool = context.data::<SqlitePool>()?;
+        let id = sqlx::query_scalar!("SELECT id FROM agents WHERE unique_name = ?", unique_name)
+            .fetch_optional(pool)
+            .await?;
+        match id {
+            Some(id) => set_deleted(pool, context, AuditEntity::Agent, &id, true).await,
+            None => Ok(0),
+        }
+    }
+
+    #[graphql(guard = "CanEdit::Member(&id)")]
+    async fn restore_agent<'ctx>(&self, context: &Context<'ctx>, id: String) -> Result<i32> {
+        let pool = context.data::<SqlitePool>()?;
+        set_deleted(pool, context, AuditEntity::Agent, &id, false).await
+    }
+
+    /// Starts a session for the agent with this email and password.
+    async fn login<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        email: String,
+        password: String,
+    ) -> Result<Session> {
+        let pool = context.data::<SqlitePool>()?;
+        let candidates = sqlx::query(
+            "
+            SELECT * FROM agents
+            WHERE email = ? COLLATE NOCASE AND password_hash IS NOT NULL AND deleted_at IS NULL
+            ",
+        )
+        .bind(email)
+        .map(|row: SqliteRow| (row.get::<String, _>("password_hash"), Agent::from_row(row)))
+        .fetch_all(pool)
+        .await?;
+        let agent = candidates
+            .into_iter()
+            .find(|(password_hash, _)| auth::verify_password(&password, password_hash))
+            .map(|(_, agent)| agent)
+            .ok_or_else(|| DomainError::unauthorized("invalid email or password"))?;
+        let mut transaction = pool.begin().await?;
+        let (token, session) =
+            auth::issue_token(&mut transaction, &agent.id, TokenKind::Session, None).await?;
+        audit::record_by(
+            &mut transaction,
+            context,
+            Some(&agent.id),
+            AuditEntity::AccessToken,
+            &session.id,
+            None,
+        )
+        .await?;
+        transaction.commit().await?;
+        Ok(Session {
+            token,
+            expires_at: session.expires_at.unwrap_or_default(),
+            agent,
+        })
+    }
+
+    /// Ends the session the request was made with.
+    #[graphql(guard = "LoggedIn")]
+    async fn logout<'ctx>(&self, context: &Context<'ctx>) -> Result<bool> {
+        let pool = context.data::<SqlitePool>()?;
+        let current_agent = auth::current_agent(context)?;
+        let mut transaction = pool.begin().await?;
+        let before = audit::snapshot(
+            &mut transaction,
+            AuditEntity::AccessToken,
+            &current_agent.token_id,
+        )
+        .await?;
+        let result = sqlx::query("DELETE FROM access_tokens WHERE id = ? AND kind = ?")
+            .bind(&current_agent.token_id)
+            .bind(TokenKind::Session)
+            .execute(&mut transaction)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(DomainError::validation(
+                "the request was made with an API token, use revokeApiToken",
+            ));
+        }
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::AccessToken,
+            &current_agent.token_id,
+            before,
+        )
+        .await?;
+        transaction.commit().await?;
+        Ok(true)
+    }
+
+    /// Sets the password an agent logs in with. Agents change their own password by giving the
+    /// current one, which ends their other sessions. The admins of the organizations an agent
+    /// is a member of set its password without it, which ends all its sessions.
+    #[graphql(guard = "LoggedIn")]
+    async fn set_password<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        agent_id: String,
+        password: String,
+        current_password: Option<String>,
+    ) -> Result<bool> {
+        let pool = context.data::<SqlitePool>()?;
+        let current_agent = auth::current_agent(context)?;
+        let password_hash: Option<String> =
+            sqlx::query_scalar("SELECT password_hash FROM agents WHERE id = ?")
+                .bind(&agent_id)
+                .fetch_optional(pool)
+                .await?
+                .ok_or_else(|| DomainError::not_found(format!("agent {}", agent_id)))?;
+        let own_password = current_agent.agent_id == agent_id;
+        match password_hash {
+            Some(password_hash) if own_password => {
+                let current_password = current_password
+                    .ok_or_else(|| DomainError::validation("currentPassword is required"))?;
+                if !auth::verify_password(&current_password, &password_hash) {
+                    return Err(DomainError::unauthorized("currentPassword is wrong"));
+                }
+            }
+            _ => CanEdit::Member(&agent_id).check(context).await?,
+        }
+        let mut transaction = pool.begin().await?;
+        let before = audit::snapshot(&mut transaction, AuditEntity::Agent, &agent_id).await?;
+        sqlx::query("UPDATE agents SET password_hash = ? WHERE id = ?")
+            .bind(auth::hash_password(&password)?)
+            .bind(&agent_id)
+            .execute(&mut transaction)
+            .await?;
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::Agent,
+            &agent_id,
+            before,
+        )
+        .await?;
+        transaction.commit().await?;
+        // the session the password is changed from stays open, all the others end
+        let current_token_id = Some(&current_agent.token_id).filter(|_| own_password);
+        sqlx::query("DELETE FROM access_tokens WHERE agent_id = ? AND kind = ? AND id IS NOT ?")
+            .bind(&agent_id)
+            .bind(TokenKind::Session)
+            .bind(current_token_id)
             .execute(pool)
             .await?;
+        Ok(true)
+    }
+
+    /// Makes a token for the scripts of the current agent. It lasts until it is revoked.
+    #[graphql(guard = "LoggedIn")]
+    async fn create_api_token<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        name: String,
+    ) -> Result<NewApiToken> {
+        let pool = context.data::<SqlitePool>()?;
+        let current_agent = auth::current_agent(context)?;
+        let mut transaction = pool.begin().await?;
+        let (token, api_token) = auth::issue_token(
+            &mut transaction,
+            &current_agent.agent_id,
+            TokenKind::Api,
+            Some(name),
+        )
+        .await?;
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::AccessToken,
+            &api_token.id,
+            None,
+        )
+        .await?;
+        transaction.commit().await?;
+        Ok(NewApiToken { token, api_token })
+    }
+
+    #[graphql(guard = "LoggedIn")]
+    async fn revoke_api_token<'ctx>(&self, context: &Context<'ctx>, id: String) -> Result<i32> {
+        let pool = context.data::<SqlitePool>()?;
+        let current_agent = auth::current_agent(context)?;
+        let mut transaction = pool.begin().await?;
+        let before = audit::snapshot(&mut transaction, AuditEntity::AccessToken, &id).await?;
+        let result =
+            sqlx::query("DELETE FROM access_tokens WHERE id = ? AND agent_id = ? AND kind = ?")
+                .bind(&id)
+                .bind(&current_agent.agent_id)
+                .bind(TokenKind::Api)
+                .execute(&mut transaction)
+                .await?;
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::AccessToken,
+            &id,
+            before,
+        )
+        .await?;
+        transaction.commit().await?;
+        Ok(result.rows_affected() as i32)
+    }
+    #[graphql(guard = "LoggedIn")]
+    async fn create_label<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        new_label: NewLabel,
+    ) -> Result<Label> {
+        let pool = context.data::<SqlitePool>()?;
+        validate(pool, "newLabel", &new_label).await?;
+        let ulid = Ulid::new().to_string();
+        let unique_name: String = unique_name(&new_label.name);
+        let mut transaction = pool.begin().await?;
+        let inserted_label = sqlx::query_as!(
+            Label,
+            r#"INSERT INTO labels (id, name, unique_name, color)
+            VALUES (?, ?, ?, ?)
+            RETURNING id, name, unique_name, color, inserted_at AS "inserted_at: DateTime""#,
+            ulid,
+            new_label.name,
+            unique_name,
+            new_label.color
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+        audit::record(&mut transaction, context, AuditEntity::Label, &ulid, None).await?;
+        transaction.commit().await?;
+        Ok(inserted_label)
+    }
+
+    #[graphql(guard = "CanEdit::Label(&id)")]
+    async fn delete_label<'ctx>(&self, context: &Context<'ctx>, id: String) -> Result<i32> {
+        let pool = context.data::<SqlitePool>()?;
+        let mut transaction = pool.begin().await?;
+        let before = audit::snapshot(&mut transaction, AuditEntity::Label, &id).await?;
+        let result = sqlx::query!("DELETE FROM labels WHERE id = ?", id)
+            .execute(&mut transaction)
+            .await?;
+        audit::record(&mut transaction, context, AuditEntity::Label, &id, before).await?;
+        transaction.commit().await?;
+        Ok(result.rows_affected() as i32)
+    }
+
+    #[graphql(guard = "LoggedIn")]
+    async fn create_location<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        new_location: NewLocation,
+    ) -> Result<Location> {
+        let pool = context.data::<SqlitePool>()?;
+        validate(pool, "newLocation", &new_location).await?;
+        let ulid = Ulid::new().to_string();
+        let mut transaction = pool.begin().await?;
+        let inserted_location = sqlx::query(
+            "INSERT INTO locations (id, name, mappable_address, lat, long, alt, note)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            RETURNING *",
+        )
+        .bind(&ulid)
+        .bind(new_location.name)
+        .bind(new_location.mappable_address)
+        .bind(new_location.lat)
+        .bind(new_location.long)
+        .bind(new_location.alt)
+        .bind(new_location.note)
+        .map(Location::from_row)
+        .fetch_one(&mut transaction)
+        .await?;
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::Location,
+            &ulid,
+            None,
+        )
+        .await?;
+        transaction.commit().await?;
+        Ok(inserted_location)
+    }
+
+    #[graphql(guard = "CanEdit::Location(&id)")]
+    async fn delete_location<'ctx>(&self, context: &Context<'ctx>, id: String) -> Result<i32> {
+        let pool = context.data::<SqlitePool>()?;
+        let mut transaction = pool.begin().await?;
+        let before = audit::snapshot(&mut transaction, AuditEntity::Location, &id).await?;
+        let result = sqlx::query("DELETE FROM locations WHERE id = ?")
+            .bind(&id)
+            .execute(&mut transaction)
+            .await?;
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::Location,
+            &id,
+            before,
+        )
+        .await?;
+        transaction.commit().await?;
+        Ok(result.rows_affected() as i32)
+    }
+
+    /// Units of the same dimension convert into each other through their conversion factor,
+    /// the quantity of the base unit of the dimension one of them is worth.
+    #[graphql(guard = "LoggedIn")]
+    async fn create_unit<'ctx>(&self, context: &Context<'ctx>, new_unit: NewUnit) -> Result<Unit> {
+        let pool = context.data::<SqlitePool>()?;
+        validate(pool, "newUnit", &new_unit).await?;
+        let ulid = Ulid::new().to_string();
+        let mut transaction = pool.begin().await?;
+        let inserted_unit = sqlx::query(
+            "INSERT INTO units (id, label, symbol, dimension, conversion_factor)
+            VALUES (?, ?, ?, ?, ?)
+            RETURNING *",
+        )
+        .bind(&ulid)
+        .bind(new_unit.label)
+        .bind(new_unit.symbol)
+        .bind(new_unit.dimension)
+        .bind(new_unit.conversion_factor)
+        .map(Unit::from_row)
+        .fetch_one(&mut transaction)
+        .await?;
+        audit::record(&mut transaction, context, AuditEntity::Unit, &ulid, None).await?;
+        transaction.commit().await?;
+        Ok(inserted_unit)
+    }
+
+    #[graphql(guard = "CanEdit::Agent(&new_plan.agent_id)")]
+    async fn create_plan<'ctx>(&self, context: &Context<'ctx>, new_plan: NewPlan) -> Result<Plan> {
+        let pool = context.data::<SqlitePool>()?;
+        validate(pool, "newPlan", &new_plan).await?;
+        let ulid = Ulid::new().to_string();
+        let mut transaction = pool.begin().await?;
+        let inserted_plan = sqlx::query(
+            "INSERT INTO plans (id, title)
+            VALUES (?, ?)
+            RETURNING *",
+        )
+        .bind(&ulid)
+        .bind(new_plan.title)
+        .map(Plan::from_row)
+        .fetch_one(&mut transaction)
+        .await?;
+        sqlx::query("INSERT INTO plan_agents (plan_id, agent_id) VALUES (?, ?)")
+            .bind(&ulid)
+            .bind(new_plan.agent_id)
+            .execute(&mut transaction)
+            .await?;
+        audit::record(&mut transaction, context, AuditEntity::Plan, &ulid, None).await?;
+        transaction.commit().await?;
+        Ok(inserted_plan)
+    }
+
+    #[graphql(guard = "CanEdit::Plan(&update_plan.id)")]
+    async fn update_plan<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        update_plan: UpdatePlan,
+    ) -> Result<i32> {
+        let pool = context.data::<SqlitePool>()?;
+        let bus = context.data::<ChangeBus>()?;
+        let UpdatePlan {
+            title,
+            description,
+            id,
+            ..
+        } = update_plan;
+        let mut transaction = pool.begin().await?;
+        let before = audit::snapshot(&mut transaction, AuditEntity::Plan, &id).await?;
+        let result = sqlx::query!(
+            "UPDATE plans SET title = ?, description = ? WHERE id = ?",
+            title,
+            description,
+            id
+        )
+        .execute(&mut transaction)
+        .await?;
+        audit::record(&mut transaction, context, AuditEntity::Plan, &id, before).await?;
+        transaction.commit().await?;
+        bus.publish(Change::Plan(id));
+        Ok(result.rows_affected() as i32)
+    }
+
+    /// Deletes a plan. Its processes are left as they are.
+    #[graphql(guard = "CanEdit::Plan(&id)")]
+    async fn delete_plan<'ctx>(&self, context: &Context<'ctx>, id: String) -> Result<i32> {
+        let pool = context.data::<SqlitePool>()?;
+        let bus = context.data::<ChangeBus>()?;
+        let deleted = set_deleted(pool, context, AuditEntity::Plan, &id, true).await?;
+        bus.publish(Change::Plan(id));
+        Ok(deleted)
+    }
+
+    #[graphql(guard = "CanEdit::Plan(&id)")]
+    async fn restore_plan<'ctx>(&self, context: &Context<'ctx>, id: String) -> Result<i32> {
+        let pool = context.data::<SqlitePool>()?;
+        let bus = context.data::<ChangeBus>()?;
+        let restored = set_deleted(pool, context, AuditEntity::Plan, &id, false).await?;
+        bus.publish(Change::Plan(id));
+        Ok(restored)
+    }
+
+    #[graphql(guard = "IfGiven(new_process.plan_id.as_deref().map(CanEdit::Plan))")]
+    async fn create_process<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        new_process: NewProcess,
+    ) -> Result<Process> {
+        let pool = context.data::<SqlitePool>()?;
+        let bus = context.data::<ChangeBus>()?;
+        let mut transaction = pool.begin().await?;
+        let inserted_process =
+            insert_process(&mut transaction, context, "newProcess", new_process, None).await?;
+        transaction.commit().await?;
+        bus.publish_process_change(pool, &inserted_process.id)
+            .await?;
+        Ok(inserted_process)
+    }
+
+    #[graphql(guard = "CanEdit::Process(&update_process.id)")]
+    async fn update_process<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        update_process: UpdateProcess,
+    ) -> Result<i32> {
+        let pool = context.data::<SqlitePool>()?;
+        validate(pool, "updateProcess", &update_process).await?;
+        let bus = context.data::<ChangeBus>()?;
+        let id: String = update_process.id;
+        let mut transaction = pool.begin().await?;
+        let before = audit::snapshot(&mut transaction, AuditEntity::Process, &id).await?;
+        let result = sqlx::query(
+            "
+            UPDATE processes
+            SET title = ?, description = ?, at_location_id = ?,
+                start_at = COALESCE(?, start_at), due_at = COALESCE(?, due_at)
+            WHERE id = ?
+            ",
+        )
+        .bind(update_process.title)
+        .bind(update_process.description)
+        .bind(update_process.at_location_id)
+        .bind(update_process.start_at)
+        .bind(update_process.due_at)
+        .bind(&id)
+        .execute(&mut transaction)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(DomainError::not_found(format!("process {}", id)));
+        }
+        if let Some(labels) = &update_process.labels {
+            set_process_links(&mut transaction, &id, AuditEntity::Label, labels).await?;
+        }
+        if let Some(agents) = &update_process.agents {
+            set_process_links(&mut transaction, &id, AuditEntity::Agent, agents).await?;
+        }
+        audit::record(&mut transaction, context, AuditEntity::Process, &id, before).await?;
+        transaction.commit().await?;
+        bus.publish_process_change(pool, &id).await?;
+        Ok(result.rows_affected() as i32)
+    }
+
+    /// Deletes a process. `restoreProcess` brings it back with its labels and agents.
+    #[graphql(guard = "CanEdit::Process(&process_id)")]
+    async fn delete_process<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        process_id: String,
+    ) -> Result<i32> {
+        let pool = context.data::<SqlitePool>()?;
+        let bus = context.data::<ChangeBus>()?;
+        let deleted = set_deleted(pool, context, AuditEntity::Process, &process_id, true).await?;
+        bus.publish_process_change(pool, &process_id).await?;
+        Ok(deleted)
+    }
+
+    #[graphql(guard = "CanEdit::Process(&process_id)")]
+    async fn restore_process<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        process_id: String,
+    ) -> Result<i32> {
+        let pool = context.data::<SqlitePool>()?;
+        let bus = context.data::<ChangeBus>()?;
+        let restored = set_deleted(pool, context, AuditEntity::Process, &process_id, false).await?;
+        bus.publish_process_change(pool, &process_id).await?;
+        Ok(restored)
+    }
+
+    #[graphql(guard = "LoggedIn")]
+    async fn create_resource_specification<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        new_resource_specification: NewResourceSpecification,
+    ) -> Result<ResourceSpecification> {
+        let pool = context.data::<SqlitePool>()?;
+        let ulid = Ulid::new().to_string();
+        let unique_name: String = unique_name(&new_resource_specification.name);
+        let mut transaction = pool.begin().await?;
+        let inserted_resource_specification = sqlx::query_as!(
+            ResourceSpecification,
+            r#"INSERT INTO resource_specifications (id, name, unique_name)
+                VALUES (?, ?, ?)
+                RETURNING id, name, unique_name, inserted_at AS "inserted_at: DateTime""#,
+            ulid,
+            new_resource_specification.name,
+            unique_name
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::ResourceSpecification,
+            &ulid,
+            None,
+        )
+        .await?;
+        transaction.commit().await?;
+        Ok(inserted_resource_specification)
+    }
+
+    #[graphql(guard = "CanEdit::ResourceSpecificationNamed(&unique_name)")]
+    async fn delete_resource_specification<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        unique_name: String,
+    ) -> Result<i32> {
+        let pool = context.data::<SqlitePool>()?;
+        let mut transaction = pool.begin().await?;
+        let id = sqlx::query_scalar!(
+            "SELECT id FROM resource_specifications WHERE unique_name = ?",
+            unique_name
+        )
+        .fetch_optional(&mut transaction)
+        .await?;
+        let id = match id {
+            Some(id) => id,
+            None => return Ok(0),
+        };
+        let before =
+            audit::snapshot(&mut transaction, AuditEntity::ResourceSpecification, &id).await?;
+        let result = sqlx::query!("DELETE FROM resource_specifications WHERE id = ?", id)
+            .execute(&mut transaction)
+            .await?;
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::ResourceSpecification,
+            &id,
+            before,
+        )
+        .await?;
+        transaction.commit().await?;
+        Ok(result.rows_affected() as i32)
+    }
+
+    #[graphql(guard = "IfGiven(new_commitment.process_id.as_deref().map(CanEdit::Process))")]
+    async fn create_commitment<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        new_commitment: NewCommitment,
+    ) -> Result<Commitment> {
+        let pool = context.data::<SqlitePool>()?;
+        let bus = context.data::<ChangeBus>()?;
+        let mut transaction = pool.begin().await?;
+        let inserted_commitment =
+            insert_commitment(&mut transaction, context, "newCommitment", new_commitment).await?;
+        transaction.commit().await?;
+        bus.publish_commitment_change(pool, ChangeKind::Created, inserted_commitment.clone())
+            .await?;
+        Ok(inserted_commitment)
+    }
+
+    #[graphql(guard = "CanEdit::Commitment(&update_commitment.id)")]
+    async fn update_commitment<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        update_commitment: UpdateCommitment,
+    ) -> Result<i32> {
+        let pool = context.data::<SqlitePool>()?;
+        validate(pool, "updateCommitment", &update_commitment).await?;
+        let bus = context.data::<ChangeBus>()?;
+        let UpdateCommitment {
+            id,
+            description,
+            action_id,
+            quantity,
+            unit_id,
+            resource_specification_id,
+            assigned_agent_id,
+            due_at,
+        } = update_commitment;
+        let mut transaction = pool.begin().await?;
+        let before = audit::snapshot(&mut transaction, AuditEntity::Commitment, &id).await?;
+        let result = sqlx::query!(
+            "
+            UPDATE commitments
+            SET description = ?,
+                unit_id = ?,
+                action_id = ?,
+                resource_specification_id = ?,
+                quantity = ?,
+                assigned_agent_id = ?,
+                due_at = ?
+            WHERE id = ?",
+            description,
+            unit_id,
+            action_id,
+            resource_specification_id,
+            quantity,
+            assigned_agent_id,
+            due_at,
+            id
+        )
+        .execute(&mut transaction)
+        .await?;
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::Commitment,
+            &id,
+            before,
+        )
+        .await?;
+        transaction.commit().await?;
+        bus.publish_commitment_update(pool, &id).await?;
+        Ok(result.rows_affected() as i32)
+    }
+
+    /// Deletes a commitment until `restoreCommitment` brings it back.
+    #[graphql(guard = "CanEdit::Commitment(&id)")]
+    async fn delete_commitment<'ctx>(&self, context: &Context<'ctx>, id: String) -> Result<i32> {
+        let pool = context.data::<SqlitePool>()?;
+        let bus = context.data::<ChangeBus>()?;
+        let deleted = set_deleted(pool, context, AuditEntity::Commitment, &id, true).await?;
+        if deleted > 0 {
+            let commitment = sqlx::query("SELECT * FROM commitments WHERE id = ?")
+                .bind(&id)
+                .map(Commitment::from_row)
+                .fetch_one(pool)
+                .await?;
+            bus.publish_commitment_change(pool, ChangeKind::Deleted, commitment)
+                .await?;
+        }
+        Ok(deleted)
+    }
+
+    #[graphql(guard = "CanEdit::Commitment(&id)")]
+    async fn restore_commitment<'ctx>(&self, context: &Context<'ctx>, id: String) -> Result<i32> {
+        let pool = context.data::<SqlitePool>()?;
+        let bus = context.data::<ChangeBus>()?;
+        let restored = set_deleted(pool, context, AuditEntity::Commitment, &id, false).await?;
+        if restored > 0 {
+            let commitment = sqlx::query("SELECT * FROM commitments WHERE id = ?")
+                .bind(&id)
+                .map(Commitment::from_row)
+                .fetch_one(pool)
+                .await?;
+            bus.publish_commitment_change(pool, ChangeKind::Created, commitment)
+                .await?;
+        }
+        Ok(restored)
+    }
+
+    #[graphql(guard = "LoggedIn")]
+    async fn create_economic_resource<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        new_economic_resource: NewEconomicResource,
+    ) -> Result<EconomicResource> {
+        let pool = context.data::<SqlitePool>()?;
+        validate(pool, "newEconomicResource", &new_economic_resource).await?;
+        let mut transaction = pool.begin().await?;
+        let id = insert_economic_resource(&mut transaction, new_economic_resource).await?;
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::EconomicResource,
+            &id,
+            None,
+        )
+        .await?;
+        transaction.commit().await?;
+        fetch_economic_resource(pool, &id).await
+    }
+
+    #[graphql(guard = "new_economic_event.editors()")]
+    async fn create_economic_event<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        new_economic_event: NewEconomicEvent,
+        new_inventoried_resource: Option<NewEconomicResource>,
+    ) -> Result<EconomicEvent> {
+        let pool = context.data::<SqlitePool>()?;
+        validate(pool, "newEconomicEvent", &new_economic_event).await?;
+        if let Some(new_inventoried_resource) = &new_inventoried_resource {
+            validate(pool, "newInventoriedResource", new_inventoried_resource).await?;
+        }
+        let action = sqlx::query_as::<_, Action>("SELECT * FROM actions WHERE actions.id = ?")
+            .bind(&new_economic_event.action_id)
+            .fetch_one(pool)
+            .await?;
+        let effect = ResourceEffect::from_action_name(&action.name);
+        let ulid = Ulid::new().to_string();
+        let mut transaction = pool.begin().await?;
+        // the resources the event creates or changes, with their state before it
+        let mut audited_resources = Vec::new();
+        let mut resource_inventoried_as_id = new_economic_event.resource_inventoried_as_id;
+        if let Some(new_resource) = new_inventoried_resource {
+            let id = insert_economic_resource(&mut transaction, new_resource).await?;
+            audited_resources.push((id.clone(), None));
+            resource_inventoried_as_id = Some(id);
+        }
+        let mut to_resource_inventoried_as_id = new_economic_event.to_resource_inventoried_as_id;
+        if let (ResourceEffect::DecrementIncrement, Some(from_id), None) = (
+            effect,
+            &resource_inventoried_as_id,
+            &to_resource_inventoried_as_id,
+        ) {
+            // a transfer without a target resource creates one for the receiver
+            let to_id = Ulid::new().to_string();
+            sqlx::query(
+                "
+                INSERT INTO economic_resources (id, name, note, tracking_identifier, conforms_to_id, primary_accountable_id, current_location_id, unit_id)
+                SELECT ?, name, note, tracking_identifier, conforms_to_id, ?, current_location_id, unit_id
+                FROM economic_resources
+                WHERE id = ?
+                ",
+            )
+            .bind(&to_id)
+            .bind(&new_economic_event.receiver_id)
+            .bind(from_id)
+            .execute(&mut transaction)
+            .await?;
+            audited_resources.push((to_id.clone(), None));
+            to_resource_inventoried_as_id = Some(to_id);
+        }
+        for id in resource_inventoried_as_id
+            .iter()
+            .chain(&to_resource_inventoried_as_id)
+        {
+            if !audited_resources.iter().any(|(audited, _)| audited == id) {
+                let before =
+                    audit::snapshot(&mut transaction, AuditEntity::EconomicResource, id).await?;
+                audited_resources.push((id.clone(), before));
+            }
+        }
+        let mut inserted_economic_event = sqlx::query(
+            "
+            INSERT INTO economic_events (id, note, action_id, provider_id, receiver_id, resource_quantity, unit_id, resource_specification_id, input_of_id, output_of_id, realization_of_id, resource_inventoried_as_id, to_resource_inventoried_as_id, has_point_in_time, has_beginning, has_end, at_location_id)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            RETURNING *
+            ",
+        )
+        .bind(&ulid)
+        .bind(new_economic_event.note)
+        .bind(new_economic_event.action_id)
+        .bind(new_economic_event.provider_id)
+        .bind(new_economic_event.receiver_id)
+        .bind(new_economic_event.resource_quantity)
+        .bind(new_economic_event.unit_id)
+        .bind(new_economic_event.resource_specification_id)
+        .bind(new_economic_event.input_of_id)
+        .bind(new_economic_event.output_of_id)
+        .bind(new_economic_event.realization_of_id)
+        .bind(resource_inventoried_as_id)
+        .bind(to_resource_inventoried_as_id)
+        .bind(new_economic_event.has_point_in_time)
+        .bind(new_economic_event.has_beginning)
+        .bind(new_economic_event.has_end)
+        .bind(new_economic_event.at_location_id)
+        .map(EconomicEvent::from_row)
+        .fetch_one(&mut transaction)
+        .await?;
+        let quantity = inserted_economic_event
+            .resource_quantity
+            .unwrap_or_default();
+        let unit_id = &inserted_economic_event.unit_id;
+        let from_id = &inserted_economic_event.resource_inventoried_as_id;
+        let to_id = &inserted_economic_event.to_resource_inventoried_as_id;
+        match (effect, from_id, to_id) {
+            (ResourceEffect::Increment, Some(from_id), _) => {
+                update_resource_quantities(&mut transaction, from_id, quantity, unit_id).await?;
+            }
+            (ResourceEffect::Decrement, Some(from_id), _) => {
+                update_resource_quantities(&mut transaction, from_id, -quantity, unit_id).await?;
+            }
+            (ResourceEffect::DecrementIncrement, Some(from_id), Some(to_id)) => {
+                update_resource_quantities(&mut transaction, from_id, -quantity, unit_id).await?;
+                update_resource_quantities(&mut transaction, to_id, quantity, unit_id).await?;
+            }
+            (ResourceEffect::NoEffect, _, _) => {}
+            _ => {
+                return Err(DomainError::validation(format!(
+                    "a {} event needs the resource it affects",
+                    action.name
+                )));
+            }
+        }
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::EconomicEvent,
+            &ulid,
+            None,
+        )
+        .await?;
+        for (id, before) in audited_resources {
+            audit::record(
+                &mut transaction,
+                context,
+                AuditEntity::EconomicResource,
+                &id,
+                before,
+            )
+            .await?;
+        }
+        transaction.commit().await?;
+        inserted_economic_event.action = Some(action);
+        let provider = sqlx::query("SELECT * FROM agents WHERE agents.id = ?")
+            .bind(&inserted_economic_event.provider_id)
+            .map(Agent::from_row)
+            .fetch_one(pool)
+            .await?;
+        inserted_economic_event.provider = Some(provider);
+        let receiver = sqlx::query("SELECT * FROM agents WHERE agents.id = ?")
+            .bind(&inserted_economic_event.receiver_id)
+            .map(Agent::from_row)
+            .fetch_one(pool)
+            .await?;
+        inserted_economic_event.receiver = Some(receiver);
+        if let Some(unit_id) = &inserted_economic_event.unit_id {
+            let unit = sqlx::query_as::<_, Unit>("SELECT * FROM units WHERE units.id = ?")
+                .bind(unit_id)
+                .fetch_one(pool)
+                .await?;
+            inserted_economic_event.unit = Some(unit);
+        }
+        if let Some(resource_specification_id) = &inserted_economic_event.resource_specification_id
+        {
+            let resource_specification = sqlx::query_as!(
+                ResourceSpecification,
+                r#"
+                SELECT id, name, unique_name, inserted_at AS "inserted_at: DateTime"
+                FROM resource_specifications
+                WHERE resource_specifications.id = ?
+                "#,
+                resource_specification_id
+            )
+            .fetch_one(pool)
+            .await?;
+            inserted_economic_event.resource_specification = Some(resource_specification);
+        }
+        if let Some(id) = &inserted_economic_event.resource_inventoried_as_id {
+            inserted_economic_event.resource_inventoried_as =
+                Some(fetch_economic_resource(pool, id).await?);
+        }
+        if let Some(id) = &inserted_economic_event.to_resource_inventoried_as_id {
+            inserted_economic_event.to_resource_inventoried_as =
+                Some(fetch_economic_resource(pool, id).await?);
+        }
+        Ok(inserted_economic_event)
+    }
+
+    #[graphql(guard = "CanEdit::Commitment(&new_fulfillment.fulfills_id)")]
+    async fn create_fulfillment<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        new_fulfillment: NewFulfillment,
+    ) -> Result<Fulfillment> {
+        let pool = context.data::<SqlitePool>()?;
+        validate(pool, "newFulfillment", &new_fulfillment).await?;
+        let bus = context.data::<ChangeBus>()?;
+        let ulid = Ulid::new().to_string();
+        let mut transaction = pool.begin().await?;
+        // without explicit quantities the whole event counts towards the commitment, in the
+        // unit of the commitment, as effort for work
+        let (resource_quantity, effort_quantity) = match (
+            new_fulfillment.resource_quantity,
+            new_fulfillment.effort_quantity,
+        ) {
+            (None, None) => {
+                let (event_quantity, event_unit_id, commitment_unit_id) = sqlx::query(
+                    "
+                    SELECT economic_events.resource_quantity, economic_events.unit_id, commitments.unit_id AS commitment_unit_id
+                    FROM economic_events, commitments
+                    WHERE economic_events.id = ?
+                    AND commitments.id = ?
+                    ",
+                )
+                .bind(&new_fulfillment.fulfilled_by_id)
+                .bind(&new_fulfillment.fulfills_id)
+                .map(|row: SqliteRow| {
+                    (
+                        row.get::<Option<Decimal>, _>("resource_quantity"),
+                        row.get::<Option<String>, _>("unit_id"),
+                        row.get::<Option<String>, _>("commitment_unit_id"),
+                    )
+                })
+                .fetch_one(&mut transaction)
+                .await?;
+                let units = measure::units_by_id(&mut transaction).await?;
+                let quantity = event_quantity
+                    .map(|quantity| {
+                        measure::convert(
+                            quantity,
+                            event_unit_id.as_ref().and_then(|id| units.get(id)),
+                            commitment_unit_id.as_ref().and_then(|id| units.get(id)),
+                        )
+                    })
+                    .transpose()?;
+                let is_effort = sqlx::query(
+                    "
+                    SELECT actions.* FROM actions
+                    JOIN commitments ON commitments.action_id = actions.id
+                    WHERE commitments.id = ?
+                    ",
+                )
+                .bind(&new_fulfillment.fulfills_id)
+                .map(Action::from_row)
+                .fetch_one(&mut transaction)
+                .await?
+                .is_effort();
+                if is_effort {
+                    (None, quantity)
+                } else {
+                    (quantity, None)
+                }
+            }
+            quantities => quantities,
+        };
+        let inserted_fulfillment = sqlx::query(
+            "
+            INSERT INTO fulfillments (id, fulfilled_by_id, fulfills_id, resource_quantity, effort_quantity, note)
+            VALUES (?, ?, ?, ?, ?, ?)
+            RETURNING *
+            ",
+        )
+        .bind(&ulid)
+        .bind(&new_fulfillment.fulfilled_by_id)
+        .bind(&new_fulfillment.fulfills_id)
+        .bind(resource_quantity)
+        .bind(effort_quantity)
+        .bind(new_fulfillment.note)
+        .map(Fulfillment::from_row)
+        .fetch_one(&mut transaction)
+        .await?;
+        let commitment_before = audit::snapshot(
+            &mut transaction,
+            AuditEntity::Commitment,
+            &new_fulfillment.fulfills_id,
+        )
+        .await?;
+        if fulfillments_cover(&mut transaction, &new_fulfillment.fulfills_id).await? {
+            sqlx::query("UPDATE commitments SET finished = TRUE WHERE id = ?")
+                .bind(&new_fulfillment.fulfills_id)
+                .execute(&mut transaction)
+                .await?;
+        }
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::Fulfillment,
+            &ulid,
+            None,
+        )
+        .await?;
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::Commitment,
+            &new_fulfillment.fulfills_id,
+            commitment_before,
+        )
+        .await?;
+        transaction.commit().await?;
+        bus.publish_commitment_update(pool, &new_fulfillment.fulfills_id)
+            .await?;
+        Ok(inserted_fulfillment)
+    }
+
+    #[graphql(guard = "CanEdit::Fulfillment(&id)")]
+    async fn delete_fulfillment<'ctx>(&self, context: &Context<'ctx>, id: String) -> Result<i32> {
+        let pool = context.data::<SqlitePool>()?;
+        let bus = context.data::<ChangeBus>()?;
+        let mut transaction = pool.begin().await?;
+        let before = audit::snapshot(&mut transaction, AuditEntity::Fulfillment, &id).await?;
+        let mut fulfills_ids: Vec<String> =
+            sqlx::query_scalar("DELETE FROM fulfillments WHERE id = ? RETURNING fulfills_id")
+                .bind(&id)
+                .fetch_all(&mut transaction)
+                .await?;
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::Fulfillment,
+            &id,
+            before,
+        )
+        .await?;
+        if let Some(fulfills_id) = fulfills_ids.last() {
+            // the commitment stays finished only while what is left covers it
+            let commitment_before =
+                audit::snapshot(&mut transaction, AuditEntity::Commitment, fulfills_id).await?;
+            let finished = fulfillments_cover(&mut transaction, fulfills_id).await?;
+            sqlx::query("UPDATE commitments SET finished = ? WHERE id = ?")
+                .bind(finished)
+                .bind(fulfills_id)
+                .execute(&mut transaction)
+                .await?;
+            audit::record(
+                &mut transaction,
+                context,
+                AuditEntity::Commitment,
+                fulfills_id,
+                commitment_before,
+            )
+            .await?;
+        }
+        transaction.commit().await?;
+        match fulfills_ids.pop() {
+            Some(fulfills_id) => {
+                bus.publish_commitment_update(pool, &fulfills_id).await?;
+                Ok(1)
+            }
+            None => Ok(0),
+        }
+    }
+
+    #[graphql(guard = "CanEdit::Commitment(&id)")]
+    async fn finish_commitment<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        id: String,
+        finished: bool,
+    ) -> Result<i32> {
+        let pool = context.data::<SqlitePool>()?;
+        let bus = context.data::<ChangeBus>()?;
+        let mut transaction = pool.begin().await?;
+        let before = audit::snapshot(&mut transaction, AuditEntity::Commitment, &id).await?;
+        let result = sqlx::query("UPDATE commitments SET finished = ? WHERE id = ?")
+            .bind(finished)
+            .bind(&id)
+            .execute(&mut transaction)
+            .await?;
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::Commitment,
+            &id,
+            before,
+        )
+        .await?;
+        transaction.commit().await?;
+        bus.publish_commitment_update(pool, &id).await?;
+        Ok(result.rows_affected() as i32)
+    }
+
+    #[graphql(guard = "LoggedIn")]
+    async fn create_intent<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        new_intent: NewIntent,
+    ) -> Result<Intent> {
+        let pool = context.data::<SqlitePool>()?;
+        validate(pool, "newIntent", &new_intent).await?;
+        let ulid = Ulid::new().to_string();
+        let mut transaction = pool.begin().await?;
+        let inserted_intent = sqlx::query(
+            "
+            INSERT INTO intents (id, name, note, action_id, provider_id, receiver_id, resource_specification_id, resource_quantity, unit_id, due_at, at_location_id)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            RETURNING *
+            ",
+        )
+        .bind(&ulid)
+        .bind(new_intent.name)
+        .bind(new_intent.note)
+        .bind(new_intent.action_id)
+        .bind(new_intent.provider_id)
+        .bind(new_intent.receiver_id)
+        .bind(new_intent.resource_specification_id)
+        .bind(new_intent.resource_quantity)
+        .bind(new_intent.unit_id)
+        .bind(new_intent.due_at)
+        .bind(new_intent.at_location_id)
+        .map(Intent::from_row)
+        .fetch_one(&mut transaction)
+        .await?;
+        audit::record(&mut transaction, context, AuditEntity::Intent, &ulid, None).await?;
+        transaction.commit().await?;
+        let mut intents = vec![inserted_intent];
+        load_intent_relations(pool, &mut intents).await?;
+        Ok(intents.remove(0))
+    }
+
+    #[graphql(guard = "CanEdit::Intent(&id)")]
+    async fn delete_intent<'ctx>(&self, context: &Context<'ctx>, id: String) -> Result<i32> {
+        let pool = context.data::<SqlitePool>()?;
+        let mut transaction = pool.begin().await?;
+        let before = audit::snapshot(&mut transaction, AuditEntity::Intent, &id).await?;
+        sqlx::query("DELETE FROM proposed_intents WHERE intent_id = ?")
+            .bind(&id)
+            .execute(&mut transaction)
+            .await?;
+        let result = sqlx::query("DELETE FROM intents WHERE id = ?")
+            .bind(&id)
+            .execute(&mut transaction)
+            .await?;
+        audit::record(&mut transaction, context, AuditEntity::Intent, &id, before).await?;
+        transaction.commit().await?;
         Ok(result.rows_affected() as i32)
     }
 
-    async fn delete_process<'ctx>(
+    #[graphql(guard = "new_proposal.editors()")]
+    async fn create_proposal<'ctx>(
         &self,
         context: &Context<'ctx>,
-        process_id: String,
-    ) -> Result<i32> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
+        new_proposal: NewProposal,
+    ) -> Result<Proposal> {
+        let pool = context.data::<SqlitePool>()?;
+        validate(pool, "newProposal", &new_proposal).await?;
+        let ulid = Ulid::new().to_string();
         let mut transaction = pool.begin().await?;
-        sqlx::query!(
-            "DELETE FROM process_labels WHERE process_id = ?",
-            process_id
+        let inserted_proposal = sqlx::query(
+            "
+            INSERT INTO proposals (id, name, note, unit_based, has_beginning, has_end)
+            VALUES (?, ?, ?, ?, ?, ?)
+            RETURNING *
+            ",
         )
-        .execute(&mut transaction)
+        .bind(&ulid)
+        .bind(new_proposal.name)
+        .bind(new_proposal.note)
+        .bind(new_proposal.unit_based.unwrap_or(false))
+        .bind(new_proposal.has_beginning)
+        .bind(new_proposal.has_end)
+        .map(Proposal::from_row)
+        .fetch_one(&mut transaction)
         .await?;
-        sqlx::query!(
-            "DELETE FROM process_agents WHERE process_id = ?",
-            process_id
+        let proposed_intents = new_proposal
+            .publishes
+            .iter()
+            .map(|intent_id| (intent_id, false))
+            .chain(
+                new_proposal
+                    .reciprocal
+                    .iter()
+                    .flatten()
+                    .map(|intent_id| (intent_id, true)),
+            );
+        for (intent_id, reciprocal) in proposed_intents {
+            sqlx::query(
+                "INSERT INTO proposed_intents (proposal_id, intent_id, reciprocal) VALUES (?, ?, ?)",
+            )
+            .bind(&ulid)
+            .bind(intent_id)
+            .bind(reciprocal)
+            .execute(&mut transaction)
+            .await?;
+        }
+        for agent_id in new_proposal.proposed_to.iter().flatten() {
+            sqlx::query("INSERT INTO proposed_to (proposal_id, agent_id) VALUES (?, ?)")
+                .bind(&ulid)
+                .bind(agent_id)
+                .execute(&mut transaction)
+                .await?;
+        }
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::Proposal,
+            &ulid,
+            None,
         )
-        .execute(&mut transaction)
         .await?;
-        let result = sqlx::query!("DELETE FROM processes WHERE id = ?", process_id)
+        transaction.commit().await?;
+        let mut proposals = vec![inserted_proposal];
+        load_proposal_relations(pool, &mut proposals).await?;
+        Ok(proposals.remove(0))
+    }
+
+    #[graphql(guard = "CanEdit::Proposal(&id)")]
+    async fn delete_proposal<'ctx>(&self, context: &Context<'ctx>, id: String) -> Result<i32> {
+        let pool = context.data::<SqlitePool>()?;
+        let mut transaction = pool.begin().await?;
+        let before = audit::snapshot(&mut transaction, AuditEntity::Proposal, &id).await?;
+        sqlx::query("DELETE FROM proposed_intents WHERE proposal_id = ?")
+            .bind(&id)
+            .execute(&mut transaction)
+            .await?;
+        sqlx::query("DELETE FROM proposed_to WHERE proposal_id = ?")
+            .bind(&id)
             .execute(&mut transaction)
             .await?;
+        let result = sqlx::query("DELETE FROM proposals WHERE id = ?")
+            .bind(&id)
+            .execute(&mut transaction)
+            .await?;
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::Proposal,
+            &id,
+            before,
+        )
+        .await?;
         transaction.commit().await?;
         Ok(result.rows_affected() as i32)
     }
 
-    async fn create_resource_specification<'ctx>(
+    #[graphql(
+        guard = "CanEdit::Intent(&new_satisfaction.satisfies_id).and(CanEdit::Commitment(&new_satisfaction.satisfied_by_id))"
+    )]
+    async fn create_satisfaction<'ctx>(
         &self,
         context: &Context<'ctx>,
-        new_resource_specification: NewResourceSpecification,
-    ) -> Result<ResourceSpecification> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
+        new_satisfaction: NewSatisfaction,
+    ) -> Result<Satisfaction> {
+        let pool = context.data::<SqlitePool>()?;
+        validate(pool, "newSatisfaction", &new_satisfaction).await?;
         let ulid = Ulid::new().to_string();
-        let unique_name: String = unique_name(&new_resource_specification.name);
-        let inserted_resource_specification = sqlx::query_as!(
-            ResourceSpecification,
-            "INSERT INTO resource_specifications (id, name, unique_name)
-                VALUES (?, ?, ?)
-                RETURNING *",
-            ulid,
-            new_resource_specification.name,
-            unique_name
+        let mut transaction = pool.begin().await?;
+        // without explicit quantities the whole commitment counts towards the intent
+        let inserted_satisfaction = sqlx::query(
+            "
+            INSERT INTO satisfactions (id, satisfies_id, satisfied_by_id, resource_quantity, effort_quantity, note)
+            SELECT ?, ?, id, CASE WHEN ? IS NULL AND ? IS NULL THEN quantity ELSE ? END, ?, ?
+            FROM commitments
+            WHERE id = ?
+            RETURNING *
+            ",
         )
-        .fetch_one(pool)
+        .bind(&ulid)
+        .bind(&new_satisfaction.satisfies_id)
+        .bind(new_satisfaction.resource_quantity)
+        .bind(new_satisfaction.effort_quantity)
+        .bind(new_satisfaction.resource_quantity)
+        .bind(new_satisfaction.effort_quantity)
+        .bind(new_satisfaction.note)
+        .bind(&new_satisfaction.satisfied_by_id)
+        .map(Satisfaction::from_row)
+        .fetch_one(&mut transaction)
         .await?;
-        Ok(inserted_resource_specification)
+        let intent_before = audit::snapshot(
+            &mut transaction,
+            AuditEntity::Intent,
+            &new_satisfaction.satisfies_id,
+        )
+        .await?;
+        if satisfactions_cover(&mut transaction, &new_satisfaction.satisfies_id).await? {
+            sqlx::query("UPDATE intents SET finished = TRUE WHERE id = ?")
+                .bind(&new_satisfaction.satisfies_id)
+                .execute(&mut transaction)
+                .await?;
+        }
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::Satisfaction,
+            &ulid,
+            None,
+        )
+        .await?;
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::Intent,
+            &new_satisfaction.satisfies_id,
+            intent_before,
+        )
+        .await?;
+        transaction.commit().await?;
+        Ok(inserted_satisfaction)
     }
 
-    async fn delete_resource_specification<'ctx>(
+    #[graphql(guard = "LoggedIn")]
+    async fn create_agreement<'ctx>(
         &self,
         context: &Context<'ctx>,
-        unique_name: String,
-    ) -> Result<i32> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
-        let result = sqlx::query!(
-            "DELETE FROM resource_specifications WHERE unique_name = ?",
-            unique_name
+        new_agreement: NewAgreement,
+    ) -> Result<Agreement> {
+        let pool = context.data::<SqlitePool>()?;
+        let ulid = Ulid::new().to_string();
+        let mut transaction = pool.begin().await?;
+        let inserted_agreement =
+            sqlx::query("INSERT INTO agreements (id, name, note) VALUES (?, ?, ?) RETURNING *")
+                .bind(&ulid)
+                .bind(new_agreement.name)
+                .bind(new_agreement.note)
+                .map(Agreement::from_row)
+                .fetch_one(&mut transaction)
+                .await?;
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::Agreement,
+            &ulid,
+            None,
         )
-        .execute(pool)
         .await?;
-        Ok(result.rows_affected() as i32)
+        transaction.commit().await?;
+        Ok(inserted_agreement)
     }
 
-    async fn create_commitment<'ctx>(
+    /// Accepts a proposal on behalf of `agent_id`: every proposed intent becomes a commitment
+    /// of the new agreement, with the accepting agent taking the side the intent left open.
+    /// For unit based proposals `quantity` multiplies the quantities of the intents. The
+    /// commitments are checked like those of `createCommitment`, so every intent needs a
+    /// resource specification, a unit and a positive quantity.
+    /// Either the proposer or the accepting agent can make the agreement.
+    #[graphql(guard = "AnyOf(vec![CanEdit::Proposal(&proposal_id), CanEdit::Agent(&agent_id)])")]
+    async fn create_agreement_from_proposal<'ctx>(
         &self,
         context: &Context<'ctx>,
-        new_commitment: NewCommitment,
-    ) -> Result<Commitment> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
+        proposal_id: String,
+        agent_id: String,
+        quantity: Option<Decimal>,
+    ) -> Result<Agreement> {
+        let pool = context.data::<SqlitePool>()?;
+        let bus = context.data::<ChangeBus>()?;
+        if let Some(false) = quantity.map(|quantity| quantity.is_positive()) {
+            return Err(DomainError::validation("quantity must be positive"));
+        }
+        let mut transaction = pool.begin().await?;
+        let proposal = sqlx::query("SELECT * FROM proposals WHERE id = ?")
+            .bind(&proposal_id)
+            .map(Proposal::from_row)
+            .fetch_one(&mut transaction)
+            .await?;
+        let intents = sqlx::query(
+            "
+            SELECT intents.*
+            FROM intents
+            JOIN proposed_intents
+            ON proposed_intents.intent_id = intents.id
+            WHERE proposed_intents.proposal_id = ?
+            ORDER BY proposed_intents.reciprocal, proposed_intents.id
+            ",
+        )
+        .bind(&proposal.id)
+        .map(Intent::from_row)
+        .fetch_all(&mut transaction)
+        .await?;
+        if let Some(intent) = intents.iter().find(|i| i.finished) {
+            return Err(DomainError::conflict(format!(
+                "intent {} of proposal {} is already satisfied",
+                intent.id, proposal.id
+            )));
+        }
+        let multiplier = if proposal.unit_based {
+            quantity.unwrap_or(Decimal::ONE)
+        } else {
+            Decimal::ONE
+        };
+        let ulid = Ulid::new().to_string();
+        let agreement =
+            sqlx::query("INSERT INTO agreements (id, name, note) VALUES (?, ?, ?) RETURNING *")
+                .bind(&ulid)
+                .bind(&proposal.name)
+                .bind(&proposal.note)
+                .map(Agreement::from_row)
+                .fetch_one(&mut transaction)
+                .await?;
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::Agreement,
+            &ulid,
+            None,
+        )
+        .await?;
+        let mut commitments = vec![];
+        for intent in intents {
+            let provider_id = intent.provider_id.unwrap_or_else(|| agent_id.clone());
+            let commitment_quantity = intent.resource_quantity.unwrap_or_default() * multiplier;
+            let (resource_specification_id, unit_id) =
+                match (intent.resource_specification_id, intent.unit_id) {
+                    (Some(resource_specification_id), Some(unit_id)) => {
+                        (resource_specification_id, unit_id)
+                    }
+                    _ => {
+                        return Err(DomainError::validation(format!(
+                            "intent {} needs a resource specification and a unit",
+                            intent.id
+                        )))
+                    }
+                };
+            let new_commitment = NewCommitment {
+                description: intent
+                    .name
+                    .or(intent.note)
+                    .unwrap_or_else(|| proposal.name.clone()),
+                process_id: None,
+                action_id: intent.action_id,
+                assigned_agent_id: Some(provider_id.clone()),
+                provider_id: Some(provider_id),
+                receiver_id: Some(intent.receiver_id.unwrap_or_else(|| agent_id.clone())),
+                clause_of_id: Some(agreement.id.clone()),
+                resource_specification_id,
+                quantity: commitment_quantity,
+                unit_id,
+                due_at: intent.due_at,
+                at_location_id: intent.at_location_id,
+            };
+            let commitment =
+                insert_commitment(&mut transaction, context, "proposedIntent", new_commitment)
+                    .await?;
+            let satisfaction_id = Ulid::new().to_string();
+            sqlx::query(
+                "
+                INSERT INTO satisfactions (id, satisfies_id, satisfied_by_id, resource_quantity)
+                VALUES (?, ?, ?, ?)
+                ",
+            )
+            .bind(&satisfaction_id)
+            .bind(&intent.id)
+            .bind(&commitment.id)
+            .bind(commitment_quantity)
+            .execute(&mut transaction)
+            .await?;
+            audit::record(
+                &mut transaction,
+                context,
+                AuditEntity::Satisfaction,
+                &satisfaction_id,
+                None,
+            )
+            .await?;
+            commitments.push(commitment);
+            let intent_before =
+                audit::snapshot(&mut transaction, AuditEntity::Intent, &intent.id).await?;
+            if satisfactions_cover(&mut transaction, &intent.id).await? {
+                sqlx::query("UPDATE intents SET finished = TRUE WHERE id = ?")
+                    .bind(&intent.id)
+                    .execute(&mut transaction)
+                    .await?;
+            }
+            audit::record(
+                &mut transaction,
+                context,
+                AuditEntity::Intent,
+                &intent.id,
+                intent_before,
+            )
+            .await?;
+        }
+        transaction.commit().await?;
+        bus.publish_created_commitments(commitments);
+        let mut agreements = vec![agreement];
+        load_agreement_relations(pool, &mut agreements).await?;
+        Ok(agreements.remove(0))
+    }
+
+    #[graphql(guard = "LoggedIn")]
+    async fn create_process_specification<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        new_process_specification: NewProcessSpecification,
+    ) -> Result<ProcessSpecification> {
+        let pool = context.data::<SqlitePool>()?;
+        let ulid = Ulid::new().to_string();
+        let mut transaction = pool.begin().await?;
+        let inserted_process_specification = sqlx::query(
+            "INSERT INTO process_specifications (id, name, note) VALUES (?, ?, ?) RETURNING *",
+        )
+        .bind(&ulid)
+        .bind(new_process_specification.name)
+        .bind(new_process_specification.note)
+        .map(ProcessSpecification::from_row)
+        .fetch_one(&mut transaction)
+        .await?;
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::ProcessSpecification,
+            &ulid,
+            None,
+        )
+        .await?;
+        transaction.commit().await?;
+        Ok(inserted_process_specification)
+    }
+
+    #[graphql(guard = "LoggedIn")]
+    async fn create_recipe_resource<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        new_recipe_resource: NewRecipeResource,
+    ) -> Result<RecipeResource> {
+        let pool = context.data::<SqlitePool>()?;
+        validate(pool, "newRecipeResource", &new_recipe_resource).await?;
         let ulid = Ulid::new().to_string();
-        // TODO put those in a transaction
-        let mut inserted_commitment= sqlx::query(
+        let mut transaction = pool.begin().await?;
+        let inserted_recipe_resource = sqlx::query(
             "
-            INSERT INTO commitments (id, description, process_id, action_id, assigned_agent_id, quantity, unit_id, resource_specification_id, due_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO recipe_resources (id, name, note, resource_specification_id, unit_id)
+            VALUES (?, ?, ?, ?, ?)
             RETURNING *
             ",
         )
         .bind(&ulid)
-        .bind(new_commitment.description)
-        .bind(new_commitment.process_id)
-        .bind(new_commitment.action_id)
-        .bind(new_commitment.assigned_agent_id)
-        .bind(new_commitment.quantity)
-        .bind(new_commitment.unit_id)
-        .bind(new_commitment.resource_specification_id)
-        .bind(new_commitment.due_at)
-        .map(Commitment::from_row)
-        .fetch_one(pool)
+        .bind(new_recipe_resource.name)
+        .bind(new_recipe_resource.note)
+        .bind(new_recipe_resource.resource_specification_id)
+        .bind(new_recipe_resource.unit_id)
+        .map(RecipeResource::from_row)
+        .fetch_one(&mut transaction)
         .await?;
-        let action = sqlx::query_as::<_, Action>(
-            "
-           SELECT *
-           FROM actions 
-           WHERE actions.id = ?
-           ",
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::RecipeResource,
+            &ulid,
+            None,
         )
-        .bind(&inserted_commitment.action_id)
-        .fetch_one(pool)
         .await?;
-        inserted_commitment.action = Some(action);
-        let unit = sqlx::query_as::<_, Unit>(
+        transaction.commit().await?;
+        Ok(inserted_recipe_resource)
+    }
+
+    #[graphql(guard = "LoggedIn")]
+    async fn create_recipe<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        new_recipe: NewRecipe,
+    ) -> Result<Recipe> {
+        let pool = context.data::<SqlitePool>()?;
+        validate(pool, "newRecipe", &new_recipe).await?;
+        let ulid = Ulid::new().to_string();
+        let mut transaction = pool.begin().await?;
+        let inserted_recipe = sqlx::query(
+            "INSERT INTO recipes (id, name, note, primary_output_id) VALUES (?, ?, ?, ?) RETURNING *",
+        )
+        .bind(&ulid)
+        .bind(new_recipe.name)
+        .bind(new_recipe.note)
+        .bind(new_recipe.primary_output_id)
+        .map(Recipe::from_row)
+        .fetch_one(&mut transaction)
+        .await?;
+        audit::record(&mut transaction, context, AuditEntity::Recipe, &ulid, None).await?;
+        transaction.commit().await?;
+        let mut recipes = vec![inserted_recipe];
+        load_recipe_relations(pool, &mut recipes).await?;
+        Ok(recipes.remove(0))
+    }
+
+    #[graphql(guard = "LoggedIn")]
+    async fn create_recipe_process<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        new_recipe_process: NewRecipeProcess,
+    ) -> Result<RecipeProcess> {
+        let pool = context.data::<SqlitePool>()?;
+        validate(pool, "newRecipeProcess", &new_recipe_process).await?;
+        let ulid = Ulid::new().to_string();
+        let mut transaction = pool.begin().await?;
+        let inserted_recipe_process = sqlx::query(
             "
-            SELECT *
-            FROM units 
-            WHERE units.id = ?
+            INSERT INTO recipe_processes (id, recipe_id, name, note, process_specification_id, has_duration_seconds)
+            VALUES (?, ?, ?, ?, ?, ?)
+            RETURNING *
             ",
         )
-        .bind(&inserted_commitment.unit_id)
-        .fetch_one(pool)
+        .bind(&ulid)
+        .bind(new_recipe_process.recipe_id)
+        .bind(new_recipe_process.name)
+        .bind(new_recipe_process.note)
+        .bind(new_recipe_process.process_specification_id)
+        .bind(new_recipe_process.has_duration_seconds)
+        .map(RecipeProcess::from_row)
+        .fetch_one(&mut transaction)
         .await?;
-        inserted_commitment.unit = Some(unit);
-        let resource_specification = sqlx::query_as!(
-            ResourceSpecification,
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::RecipeProcess,
+            &ulid,
+            None,
+        )
+        .await?;
+        transaction.commit().await?;
+        Ok(inserted_recipe_process)
+    }
+
+    #[graphql(guard = "LoggedIn")]
+    async fn create_recipe_exchange<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        new_recipe_exchange: NewRecipeExchange,
+    ) -> Result<RecipeExchange> {
+        let pool = context.data::<SqlitePool>()?;
+        validate(pool, "newRecipeExchange", &new_recipe_exchange).await?;
+        let ulid = Ulid::new().to_string();
+        let mut transaction = pool.begin().await?;
+        let inserted_recipe_exchange = sqlx::query(
+            "INSERT INTO recipe_exchanges (id, recipe_id, name, note) VALUES (?, ?, ?, ?) RETURNING *",
+        )
+        .bind(&ulid)
+        .bind(new_recipe_exchange.recipe_id)
+        .bind(new_recipe_exchange.name)
+        .bind(new_recipe_exchange.note)
+        .map(RecipeExchange::from_row)
+        .fetch_one(&mut transaction)
+        .await?;
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::RecipeExchange,
+            &ulid,
+            None,
+        )
+        .await?;
+        transaction.commit().await?;
+        Ok(inserted_recipe_exchange)
+    }
+
+    /// A recipe flow belongs to exactly one of a recipe process (as input or output)
+    /// or a recipe exchange.
+    #[graphql(guard = "LoggedIn")]
+    async fn create_recipe_flow<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        new_recipe_flow: NewRecipeFlow,
+    ) -> Result<RecipeFlow> {
+        let pool = context.data::<SqlitePool>()?;
+        validate(pool, "newRecipeFlow", &new_recipe_flow).await?;
+        let ulid = Ulid::new().to_string();
+        let mut transaction = pool.begin().await?;
+        let inserted_recipe_flow = sqlx::query(
             "
-           SELECT *
-           FROM resource_specifications
-           WHERE resource_specifications.id = ?
-           ",
-            inserted_commitment.resource_specification_id
+            INSERT INTO recipe_flows (id, note, action_id, recipe_flow_resource_id, resource_quantity, unit_id, recipe_input_of_id, recipe_output_of_id, recipe_clause_of_id)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            RETURNING *
+            ",
         )
-        .fetch_one(pool)
+        .bind(&ulid)
+        .bind(new_recipe_flow.note)
+        .bind(new_recipe_flow.action_id)
+        .bind(new_recipe_flow.recipe_flow_resource_id)
+        .bind(new_recipe_flow.resource_quantity)
+        .bind(new_recipe_flow.unit_id)
+        .bind(new_recipe_flow.recipe_input_of_id)
+        .bind(new_recipe_flow.recipe_output_of_id)
+        .bind(new_recipe_flow.recipe_clause_of_id)
+        .map(RecipeFlow::from_row)
+        .fetch_one(&mut transaction)
         .await?;
-        inserted_commitment.resource_specification = Some(resource_specification);
-        if let Some(assigned_agent_id) = &inserted_commitment.assigned_agent_id {
-            let assigned_agent = sqlx::query_as::<_, Agent>(
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::RecipeFlow,
+            &ulid,
+            None,
+        )
+        .await?;
+        transaction.commit().await?;
+        Ok(inserted_recipe_flow)
+    }
+
+    /// Creates a plan delivering `quantity` of the primary output of a recipe by `due_at`.
+    /// Recipe processes are back-scheduled from `due_at` and every process flow becomes a
+    /// commitment, inputs due when their process starts and outputs when it ends. Flow
+    /// quantities are scaled to the requested quantity. Exchanges are not planned.
+    #[graphql(guard = "IfGiven(agent_id.as_deref().map(CanEdit::Agent))")]
+    async fn create_plan_from_recipe<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        recipe_id: String,
+        quantity: Decimal,
+        due_at: DateTime,
+        agent_id: Option<String>,
+    ) -> Result<Plan> {
+        let pool = context.data::<SqlitePool>()?;
+        let bus = context.data::<ChangeBus>()?;
+        let mut recipes = sqlx::query("SELECT * FROM recipes WHERE id = ?")
+            .bind(&recipe_id)
+            .map(Recipe::from_row)
+            .fetch_all(pool)
+            .await?;
+        load_recipe_relations(pool, &mut recipes).await?;
+        let recipe = recipes
+            .first()
+            .ok_or_else(|| DomainError::not_found(format!("recipe {}", recipe_id)))?;
+        let factor = planning::scale_factor(recipe, quantity, None, &HashMap::new())?;
+        let schedule = planning::back_schedule(recipe, due_at)?;
+        let ulid = Ulid::new().to_string();
+        let mut transaction = pool.begin().await?;
+        let plan =
+            sqlx::query("INSERT INTO plans (id, title, description) VALUES (?, ?, ?) RETURNING *")
+                .bind(&ulid)
+                .bind(&recipe.name)
+                .bind(&recipe.note)
+                .map(Plan::from_row)
+                .fetch_one(&mut transaction)
+                .await?;
+        if let Some(agent_id) = &agent_id {
+            sqlx::query("INSERT INTO plan_agents (plan_id, agent_id) VALUES (?, ?)")
+                .bind(&ulid)
+                .bind(agent_id)
+                .execute(&mut transaction)
+                .await?;
+        }
+        audit::record(&mut transaction, context, AuditEntity::Plan, &ulid, None).await?;
+        let commitments = insert_schedule(
+            &mut transaction,
+            context,
+            &ulid,
+            &schedule,
+            factor,
+            &agent_id,
+        )
+        .await?;
+        transaction.commit().await?;
+        bus.publish_created_commitments(commitments);
+        bus.publish(Change::Plan(ulid));
+        Ok(plan)
+    }
+
+    /// Explodes the demand of a commitment or intent for a resource specification into the
+    /// processes and commitments producing it, netting out the on-hand inventory of `agent_id`,
+    /// or of the receiver of the demand, at every level. Generated processes go to `plan_id`,
+    /// the plan of the commitment's process, or a new plan when neither exists. A demand is
+    /// exploded into a plan once; exploding it again is a conflict.
+    #[graphql(
+        guard = "IfGiven(commitment_id.as_deref().map(CanEdit::Commitment)).and(IfGiven(intent_id.as_deref().map(CanEdit::Intent))).and(IfGiven(plan_id.as_deref().map(CanEdit::Plan))).and(IfGiven(agent_id.as_deref().map(CanEdit::Agent)))"
+    )]
+    async fn explode_demand<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        commitment_id: Option<String>,
+        intent_id: Option<String>,
+        plan_id: Option<String>,
+        agent_id: Option<String>,
+    ) -> Result<DemandExplosion> {
+        let pool = context.data::<SqlitePool>()?;
+        let bus = context.data::<ChangeBus>()?;
+        let exploded_into = |row: &SqliteRow| row.get::<Option<String>, _>("exploded_into_plan_id");
+        let (demand_entity, demand_id, exploded_into_plan_id, demand) =
+            match (commitment_id, intent_id) {
+                (Some(commitment_id), None) => {
+                    let (exploded_into_plan_id, commitment) =
+                        sqlx::query("SELECT * FROM commitments WHERE id = ?")
+                            .bind(&commitment_id)
+                            .map(|row: SqliteRow| (exploded_into(&row), Commitment::from_row(row)))
+                            .fetch_optional(pool)
+                            .await?
+                            .ok_or_else(|| {
+                                DomainError::not_found(format!("commitment {}", commitment_id))
+                            })?;
+                    let demand = (
+                        commitment.resource_specification_id,
+                        commitment.quantity,
+                        commitment.unit_id,
+                        commitment.due_at,
+                        commitment.process_id,
+                        commitment.receiver_id,
+                    );
+                    (
+                        AuditEntity::Commitment,
+                        commitment_id,
+                        exploded_into_plan_id,
+                        demand,
+                    )
+                }
+                (None, Some(intent_id)) => {
+                    let (exploded_into_plan_id, intent) =
+                        sqlx::query("SELECT * FROM intents WHERE id = ?")
+                            .bind(&intent_id)
+                            .map(|row: SqliteRow| (exploded_into(&row), Intent::from_row(row)))
+                            .fetch_optional(pool)
+                            .await?
+                            .ok_or_else(|| {
+                                DomainError::not_found(format!("intent {}", intent_id))
+                            })?;
+                    let demand = (
+                        intent.resource_specification_id,
+                        intent.resource_quantity.unwrap_or_default(),
+                        intent.unit_id,
+                        intent.due_at,
+                        None,
+                        intent.receiver_id,
+                    );
+                    (
+                        AuditEntity::Intent,
+                        intent_id,
+                        exploded_into_plan_id,
+                        demand,
+                    )
+                }
+                _ => {
+                    return Err(DomainError::validation(
+                        "exactly one of commitmentId or intentId is required",
+                    ))
+                }
+            };
+        if let Some(plan_id) = exploded_into_plan_id {
+            return Err(DomainError::conflict(format!(
+                "the demand was already exploded into plan {}",
+                plan_id
+            )));
+        }
+        let (resource_specification_id, quantity, unit_id, due_at, process_id, receiver_id) =
+            demand;
+        let resource_specification_id = resource_specification_id
+            .ok_or_else(|| DomainError::validation("the demand has no resource specification"))?;
+        let due_at = due_at.ok_or_else(|| DomainError::validation("the demand has no due date"))?;
+        let net_demands = planning::explode_demand(
+            pool,
+            planning::Demand {
+                resource_specification_id: resource_specification_id.clone(),
+                quantity,
+                unit_id,
+                due_at,
+            },
+            agent_id.as_deref().or(receiver_id.as_deref()),
+        )
+        .await?;
+
+        let mut plan = None;
+        let mut commitments = vec![];
+        let mut transaction = pool.begin().await?;
+        if net_demands.iter().any(|d| d.recipe.is_some()) {
+            let plan_id = match plan_id {
+                Some(plan_id) => Some(plan_id),
+                None => sqlx::query("SELECT plan_id FROM processes WHERE id = ?")
+                    .bind(process_id)
+                    .map(|row| row.get("plan_id"))
+                    .fetch_optional(&mut transaction)
+                    .await?
+                    .flatten(),
+            };
+            let demand_plan = match plan_id {
+                Some(plan_id) => {
+                    sqlx::query("SELECT * FROM plans WHERE id = ?")
+                        .bind(plan_id)
+                        .map(Plan::from_row)
+                        .fetch_one(&mut transaction)
+                        .await?
+                }
+                None => {
+                    let ulid = Ulid::new().to_string();
+                    let inserted_plan = sqlx::query(
+                        "
+                        INSERT INTO plans (id, title)
+                        SELECT ?, 'Demand for ' || name
+                        FROM resource_specifications
+                        WHERE id = ?
+                        RETURNING *
+                        ",
+                    )
+                    .bind(&ulid)
+                    .bind(&resource_specification_id)
+                    .map(Plan::from_row)
+                    .fetch_one(&mut transaction)
+                    .await?;
+                    if let Some(agent_id) = &agent_id {
+                        sqlx::query("INSERT INTO plan_agents (plan_id, agent_id) VALUES (?, ?)")
+                            .bind(&ulid)
+                            .bind(agent_id)
+                            .execute(&mut transaction)
+                            .await?;
+                    }
+                    audit::record(&mut transaction, context, AuditEntity::Plan, &ulid, None)
+                        .await?;
+                    inserted_plan
+                }
+            };
+            let before = audit::snapshot(&mut transaction, demand_entity, &demand_id).await?;
+            let exploded = sqlx::query(&format!(
                 "
-           SELECT *
-           FROM agents
-           WHERE agents.id = ?
-           ",
-            )
-            .bind(assigned_agent_id)
-            .fetch_one(pool)
+                UPDATE {} SET exploded_into_plan_id = ?
+                WHERE id = ? AND exploded_into_plan_id IS NULL
+                ",
+                demand_entity.table()
+            ))
+            .bind(&demand_plan.id)
+            .bind(&demand_id)
+            .execute(&mut transaction)
             .await?;
-            inserted_commitment.assigned_agent = Some(assigned_agent);
+            if exploded.rows_affected() == 0 {
+                return Err(DomainError::conflict(
+                    "the demand was already exploded into a plan",
+                ));
+            }
+            audit::record(&mut transaction, context, demand_entity, &demand_id, before).await?;
+            let units = measure::units_by_id(&mut transaction).await?;
+            for net_demand in net_demands.iter() {
+                if let Some(recipe) = &net_demand.recipe {
+                    let factor = planning::scale_factor(
+                        recipe,
+                        net_demand.net_quantity,
+                        net_demand.demand.unit_id.as_deref(),
+                        &units,
+                    )?;
+                    let schedule = planning::back_schedule(recipe, net_demand.demand.due_at)?;
+                    commitments.extend(
+                        insert_schedule(
+                            &mut transaction,
+                            context,
+                            &demand_plan.id,
+                            &schedule,
+                            factor,
+                            &agent_id,
+                        )
+                        .await?,
+                    );
+                }
+            }
+            plan = Some(demand_plan);
         }
-        Ok(inserted_commitment)
+        transaction.commit().await?;
+        bus.publish_created_commitments(commitments);
+        if let Some(plan) = &plan {
+            bus.publish(Change::Plan(plan.id.clone()));
+        }
+        let requirements = net_demands
+            .into_iter()
+            .map(|d| Requirement {
+                resource_specification_id: d.demand.resource_specification_id,
+                quantity: d.demand.quantity,
+                on_hand_quantity: d.on_hand_quantity,
+                net_quantity: d.net_quantity,
+                due_at: d.demand.due_at,
+                recipe_id: d.recipe.map(|r| r.id),
+            })
+            .collect();
+        Ok(DemandExplosion { plan, requirements })
     }
 
-    async fn update_commitment<'ctx>(
+    #[graphql(guard = "LoggedIn")]
+    async fn create_value_equation<'ctx>(
         &self,
         context: &Context<'ctx>,
-        update_commitment: UpdateCommitment,
-    ) -> Result<i32> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
-        let UpdateCommitment {
-            id,
-            description,
-            action_id,
-            quantity,
-            unit_id,
-            resource_specification_id,
-            assigned_agent_id,
-            due_at,
-        } = update_commitment;
-        let result = sqlx::query!(
-            "
-            UPDATE commitments
-            SET description = ?,
-                unit_id = ?,
-                action_id = ?,
-                resource_specification_id = ?,
-                quantity = ?,
-                assigned_agent_id = ?,
-                due_at = ?
-            WHERE id = ?",
-            description,
-            unit_id,
-            action_id,
-            resource_specification_id,
-            quantity,
-            assigned_agent_id,
-            due_at,
-            id
+        new_value_equation: NewValueEquation,
+    ) -> Result<ValueEquation> {
+        let pool = context.data::<SqlitePool>()?;
+        validate(pool, "newValueEquation", &new_value_equation).await?;
+        let ulid = Ulid::new().to_string();
+        let mut transaction = pool.begin().await?;
+        let inserted_value_equation = sqlx::query(
+            "INSERT INTO value_equations (id, name, note, context_agent_id) VALUES (?, ?, ?, ?) RETURNING *",
         )
-        .execute(pool)
+        .bind(&ulid)
+        .bind(new_value_equation.name)
+        .bind(new_value_equation.note)
+        .bind(new_value_equation.context_agent_id)
+        .map(ValueEquation::from_row)
+        .fetch_one(&mut transaction)
         .await?;
-        Ok(result.rows_affected() as i32)
+        for weight in new_value_equation.weights {
+            sqlx::query(
+                "
+                INSERT INTO value_equation_weights (id, value_equation_id, action_id, resource_specification_id, agent_relation_type_id, weight)
+                VALUES (?, ?, ?, ?, ?, ?)
+                ",
+            )
+            .bind(Ulid::new().to_string())
+            .bind(&ulid)
+            .bind(weight.action_id)
+            .bind(weight.resource_specification_id)
+            .bind(weight.agent_relation_type_id)
+            .bind(weight.weight)
+            .execute(&mut transaction)
+            .await?;
+        }
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::ValueEquation,
+            &ulid,
+            None,
+        )
+        .await?;
+        transaction.commit().await?;
+        let mut value_equations = vec![inserted_value_equation];
+        load_value_equation_relations(pool, &mut value_equations).await?;
+        Ok(value_equations.remove(0))
     }
 
-    async fn delete_commitment<'ctx>(&self, context: &Context<'ctx>, id: String) -> Result<i32> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
-        let result = sqlx::query!("DELETE FROM commitments WHERE id = ?", id)
-            .execute(pool)
+    #[graphql(guard = "CanEdit::ValueEquation(&id)")]
+    async fn delete_value_equation<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        id: String,
+    ) -> Result<i32> {
+        let pool = context.data::<SqlitePool>()?;
+        let mut transaction = pool.begin().await?;
+        let before = audit::snapshot(&mut transaction, AuditEntity::ValueEquation, &id).await?;
+        sqlx::query("DELETE FROM value_equation_weights WHERE value_equation_id = ?")
+            .bind(&id)
+            .execute(&mut transaction)
             .await?;
+        let result = sqlx::query("DELETE FROM value_equations WHERE id = ?")
+            .bind(&id)
+            .execute(&mut transaction)
+            .await?;
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::ValueEquation,
+            &id,
+            before,
+        )
+        .await?;
+        transaction.commit().await?;
         Ok(result.rows_affected() as i32)
     }
 
+    #[graphql(guard = "CanEdit::Relationship(&id)")]
     async fn delete_relationship<'ctx>(&self, context: &Context<'ctx>, id: String) -> Result<i32> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
+        let pool = context.data::<SqlitePool>()?;
+        let mut transaction = pool.begin().await?;
+        let before = audit::snapshot(&mut transaction, AuditEntity::AgentRelation, &id).await?;
         let result = sqlx::query!("DELETE FROM agent_relations WHERE id = ?", id)
-            .execute(pool)
+            .execute(&mut transaction)
             .await?;
+        audit::record(
+            &mut transaction,
+            context,
+            AuditEntity::AgentRelation,
+            &id,
+            before,
+        )
+        .await?;
+        transaction.commit().await?;
         Ok(result.rows_affected() as i32)
     }
 }
@@ -0,0 +1,232 @@
+use super::error::{DomainError, Result};
+use async_graphql::connection::{query, Connection, Edge, EmptyFields};
+use async_graphql::SimpleObject;
+use sqlx::{
+    sqlite::{SqlitePool, SqliteRow},
+    Row,
+};
+
+/// Page size when neither `first` nor `last` is given, and the largest page served.
+const MAX_PAGE_SIZE: usize = 100;
+
+#[derive(SimpleObject)]
+pub(super) struct TotalCount {
+    /// Number of nodes in the whole list, across all pages.
+    total_count: i64,
+}
+
+pub(super) type Page<T> = Connection<String, T, TotalCount, EmptyFields>;
+
+/// Rows of `table`, optionally joined to other tables, narrowed by conditions and sorted on
+/// a key of the table. Rows are newest first unless another order is given.
+pub(super) struct List<'a> {
+    table: &'a str,
+    join: &'a str,
+    conditions: Vec<String>,
+    values: Vec<String>,
+    order_key: String,
+    descending: bool,
+}
+
+impl<'a> List<'a> {
+    pub(super) fn table(table: &'a str) -> Self {
+        List {
+            table,
+            join: "",
+            conditions: vec![],
+            values: vec![],
+            order_key: format!("{}.id", table),
+            descending: true,
+        }
+    }
+
+    pub(super) fn join(self, join: &'a str) -> Self {
+        List { join, ..self }
+    }
+
+    /// Sorts on an SQL expression over the columns of the table. The expression must not be
+    /// NULL, rows with equal keys are sorted by id in the same direction.
+    pub(super) fn order_by(self, key: &str, descending: bool) -> Self {
+        List {
+            order_key: key.to_string(),
+            descending,
+            ..self
+        }
+    }
+
+    /// Keeps the rows matching `condition`, which has a `?` for each of `values`.
+    pub(super) fn filter<I>(&mut self, condition: &str, values: I)
+    where
+        I: IntoIterator<Item = String>,
+    {
+        self.conditions.push(format!("({})", condition));
+        self.values.extend(values);
+    }
+
+    fn where_clause(&self) -> String {
+        if self.conditions.is_empty() {
+            "TRUE".to_string()
+        } else {
+            self.conditions.join(" AND ")
+        }
+    }
+
+    /// Compares the sort key of a row with the key of the row a cursor points at.
+    fn cursor_condition(&self, comparison: &str) -> String {
+        format!(
+            "({key}, {table}.id) {comparison} (SELECT {key}, {table}.id FROM {table} WHERE {table}.id = ?)",
+            key = self.order_key,
+            table = self.table,
+            comparison = comparison,
+        )
+    }
+}
+
+/// One page of a list, before it is served as a connection.
+pub(super) struct PageRows<T> {
+    pub(super) nodes: Vec<T>,
+    cursors: Vec<String>,
+    has_previous_page: bool,
+    has_next_page: bool,
+    total_count: i64,
+}
+
+impl<T> PageRows<T> {
+    pub(super) fn into_connection(self) -> Page<T> {
+        let mut connection = Connection::with_additional_fields(
+            self.has_previous_page,
+            self.has_next_page,
+            TotalCount {
+                total_count: self.total_count,
+            },
+        );
+        connection.append(
+            self.cursors
+                .into_iter()
+                .zip(self.nodes)
+                .map(|(cursor, node)| Edge::new(cursor, node)),
+        );
+        connection
+    }
+}
+
+/// Reads the page of `list` selected by Relay arguments. Cursors are row ids, which as
+/// ULIDs stay valid while rows are added: `after` moves on towards the end of the list and
+/// `before` back towards its start.
+pub(super) async fn fetch_page<T, F>(
+    pool: &SqlitePool,
+    list: List<'_>,
+    after: Option<String>,
+    before: Option<String>,
+    first: Option<usize>,
+    last: Option<usize>,
+    from_row: F,
+) -> Result<PageRows<T>>
+where
+    F: Fn(SqliteRow) -> sqlx::Result<T>,
+{
+    if first.max(last).unwrap_or(0) > MAX_PAGE_SIZE {
+        return Err(DomainError::validation(format!(
+            "pages hold at most {} nodes",
+            MAX_PAGE_SIZE
+        )));
+    }
+    let count_sql = format!(
+        "SELECT COUNT(*) FROM {} {} WHERE {}",
+        list.table,
+        list.join,
+        list.where_clause()
+    );
+    let total_count: i64 = list
+        .values
+        .iter()
+        .fold(sqlx::query_scalar(&count_sql), |query, value| {
+            query.bind(value)
+        })
+        .fetch_one(pool)
+        .await?;
+
+    let (towards_end, towards_start) = if list.descending {
+        ("<", ">")
+    } else {
+        (">", "<")
+    };
+    let mut conditions = vec![list.where_clause()];
+    conditions.extend(after.iter().map(|_| list.cursor_condition(towards_end)));
+    conditions.extend(before.iter().map(|_| list.cursor_condition(towards_start)));
+    // `last` takes the nodes at the end of the window, so those are read in reverse
+    let from_end = last.is_some();
+    let direction = if list.descending == from_end {
+        "ASC"
+    } else {
+        "DESC"
+    };
+    let limit = first.or(last).unwrap_or(MAX_PAGE_SIZE);
+    let sql = format!(
+        "
+        SELECT {table}.* FROM {table} {join}
+        WHERE {conditions}
+        ORDER BY {key} {direction}, {table}.id {direction}
+        LIMIT ?
+        ",
+        table = list.table,
+        join = list.join,
+        conditions = conditions.join(" AND "),
+        key = list.order_key,
+        direction = direction,
+    );
+    let mut rows = list
+        .values
+        .iter()
+        .chain(after.iter())
+        .chain(before.iter())
+        .fold(sqlx::query(&sql), |query, value| query.bind(value))
+        .bind(limit as i64 + 1)
+        .fetch_all(pool)
+        .await?;
+    let has_more = rows.len() > limit;
+    rows.truncate(limit);
+    if from_end {
+        rows.reverse();
+    }
+    let cursors = rows.iter().map(|row| row.get("id")).collect();
+    let nodes = rows
+        .into_iter()
+        .map(from_row)
+        .collect::<sqlx::Result<Vec<_>>>()?;
+    // the far end of the window only has more nodes when a cursor bounds it
+    Ok(PageRows {
+        nodes,
+        cursors,
+        has_previous_page: if from_end { has_more } else { after.is_some() },
+        has_next_page: if from_end { before.is_some() } else { has_more },
+        total_count,
+    })
+}
+
+/// Serves a Relay connection over a list.
+pub(super) async fn paginate<T, F>(
+    pool: &SqlitePool,
+    list: List<'_>,
+    after: Option<String>,
+    before: Option<String>,
+    first: Option<i32>,
+    last: Option<i32>,
+    from_row: F,
+) -> Result<Page<T>>
+where
+    F: Fn(SqliteRow) -> sqlx::Result<T> + Send + Sync,
+    T: Send + Sync,
+{
+    Ok(query(
+        after,
+        before,
+        first,
+        last,
+        |after, before, first, last| async move {
+            let page = fetch_page(pool, list, after, before, first, last, from_row).await?;
+            Ok::<_, DomainError>(page.into_connection())
+        },
+    )
+    .await?)
+}
@@ -0,0 +1,369 @@
+use super::auth;
+use super::error::DomainError;
+use async_graphql::async_trait::async_trait;
+use async_graphql::{Context, Guard, Result};
+use sqlx::sqlite::SqlitePool;
+use std::fmt;
+
+/// Lets through agents taking part in what a mutation changes, directly or as an admin of an
+/// organization taking part in it. What no agent takes part in belongs to the agent that created
+/// it, and what does not exist is not found.
+pub(super) enum CanEdit<'a> {
+    /// The agent itself.
+    Agent(&'a str),
+    /// The agent itself and the organizations it is a member of through a membership relation
+    /// type. Admins of those organizations are let through only when they also administer
+    /// every organization the agent administers.
+    Member(&'a str),
+    /// Like `Member`, for the agent with this unique name.
+    MemberNamed(&'a str),
+    /// The agents of the plan.
+    Plan(&'a str),
+    /// The agents of the process and of its plan.
+    Process(&'a str),
+    /// Those who can edit the process of the commitment, or the agents of the commitment when
+    /// it is not part of a process.
+    Commitment(&'a str),
+    /// Those who can edit the commitment the fulfillment fulfills.
+    Fulfillment(&'a str),
+    /// The subject and the object of the relationship.
+    Relationship(&'a str),
+    /// The primary accountable of the economic resource.
+    Resource(&'a str),
+    /// The provider and the receiver of the intent.
+    Intent(&'a str),
+    /// The providers and receivers of the intents the proposal publishes.
+    Proposal(&'a str),
+    /// Nobody but the agent that created the label, the catalog being shared.
+    Label(&'a str),
+    /// Nobody but the agent that created the location.
+    Location(&'a str),
+    /// Nobody but the agent that created the resource specification with this unique name.
+    ResourceSpecificationNamed(&'a str),
+    /// Nobody but the agent that created the value equation.
+    ValueEquation(&'a str),
+}
+
+impl fmt::Display for CanEdit<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CanEdit::Agent(id) => write!(f, "agent {}", id),
+            CanEdit::Member(id) => write!(f, "agent {}", id),
+            CanEdit::MemberNamed(unique_name) => write!(f, "agent {}", unique_name),
+            CanEdit::Plan(id) => write!(f, "plan {}", id),
+            CanEdit::Process(id) => write!(f, "process {}", id),
+            CanEdit::Commitment(id) => write!(f, "commitment {}", id),
+            CanEdit::Fulfillment(id) => write!(f, "fulfillment {}", id),
+            CanEdit::Relationship(id) => write!(f, "relationship {}", id),
+            CanEdit::Resource(id) => write!(f, "economic resource {}", id),
+            CanEdit::Intent(id) => write!(f, "intent {}", id),
+            CanEdit::Proposal(id) => write!(f, "proposal {}", id),
+            CanEdit::Label(id) => write!(f, "label {}", id),
+            CanEdit::Location(id) => write!(f, "location {}", id),
+            CanEdit::ResourceSpecificationNamed(unique_name) => {
+                write!(f, "resource specification {}", unique_name)
+            }
+            CanEdit::ValueEquation(id) => write!(f, "value equation {}", id),
+        }
+    }
+}
+
+/// The agents of a process and of its plan. The process id is bound twice.
+const PROCESS_AGENTS: &str = "
+    SELECT agent_id FROM process_agents WHERE process_id = ?
+    UNION
+    SELECT agent_id FROM plan_agents
+    JOIN processes ON processes.plan_id = plan_agents.plan_id
+    WHERE processes.id = ?
+";
+
+/// The agents of the process of a commitment and of its plan, or of the commitment when it
+/// is not part of a process. The commitment id is bound three times.
+const COMMITMENT_AGENTS: &str = "
+    SELECT agent_id FROM process_agents
+    JOIN commitments ON commitments.process_id = process_agents.process_id
+    WHERE commitments.id = ?
+    UNION
+    SELECT agent_id FROM plan_agents
+    JOIN processes ON processes.plan_id = plan_agents.plan_id
+    JOIN commitments ON commitments.process_id = processes.id
+    WHERE commitments.id = ?
+    UNION
+    SELECT value FROM commitments, json_each(json_array(provider_id, receiver_id, assigned_agent_id))
+    WHERE commitments.id = ? AND process_id IS NULL AND value IS NOT NULL
+";
+
+impl CanEdit<'_> {
+    /// Whether what is changed exists.
+    async fn exists(&self, pool: &SqlitePool) -> sqlx::Result<bool> {
+        let (sql, id) = match self {
+            CanEdit::Agent(id) | CanEdit::Member(id) => ("SELECT 1 FROM agents WHERE id = ?", id),
+            CanEdit::MemberNamed(unique_name) => {
+                ("SELECT 1 FROM agents WHERE unique_name = ?", unique_name)
+            }
+            CanEdit::Plan(id) => ("SELECT 1 FROM plans WHERE id = ?", id),
+            CanEdit::Process(id) => ("SELECT 1 FROM processes WHERE id = ?", id),
+            CanEdit::Commitment(id) => ("SELECT 1 FROM commitments WHERE id = ?", id),
+            CanEdit::Fulfillment(id) => ("SELECT 1 FROM fulfillments WHERE id = ?", id),
+            CanEdit::Relationship(id) => ("SELECT 1 FROM agent_relations WHERE id = ?", id),
+            CanEdit::Resource(id) => ("SELECT 1 FROM economic_resources WHERE id = ?", id),
+            CanEdit::Intent(id) => ("SELECT 1 FROM intents WHERE id = ?", id),
+            CanEdit::Proposal(id) => ("SELECT 1 FROM proposals WHERE id = ?", id),
+            CanEdit::Label(id) => ("SELECT 1 FROM labels WHERE id = ?", id),
+            CanEdit::Location(id) => ("SELECT 1 FROM locations WHERE id = ?", id),
+            CanEdit::ResourceSpecificationNamed(unique_name) => (
+                "SELECT 1 FROM resource_specifications WHERE unique_name = ?",
+                unique_name,
+            ),
+            CanEdit::ValueEquation(id) => ("SELECT 1 FROM value_equations WHERE id = ?", id),
+        };
+        let found: Option<i32> = sqlx::query_scalar(sql)
+            .bind(*id)
+            .fetch_optional(pool)
+            .await?;
+        Ok(found.is_some())
+    }
+
+    /// The agent that created what is changed, as recorded in the audit log.
+    async fn creators(&self, pool: &SqlitePool) -> sqlx::Result<Vec<String>> {
+        let id = match self {
+            CanEdit::MemberNamed(_) => return Ok(Vec::new()),
+            CanEdit::ResourceSpecificationNamed(unique_name) => {
+                let id: Option<String> = sqlx::query_scalar(
+                    "SELECT id FROM resource_specifications WHERE unique_name = ?",
+                )
+                .bind(unique_name)
+                .fetch_optional(pool)
+                .await?;
+                match id {
+                    Some(id) => id,
+                    None => return Ok(Vec::new()),
+                }
+            }
+            CanEdit::Agent(id)
+            | CanEdit::Member(id)
+            | CanEdit::Plan(id)
+            | CanEdit::Process(id)
+            | CanEdit::Commitment(id)
+            | CanEdit::Fulfillment(id)
+            | CanEdit::Relationship(id)
+            | CanEdit::Resource(id)
+            | CanEdit::Intent(id)
+            | CanEdit::Proposal(id)
+            | CanEdit::Label(id)
+            | CanEdit::Location(id)
+            | CanEdit::ValueEquation(id) => id.to_string(),
+        };
+        sqlx::query_scalar(
+            "
+            SELECT agent_id FROM audit_log
+            WHERE entity_id = ? AND before IS NULL AND agent_id IS NOT NULL
+            ",
+        )
+        .bind(id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// The agents taking part in what is changed.
+    async fn owners(&self, pool: &SqlitePool) -> sqlx::Result<Vec<String>> {
+        let (sql, id, binds) = match self {
+            CanEdit::Agent(id) => ("SELECT id FROM agents WHERE id = ?", id.to_string(), 1),
+            CanEdit::Member(id) => (
+                "
+                SELECT id FROM agents WHERE id = ?
+                UNION
+                SELECT object_id FROM agent_relations
+                JOIN agent_relation_types ON agent_relation_types.id = agent_relations.agent_relation_type_id
+                WHERE subject_id = ? AND agent_relation_types.membership AND end_at IS NULL
+                ",
+                id.to_string(),
+                2,
+            ),
+            CanEdit::MemberNamed(unique_name) => (
+                "
+                SELECT id FROM agents WHERE unique_name = ?
+                UNION
+                SELECT object_id FROM agent_relations
+                JOIN agent_relation_types ON agent_relation_types.id = agent_relations.agent_relation_type_id
+                WHERE subject_id = (SELECT id FROM agents WHERE unique_name = ?)
+                AND agent_relation_types.membership AND end_at IS NULL
+                ",
+                unique_name.to_string(),
+                2,
+            ),
+            CanEdit::Plan(id) => (
+                "SELECT agent_id FROM plan_agents WHERE plan_id = ?",
+                id.to_string(),
+                1,
+            ),
+            CanEdit::Process(id) => (PROCESS_AGENTS, id.to_string(), 2),
+            CanEdit::Commitment(id) => (COMMITMENT_AGENTS, id.to_string(), 3),
+            CanEdit::Fulfillment(id) => {
+                let commitment_id: Option<String> =
+                    sqlx::query_scalar("SELECT fulfills_id FROM fulfillments WHERE id = ?")
+                        .bind(id)
+                        .fetch_optional(pool)
+                        .await?;
+                match commitment_id {
+                    Some(commitment_id) => (COMMITMENT_AGENTS, commitment_id, 3),
+                    None => return Ok(Vec::new()),
+                }
+            }
+            CanEdit::Relationship(id) => (
+                "
+                SELECT subject_id FROM agent_relations WHERE id = ?
+                UNION
+                SELECT object_id FROM agent_relations WHERE id = ?
+                ",
+                id.to_string(),
+                2,
+            ),
+            CanEdit::Resource(id) => (
+                "
+                SELECT primary_accountable_id FROM economic_resources
+                WHERE id = ? AND primary_accountable_id IS NOT NULL
+                ",
+                id.to_string(),
+                1,
+            ),
+            CanEdit::Intent(id) => (
+                "
+                SELECT value FROM intents, json_each(json_array(provider_id, receiver_id))
+                WHERE intents.id = ? AND value IS NOT NULL
+                ",
+                id.to_string(),
+                1,
+            ),
+            CanEdit::Proposal(id) => (
+                "
+                SELECT value FROM proposed_intents
+                JOIN intents ON intents.id = proposed_intents.intent_id,
+                json_each(json_array(intents.provider_id, intents.receiver_id))
+                WHERE proposed_intents.proposal_id = ? AND NOT proposed_intents.reciprocal
+                AND value IS NOT NULL
+                ",
+                id.to_string(),
+                1,
+            ),
+            CanEdit::Label(_)
+            | CanEdit::Location(_)
+            | CanEdit::ResourceSpecificationNamed(_)
+            | CanEdit::ValueEquation(_) => return Ok(Vec::new()),
+        };
+        (0..binds)
+            .fold(sqlx::query_scalar(sql), |query, _| query.bind(id.clone()))
+            .fetch_all(pool)
+            .await
+    }
+}
+
+/// The current agent and the organizations it is an admin of.
+async fn acting_agents(pool: &SqlitePool, agent_id: &str) -> sqlx::Result<Vec<String>> {
+    sqlx::query_scalar(
+        "
+        SELECT ?
+        UNION
+        SELECT object_id FROM agent_relations
+        JOIN agent_relation_types ON agent_relation_types.id = agent_relations.agent_relation_type_id
+        WHERE subject_id = ? AND agent_relation_types.admin AND end_at IS NULL
+        ",
+    )
+    .bind(agent_id)
+    .bind(agent_id)
+    .fetch_all(pool)
+    .await
+}
+
+#[async_trait]
+impl Guard for CanEdit<'_> {
+    async fn check(&self, context: &Context<'_>) -> Result<()> {
+        let pool = context.data::<SqlitePool>()?;
+        let current_agent = auth::current_agent(context)?;
+        if let CanEdit::MemberNamed(unique_name) = self {
+            let id: Option<String> =
+                sqlx::query_scalar("SELECT id FROM agents WHERE unique_name = ?")
+                    .bind(unique_name)
+                    .fetch_optional(pool)
+                    .await?;
+            return match id {
+                Some(id) => CanEdit::Member(&id).check(context).await,
+                None => Err(DomainError::not_found(self).into()),
+            };
+        }
+        if !self.exists(pool).await? {
+            return Err(DomainError::not_found(self).into());
+        }
+        let mut owners = self.owners(pool).await?;
+        if owners.is_empty() {
+            owners = self.creators(pool).await?;
+        }
+        let acting_agents = acting_agents(pool, &current_agent.agent_id).await?;
+        if !owners.iter().any(|owner| acting_agents.contains(owner)) {
+            return Err(DomainError::unauthorized(format!(
+                "you are not allowed to change {}",
+                self
+            ))
+            .into());
+        }
+        if let CanEdit::Member(id) = self {
+            // acting for an agent gives its admin rights, which have to be the caller's already
+            let administered = self::acting_agents(pool, id).await?;
+            if let Some(organization) = administered
+                .iter()
+                .find(|agent| agent != id && !acting_agents.contains(agent))
+            {
+                return Err(DomainError::unauthorized(format!(
+                    "agent {} is an admin of {}, which you are not",
+                    id, organization
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks a guard when the argument it is about was given, and only a login otherwise.
+pub(super) struct IfGiven<G>(pub(super) Option<G>);
+
+#[async_trait]
+impl<G: Guard + Send + Sync> Guard for IfGiven<G> {
+    async fn check(&self, context: &Context<'_>) -> Result<()> {
+        match &self.0 {
+            Some(guard) => guard.check(context).await,
+            None => auth::current_agent(context).map(|_| ()),
+        }
+    }
+}
+
+/// Lets through agents that pass any of the given checks.
+pub(super) struct AnyOf<'a>(pub(super) Vec<CanEdit<'a>>);
+
+#[async_trait]
+impl Guard for AnyOf<'_> {
+    async fn check(&self, context: &Context<'_>) -> Result<()> {
+        let mut denied = Err(DomainError::unauthorized("you are not allowed to do this").into());
+        for guard in &self.0 {
+            match guard.check(context).await {
+                Ok(()) => return Ok(()),
+                Err(error) => denied = Err(error),
+            }
+        }
+        denied
+    }
+}
+
+/// Lets through agents that pass every one of the given checks.
+pub(super) struct AllOf<'a>(pub(super) Vec<CanEdit<'a>>);
+
+#[async_trait]
+impl Guard for AllOf<'_> {
+    async fn check(&self, context: &Context<'_>) -> Result<()> {
+        auth::current_agent(context)?;
+        for guard in &self.0 {
+            guard.check(context).await?;
+        }
+        Ok(())
+    }
+}
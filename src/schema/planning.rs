@@ -0,0 +1,467 @@
+use super::error::{DomainError, Result};
+use super::measure::{self, Decimal};
+use super::time::DateTime;
+use super::{load_recipe_relations, Recipe, RecipeFlow, RecipeProcess, Unit};
+use chrono::Duration;
+use sqlx::{
+    sqlite::{SqlitePool, SqliteRow},
+    Row,
+};
+use std::collections::{HashMap, VecDeque};
+
+/// Nesting depth after which dependent demand is assumed to loop through recipes.
+const MAX_DEMAND_DEPTH: usize = 32;
+
+/// A recipe process placed on the calendar of a plan.
+pub(super) struct ScheduledProcess<'a> {
+    pub(super) recipe_process: &'a RecipeProcess,
+    pub(super) start_at: DateTime,
+    pub(super) due_at: DateTime,
+}
+
+/// The unit of a recipe flow, or of its resource when the flow has none.
+fn flow_unit_id(flow: &RecipeFlow) -> Option<&str> {
+    flow.unit_id.as_deref().or_else(|| {
+        flow.recipe_flow_resource
+            .as_ref()
+            .and_then(|r| r.unit_id.as_deref())
+    })
+}
+
+/// Ratio between the requested quantity, converted into the unit of the primary
+/// output, and the quantity of the primary output the recipe produces in one run.
+pub(super) fn scale_factor(
+    recipe: &Recipe,
+    quantity: Decimal,
+    unit_id: Option<&str>,
+    units: &HashMap<String, Unit>,
+) -> Result<Decimal> {
+    if !quantity.is_positive() {
+        return Err(DomainError::validation("quantity must be positive"));
+    }
+    let primary_output = recipe
+        .processes
+        .iter()
+        .flat_map(|p| p.outputs.iter())
+        .find(|f| f.recipe_flow_resource_id == recipe.primary_output_id);
+    let primary_output_quantity = primary_output
+        .and_then(|f| f.resource_quantity)
+        .filter(Decimal::is_positive)
+        .unwrap_or(Decimal::ONE);
+    let quantity = measure::convert(
+        quantity,
+        unit_id.and_then(|id| units.get(id)),
+        primary_output
+            .and_then(flow_unit_id)
+            .and_then(|id| units.get(id)),
+    )?;
+    quantity
+        .checked_div(primary_output_quantity)
+        .ok_or_else(|| DomainError::validation("quantity is out of range"))
+}
+
+/// Quantity of a flow once the recipe is scaled, kept exact since units such as
+/// kilograms or hours are divisible.
+pub(super) fn scaled_quantity(flow: &RecipeFlow, factor: Decimal) -> Decimal {
+    flow.resource_quantity.unwrap_or_default() * factor
+}
+
+/// Back-schedules the processes of a recipe from its due date.
+///
+/// The process producing the primary output ends at `due_at`. Every process
+/// producing an input of another process ends when that process starts, the
+/// earliest consumer winning. Processes not feeding the primary output end at
+/// `due_at`. Processes are returned ordered by their start.
+pub(super) fn back_schedule(
+    recipe: &Recipe,
+    due_at: DateTime,
+) -> Result<Vec<ScheduledProcess<'_>>> {
+    let terminal = recipe
+        .processes
+        .iter()
+        .position(|p| {
+            p.outputs
+                .iter()
+                .any(|f| f.recipe_flow_resource_id == recipe.primary_output_id)
+        })
+        .ok_or_else(|| DomainError::validation("no recipe process produces the primary output"))?;
+    let mut producers: HashMap<&str, Vec<usize>> = HashMap::new();
+    recipe.processes.iter().enumerate().for_each(|(index, p)| {
+        p.outputs.iter().for_each(|f| {
+            producers
+                .entry(f.recipe_flow_resource_id.as_str())
+                .or_default()
+                .push(index)
+        })
+    });
+    let duration =
+        |p: &RecipeProcess| Duration::seconds(p.has_duration_seconds.unwrap_or(0) as i64);
+
+    let mut due_dates: Vec<Option<DateTime>> = vec![None; recipe.processes.len()];
+    let mut updates = vec![0; recipe.processes.len()];
+    due_dates[terminal] = Some(due_at);
+    let mut pending = VecDeque::from(vec![terminal]);
+    while let Some(index) = pending.pop_front() {
+        let process = &recipe.processes[index];
+        let start_at = due_dates[index].expect("pending process is scheduled") - duration(process);
+        for input in process.inputs.iter() {
+            let input_producers = producers
+                .get(input.recipe_flow_resource_id.as_str())
+                .into_iter()
+                .flatten();
+            for &producer in input_producers {
+                let done_in_time = matches!(due_dates[producer], Some(due) if due <= start_at);
+                if producer == index || done_in_time {
+                    continue;
+                }
+                updates[producer] += 1;
+                if updates[producer] > recipe.processes.len() {
+                    return Err(DomainError::validation(format!(
+                        "recipe process {} is part of a cycle",
+                        recipe.processes[producer].name
+                    )));
+                }
+                due_dates[producer] = Some(start_at);
+                pending.push_back(producer);
+            }
+        }
+    }
+
+    let mut scheduled = recipe
+        .processes
+        .iter()
+        .zip(due_dates)
+        .map(|(recipe_process, due)| {
+            let due_at = due.unwrap_or(due_at);
+            ScheduledProcess {
+                recipe_process,
+                start_at: due_at - duration(recipe_process),
+                due_at,
+            }
+        })
+        .collect::<Vec<_>>();
+    scheduled.sort_by_key(|s| s.start_at);
+    Ok(scheduled)
+}
+
+/// Quantity of a resource specification needed by a date.
+pub(super) struct Demand {
+    pub(super) resource_specification_id: String,
+    pub(super) quantity: Decimal,
+    pub(super) unit_id: Option<String>,
+    pub(super) due_at: DateTime,
+}
+
+/// A demand after netting: what stock covers, what is left and how the rest is produced.
+pub(super) struct NetDemand {
+    pub(super) demand: Demand,
+    pub(super) on_hand_quantity: Decimal,
+    pub(super) net_quantity: Decimal,
+    pub(super) recipe: Option<Recipe>,
+}
+
+/// Explodes a demand through recipes, MRP style. Each demand is first netted against the
+/// on-hand stock of its resource specification still unallocated in this explosion. What is
+/// left is produced by a recipe whose primary output conforms to the specification, and the
+/// resources that recipe consumes without producing them become demands due when their
+/// process starts. Demands without a recipe are left for purchasing. Only the stock `owner_id`
+/// is primarily accountable for counts, none without an owner. Stock is counted in the base
+/// unit of its dimension, so resources kept in kilograms cover demands in grams.
+pub(super) async fn explode_demand(
+    pool: &SqlitePool,
+    demand: Demand,
+    owner_id: Option<&str>,
+) -> Result<Vec<NetDemand>> {
+    let units = measure::units_by_id(pool).await?;
+    let to_base = |quantity: Decimal, unit_id: &Option<String>| match unit_id
+        .as_ref()
+        .and_then(|id| units.get(id))
+        .and_then(|unit| unit.conversion_factor)
+    {
+        Some(factor) if factor.is_positive() => (quantity * factor, factor),
+        _ => (quantity, Decimal::ONE),
+    };
+    let mut stock: HashMap<String, Decimal> = HashMap::new();
+    let resources = sqlx::query(
+        "
+        SELECT conforms_to_id, onhand_quantity, unit_id
+        FROM economic_resources
+        WHERE conforms_to_id IS NOT NULL
+        AND primary_accountable_id = ?
+        ",
+    )
+    .bind(owner_id)
+    .map(|row: SqliteRow| {
+        (
+            row.get::<String, _>("conforms_to_id"),
+            row.get::<Option<Decimal>, _>("onhand_quantity")
+                .unwrap_or_default(),
+            row.get::<Option<String>, _>("unit_id"),
+        )
+    })
+    .fetch_all(pool)
+    .await?;
+    for (conforms_to_id, quantity, unit_id) in resources {
+        let available = stock.entry(conforms_to_id).or_default();
+        *available = *available + to_base(quantity, &unit_id).0;
+    }
+    let mut recipes: HashMap<String, Option<Recipe>> = HashMap::new();
+    let mut pending = VecDeque::from(vec![(demand, 0)]);
+    let mut net_demands = vec![];
+    while let Some((demand, depth)) = pending.pop_front() {
+        if depth > MAX_DEMAND_DEPTH {
+            return Err(DomainError::validation(format!(
+                "demand for resource specification {} nests more than {} recipes deep",
+                demand.resource_specification_id, MAX_DEMAND_DEPTH
+            )));
+        }
+        let available = stock
+            .entry(demand.resource_specification_id.clone())
+            .or_default();
+        let (quantity, factor) = to_base(demand.quantity, &demand.unit_id);
+        let on_hand = quantity.min(*available).max(Decimal::ZERO);
+        *available = *available - on_hand;
+        let on_hand_quantity = on_hand
+            .checked_div(factor)
+            .ok_or_else(|| DomainError::validation("stock is out of range"))?;
+        let net_quantity = demand.quantity - on_hand_quantity;
+        if !recipes.contains_key(&demand.resource_specification_id) {
+            let recipe = recipe_producing(pool, &demand.resource_specification_id).await?;
+            recipes.insert(demand.resource_specification_id.clone(), recipe);
+        }
+        let recipe = match &recipes[&demand.resource_specification_id] {
+            Some(recipe) if net_quantity.is_positive() => recipe.clone(),
+            _ => {
+                net_demands.push(NetDemand {
+                    demand,
+                    on_hand_quantity,
+                    net_quantity,
+                    recipe: None,
+                });
+                continue;
+            }
+        };
+        let factor = scale_factor(&recipe, net_quantity, demand.unit_id.as_deref(), &units)?;
+        let schedule = back_schedule(&recipe, demand.due_at)?;
+        for scheduled in schedule.iter() {
+            let consumed = scheduled
+                .recipe_process
+                .inputs
+                .iter()
+                .filter(|f| f.action.as_ref().map(|a| a.name.as_str()) == Some("consume"));
+            for flow in consumed {
+                let produced_in_recipe = recipe.processes.iter().any(|p| {
+                    p.outputs
+                        .iter()
+                        .any(|o| o.recipe_flow_resource_id == flow.recipe_flow_resource_id)
+                });
+                let resource_specification_id = flow
+                    .recipe_flow_resource
+                    .as_ref()
+                    .and_then(|r| r.resource_specification_id.clone());
+                if let (false, Some(resource_specification_id)) =
+                    (produced_in_recipe, resource_specification_id)
+                {
+                    pending.push_back((
+                        Demand {
+                            resource_specification_id,
+                            quantity: scaled_quantity(flow, factor),
+                            unit_id: flow_unit_id(flow).map(str::to_string),
+                            due_at: scheduled.start_at,
+                        },
+                        depth + 1,
+                    ));
+                }
+            }
+        }
+        net_demands.push(NetDemand {
+            demand,
+            on_hand_quantity,
+            net_quantity,
+            recipe: Some(recipe),
+        });
+    }
+    Ok(net_demands)
+}
+
+/// The first recipe whose primary output conforms to a resource specification.
+async fn recipe_producing(
+    pool: &SqlitePool,
+    resource_specification_id: &str,
+) -> sqlx::Result<Option<Recipe>> {
+    let mut recipes = sqlx::query(
+        "
+        SELECT recipes.*
+        FROM recipes
+        JOIN recipe_resources
+        ON recipe_resources.id = recipes.primary_output_id
+        WHERE recipe_resources.resource_specification_id = ?
+        ORDER BY recipes.inserted_at, recipes.id
+        LIMIT 1
+        ",
+    )
+    .bind(resource_specification_id)
+    .map(Recipe::from_row)
+    .fetch_all(pool)
+    .await?;
+    load_recipe_relations(pool, &mut recipes).await?;
+    Ok(recipes.pop())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flow(resource_id: &str, quantity: i32) -> RecipeFlow {
+        RecipeFlow {
+            recipe_flow_resource_id: resource_id.to_string(),
+            resource_quantity: Some(Decimal::from(quantity)),
+            ..Default::default()
+        }
+    }
+
+    fn process(
+        name: &str,
+        hours: i32,
+        inputs: Vec<RecipeFlow>,
+        outputs: Vec<RecipeFlow>,
+    ) -> RecipeProcess {
+        RecipeProcess {
+            name: name.to_string(),
+            has_duration_seconds: Some(hours * 3600),
+            inputs,
+            outputs,
+            ..Default::default()
+        }
+    }
+
+    fn recipe(primary_output_id: &str, processes: Vec<RecipeProcess>) -> Recipe {
+        Recipe {
+            primary_output_id: primary_output_id.to_string(),
+            processes,
+            ..Default::default()
+        }
+    }
+
+    fn hours(hours: i64) -> DateTime {
+        DateTime::default() + Duration::hours(hours)
+    }
+
+    /// Kneading dough, then baking 4 breads out of it.
+    fn bread() -> Recipe {
+        recipe(
+            "bread",
+            vec![
+                process("bake", 1, vec![flow("dough", 2)], vec![flow("bread", 4)]),
+                process("knead", 2, vec![flow("flour", 3)], vec![flow("dough", 2)]),
+            ],
+        )
+    }
+
+    #[test]
+    fn scale_factor_is_relative_to_the_primary_output() {
+        let factor = scale_factor(&bread(), Decimal::from(10), None, &HashMap::new()).unwrap();
+        assert_eq!(
+            factor,
+            Decimal::from(5).checked_div(Decimal::from(2)).unwrap()
+        );
+    }
+
+    #[test]
+    fn scale_factor_converts_the_quantity_into_the_unit_of_the_primary_output() {
+        let unit = |id: &str, conversion_factor: i32| Unit {
+            id: id.to_string(),
+            label: id.to_string(),
+            dimension: Some("mass".to_string()),
+            conversion_factor: Some(Decimal::from(conversion_factor)),
+            ..Default::default()
+        };
+        let units = vec![unit("gram", 1), unit("kilogram", 1000)]
+            .into_iter()
+            .map(|unit| (unit.id.clone(), unit))
+            .collect();
+        let mut recipe = bread();
+        recipe.processes[0].outputs[0].unit_id = Some("kilogram".to_string());
+        let factor = scale_factor(&recipe, Decimal::from(10000), Some("gram"), &units).unwrap();
+        assert_eq!(
+            factor,
+            Decimal::from(5).checked_div(Decimal::from(2)).unwrap()
+        );
+    }
+
+    #[test]
+    fn scale_factor_rejects_a_quantity_that_is_not_positive() {
+        assert!(scale_factor(&bread(), Decimal::ZERO, None, &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn scaled_quantities_are_not_rounded() {
+        let factor = scale_factor(&bread(), Decimal::from(10), None, &HashMap::new()).unwrap();
+        assert_eq!(
+            scaled_quantity(&flow("flour", 3), factor),
+            Decimal::from(15).checked_div(Decimal::from(2)).unwrap()
+        );
+        assert_eq!(scaled_quantity(&flow("dough", 2), factor), Decimal::from(5));
+    }
+
+    #[test]
+    fn producers_are_due_when_their_consumers_start() {
+        let recipe = bread();
+        let schedule = back_schedule(&recipe, hours(10)).unwrap();
+        let names: Vec<&str> = schedule
+            .iter()
+            .map(|s| s.recipe_process.name.as_str())
+            .collect();
+        assert_eq!(names, ["knead", "bake"]);
+        assert_eq!(
+            (schedule[0].start_at, schedule[0].due_at),
+            (hours(7), hours(9))
+        );
+        assert_eq!(
+            (schedule[1].start_at, schedule[1].due_at),
+            (hours(9), hours(10))
+        );
+    }
+
+    #[test]
+    fn the_earliest_consumer_sets_the_due_date() {
+        let recipe = recipe(
+            "bread",
+            vec![
+                process(
+                    "bake",
+                    1,
+                    vec![flow("dough", 1), flow("filling", 1)],
+                    vec![flow("bread", 1)],
+                ),
+                process("knead", 3, vec![flow("flour", 1)], vec![flow("dough", 1)]),
+                process("cook", 1, vec![flow("flour", 1)], vec![flow("filling", 1)]),
+                process("mill", 1, vec![], vec![flow("flour", 2)]),
+            ],
+        );
+        let schedule = back_schedule(&recipe, hours(10)).unwrap();
+        let mill = schedule
+            .iter()
+            .find(|s| s.recipe_process.name == "mill")
+            .unwrap();
+        assert_eq!((mill.start_at, mill.due_at), (hours(5), hours(6)));
+    }
+
+    #[test]
+    fn a_cycle_is_rejected() {
+        let recipe = recipe(
+            "bread",
+            vec![
+                process("bake", 1, vec![flow("dough", 1)], vec![flow("bread", 1)]),
+                process("knead", 1, vec![flow("bread", 1)], vec![flow("dough", 1)]),
+            ],
+        );
+        assert!(back_schedule(&recipe, hours(10)).is_err());
+    }
+
+    #[test]
+    fn a_recipe_without_its_primary_output_is_rejected() {
+        let recipe = recipe("cake", bread().processes);
+        assert!(back_schedule(&recipe, hours(10)).is_err());
+    }
+}
@@ -0,0 +1,189 @@
+use super::error::Result;
+use super::location::load_location;
+use super::{EconomicEvent, EconomicResource, Location, Process, ProvenanceNode};
+use async_graphql::{ComplexObject, Context};
+use sqlx::sqlite::SqlitePool;
+use std::collections::{HashSet, VecDeque};
+
+/// Which way a provenance chain is walked: `Trace` goes back to where something came
+/// from, `Track` goes forward to where it went.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum Direction {
+    Trace,
+    Track,
+}
+
+impl ProvenanceNode {
+    fn id(&self) -> &str {
+        match self {
+            ProvenanceNode::EconomicEvent(event) => &event.id,
+            ProvenanceNode::EconomicResource(resource) => &resource.id,
+            ProvenanceNode::Process(process) => &process.id,
+        }
+    }
+}
+
+async fn fetch_events(
+    pool: &SqlitePool,
+    filter: &str,
+    id: &str,
+) -> sqlx::Result<Vec<ProvenanceNode>> {
+    let events = sqlx::query(&format!(
+        "SELECT * FROM economic_events WHERE {} ORDER BY inserted_at, id",
+        filter
+    ))
+    .bind(id)
+    .map(EconomicEvent::from_row)
+    .fetch_all(pool)
+    .await?;
+    Ok(events
+        .into_iter()
+        .map(ProvenanceNode::EconomicEvent)
+        .collect())
+}
+
+async fn fetch_resource(
+    pool: &SqlitePool,
+    id: &Option<String>,
+) -> sqlx::Result<Vec<ProvenanceNode>> {
+    let resource = sqlx::query("SELECT * FROM economic_resources WHERE id = ?")
+        .bind(id)
+        .map(EconomicResource::from_row)
+        .fetch_optional(pool)
+        .await?;
+    Ok(resource
+        .into_iter()
+        .map(ProvenanceNode::EconomicResource)
+        .collect())
+}
+
+async fn fetch_process(
+    pool: &SqlitePool,
+    id: &Option<String>,
+) -> sqlx::Result<Vec<ProvenanceNode>> {
+    let process = sqlx::query("SELECT * FROM processes WHERE id = ?")
+        .bind(id)
+        .map(Process::from_row)
+        .fetch_optional(pool)
+        .await?;
+    Ok(process.into_iter().map(ProvenanceNode::Process).collect())
+}
+
+/// The nodes one step away from `node`.
+///
+/// Backwards, a resource comes from the events that produced it or transferred it in, an
+/// output event from its process, a process from its input events and an input or transfer
+/// event from the resource it took. Forwards is the mirror image.
+async fn next_nodes(
+    pool: &SqlitePool,
+    node: &ProvenanceNode,
+    direction: Direction,
+) -> sqlx::Result<Vec<ProvenanceNode>> {
+    match (node, direction) {
+        (ProvenanceNode::EconomicResource(resource), Direction::Trace) => {
+            fetch_events(
+                pool,
+                "(resource_inventoried_as_id = ?1 AND output_of_id IS NOT NULL) OR to_resource_inventoried_as_id = ?1",
+                &resource.id,
+            )
+            .await
+        }
+        (ProvenanceNode::EconomicResource(resource), Direction::Track) => {
+            fetch_events(
+                pool,
+                "resource_inventoried_as_id = ?1 AND (input_of_id IS NOT NULL OR to_resource_inventoried_as_id IS NOT NULL)",
+                &resource.id,
+            )
+            .await
+        }
+        (ProvenanceNode::EconomicEvent(event), Direction::Trace) => {
+            if event.output_of_id.is_some() {
+                fetch_process(pool, &event.output_of_id).await
+            } else if event.input_of_id.is_some() || event.to_resource_inventoried_as_id.is_some() {
+                fetch_resource(pool, &event.resource_inventoried_as_id).await
+            } else {
+                Ok(vec![])
+            }
+        }
+        (ProvenanceNode::EconomicEvent(event), Direction::Track) => {
+            if event.input_of_id.is_some() {
+                fetch_process(pool, &event.input_of_id).await
+            } else if event.to_resource_inventoried_as_id.is_some() {
+                fetch_resource(pool, &event.to_resource_inventoried_as_id).await
+            } else if event.output_of_id.is_some() {
+                fetch_resource(pool, &event.resource_inventoried_as_id).await
+            } else {
+                Ok(vec![])
+            }
+        }
+        (ProvenanceNode::Process(process), Direction::Trace) => {
+            fetch_events(pool, "input_of_id = ?1", &process.id).await
+        }
+        (ProvenanceNode::Process(process), Direction::Track) => {
+            fetch_events(pool, "output_of_id = ?1", &process.id).await
+        }
+    }
+}
+
+/// Walks the event graph breadth first from `start`, returning every node reached ordered by
+/// its distance from `start`. Each node appears once, so cycles through transfers end.
+pub(super) async fn walk(
+    pool: &SqlitePool,
+    start: ProvenanceNode,
+    direction: Direction,
+) -> sqlx::Result<Vec<ProvenanceNode>> {
+    let mut visited = HashSet::from([start.id().to_string()]);
+    let mut pending = VecDeque::from(vec![start]);
+    let mut chain = vec![];
+    while let Some(node) = pending.pop_front() {
+        for next in next_nodes(pool, &node, direction).await? {
+            if visited.insert(next.id().to_string()) {
+                chain.push(next.clone());
+                pending.push_back(next);
+            }
+        }
+    }
+    Ok(chain)
+}
+
+#[ComplexObject]
+impl EconomicResource {
+    /// Where this resource came from, nearest first.
+    async fn trace<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<ProvenanceNode>> {
+        let pool = context.data::<SqlitePool>()?;
+        let start = ProvenanceNode::EconomicResource(self.clone());
+        Ok(walk(pool, start, Direction::Trace).await?)
+    }
+
+    /// Where this resource went, nearest first.
+    async fn track<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<ProvenanceNode>> {
+        let pool = context.data::<SqlitePool>()?;
+        let start = ProvenanceNode::EconomicResource(self.clone());
+        Ok(walk(pool, start, Direction::Track).await?)
+    }
+
+    async fn current_location<'ctx>(&self, context: &Context<'ctx>) -> Result<Option<Location>> {
+        load_location(context, &self.current_location_id).await
+    }
+}
+
+#[ComplexObject]
+impl EconomicEvent {
+    /// Where the flow of this event came from, nearest first.
+    async fn trace<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<ProvenanceNode>> {
+        let pool = context.data::<SqlitePool>()?;
+        let start = ProvenanceNode::EconomicEvent(self.clone());
+        Ok(walk(pool, start, Direction::Trace).await?)
+    }
+
+    /// Where the flow of this event went, nearest first.
+    async fn track<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<ProvenanceNode>> {
+        let pool = context.data::<SqlitePool>()?;
+        let start = ProvenanceNode::EconomicEvent(self.clone());
+        Ok(walk(pool, start, Direction::Track).await?)
+    }
+
+    async fn at_location<'ctx>(&self, context: &Context<'ctx>) -> Result<Option<Location>> {
+        load_location(context, &self.at_location_id).await
+    }
+}
@@ -1,51 +1,122 @@
+use super::auth::{self, CurrentAgent, LoggedIn, TokenKind};
+use super::error::{DomainError, Result};
+use super::filter::{
+    AgentFilter, AgentOrderBy, NameOrderBy, PlanOrderBy, ProcessFilter, ProcessOrderBy,
+};
+use super::measure::{self, Decimal};
+use super::pagination::{self, List, Page};
+use super::time::DateTime;
 use super::{
-    Action, Agent, AgentRelationship, AgentType, Commitment, Label, Plan, Process,
-    ResourceSpecification, Unit,
+    contribution, load_agreement_relations, load_intent_relations, load_proposal_relations,
+    load_recipe_relations, load_value_equation_relations, location, search, Action, Agent,
+    AgentRelationship, AgentType, Agreement, ApiToken, AuditEntry, EconomicEvent, EconomicResource,
+    IncomeDistribution, Intent, Label, Location, Measure, NearbyLocation, Plan, Process,
+    ProcessSpecification, Proposal, Recipe, RecipeResource, ResourceSpecification, SearchResult,
+    SearchType, Unit, ValueEquation,
 };
 
-use async_graphql::{Context, Object, Result};
-use sqlx::{sqlite::SqlitePool, Row};
+use async_graphql::{Context, Object};
+use sqlx::{sqlite::SqlitePool, FromRow};
 use std::collections::{HashMap, HashSet};
 
 pub struct QueryRoot;
 
 #[Object]
 impl QueryRoot {
-    async fn agents<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<Agent>> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
-        // TODO find a way to type check queries with an enum in the struct
-        let agents = sqlx::query_as::<_, Agent>("SELECT * FROM agents ORDER BY inserted_at DESC")
-            .fetch_all(pool)
-            .await?;
-        Ok(agents.to_vec())
+    #[allow(clippy::too_many_arguments)]
+    async fn agents<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        filter: Option<AgentFilter>,
+        #[graphql(default)] order_by: AgentOrderBy,
+        #[graphql(default)] include_deleted: bool,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Page<Agent>> {
+        let pool = context.data::<SqlitePool>()?;
+        let mut list = order_by.apply(List::table("agents"));
+        filter.unwrap_or_default().apply(&mut list);
+        if !include_deleted {
+            list.filter("agents.deleted_at IS NULL", []);
+        }
+        pagination::paginate(pool, list, after, before, first, last, |row| {
+            FromRow::from_row(&row)
+        })
+        .await
     }
 
-    async fn individuals<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<Agent>> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
-        let agents = sqlx::query_as::<_, Agent>(
-            "SELECT * FROM agents WHERE agents.agent_type == ? ORDER BY inserted_at DESC",
-        )
-        .bind(AgentType::Individual)
-        .fetch_all(pool)
-        .await?;
-        Ok(agents.to_vec())
+    #[graphql(deprecation = "use agents(filter: {agentType: INDIVIDUAL})")]
+    async fn individuals<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Page<Agent>> {
+        let pool = context.data::<SqlitePool>()?;
+        let mut list = List::table("agents");
+        AgentFilter::agent_type(AgentType::Individual).apply(&mut list);
+        list.filter("agents.deleted_at IS NULL", []);
+        pagination::paginate(pool, list, after, before, first, last, |row| {
+            FromRow::from_row(&row)
+        })
+        .await
+    }
+
+    #[graphql(deprecation = "use agents(filter: {agentType: ORGANIZATION})")]
+    async fn organizations<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Page<Agent>> {
+        let pool = context.data::<SqlitePool>()?;
+        let mut list = List::table("agents");
+        AgentFilter::agent_type(AgentType::Organization).apply(&mut list);
+        list.filter("agents.deleted_at IS NULL", []);
+        pagination::paginate(pool, list, after, before, first, last, |row| {
+            FromRow::from_row(&row)
+        })
+        .await
+    }
+
+    /// The agent the request is made on behalf of, if it was made with a token.
+    async fn current_agent<'ctx>(&self, context: &Context<'ctx>) -> Result<Option<Agent>> {
+        let pool = context.data::<SqlitePool>()?;
+        let current_agent = match context.data_opt::<CurrentAgent>() {
+            Some(current_agent) => current_agent,
+            None => return Ok(None),
+        };
+        let agent = sqlx::query("SELECT * FROM agents WHERE id = ?")
+            .bind(&current_agent.agent_id)
+            .map(Agent::from_row)
+            .fetch_optional(pool)
+            .await?;
+        Ok(agent)
     }
 
-    async fn organizations<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<Agent>> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
-        let agents = sqlx::query_as::<_, Agent>(
-            "SELECT * FROM agents WHERE agents.agent_type == ? ORDER BY inserted_at DESC",
+    /// The API tokens of the current agent, newest first.
+    #[graphql(guard = "LoggedIn")]
+    async fn api_tokens<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<ApiToken>> {
+        let pool = context.data::<SqlitePool>()?;
+        let current_agent = auth::current_agent(context)?;
+        let api_tokens = sqlx::query_as::<_, ApiToken>(
+            "
+            SELECT id, name, expires_at, last_used_at, inserted_at FROM access_tokens
+            WHERE agent_id = ? AND kind = ?
+            ORDER BY id DESC
+            ",
         )
-        .bind(AgentType::Organization)
+        .bind(&current_agent.agent_id)
+        .bind(TokenKind::Api)
         .fetch_all(pool)
         .await?;
-        Ok(agents.to_vec())
+        Ok(api_tokens)
     }
 
     async fn agent_relations<'ctx>(
@@ -53,9 +124,7 @@ impl QueryRoot {
         context: &Context<'ctx>,
         agent_id: String,
     ) -> Result<Vec<AgentRelationship>> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
+        let pool = context.data::<SqlitePool>()?;
         let mut relations = sqlx::query("
             SELECT agent_relations.id, subject_id, object_id, agent_relation_types.name AS agent_relation_type_name FROM agent_relations
             JOIN agent_relation_types ON agent_relation_types.id = agent_relations.agent_relation_type_id 
@@ -91,314 +160,551 @@ impl QueryRoot {
                 acc
             },
         );
-        relations.iter_mut().for_each(|p| {
-            p.subject = agents_hashmap
-                .get(&p.subject_id)
-                .expect(&format!(
-                    "subject {} missing for relationship id {}",
-                    &p.subject_id, &p.id
-                ))
-                .to_owned()
-                .to_owned();
-            p.object = agents_hashmap
-                .get(&p.object_id)
-                .expect(&format!(
-                    "object {} missing for relationship id {}",
-                    &p.object_id, &p.id
+        for relationship in relations.iter_mut() {
+            let subject = agents_hashmap
+                .get(&relationship.subject_id)
+                .ok_or_else(|| {
+                    DomainError::not_found(format!(
+                        "subject {} of relationship {}",
+                        relationship.subject_id, relationship.id
+                    ))
+                })?;
+            relationship.subject = subject.to_owned().to_owned();
+            let object = agents_hashmap.get(&relationship.object_id).ok_or_else(|| {
+                DomainError::not_found(format!(
+                    "object {} of relationship {}",
+                    relationship.object_id, relationship.id
                 ))
-                .to_owned()
-                .to_owned();
-        });
+            })?;
+            relationship.object = object.to_owned().to_owned();
+        }
         Ok(relations.to_vec())
     }
 
-    async fn plans<'ctx>(&self, context: &Context<'ctx>, agent_id: String) -> Result<Vec<Plan>> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
-        let plans =
-            sqlx::query("SELECT plans.id, title, description, plans.inserted_at FROM plans JOIN plan_agents ON plan_agents.plan_id = plans.id WHERE plan_agents.agent_id = ? ORDER BY plans.inserted_at DESC")
-                .bind(agent_id)
+    #[allow(clippy::too_many_arguments)]
+    async fn plans<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        agent_id: String,
+        #[graphql(default)] order_by: PlanOrderBy,
+        #[graphql(default)] include_deleted: bool,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Page<Plan>> {
+        let pool = context.data::<SqlitePool>()?;
+        let mut list = order_by
+            .apply(List::table("plans").join("JOIN plan_agents ON plan_agents.plan_id = plans.id"));
+        list.filter("plan_agents.agent_id = ?", [agent_id]);
+        if !include_deleted {
+            list.filter("plans.deleted_at IS NULL", []);
+        }
+        pagination::paginate(pool, list, after, before, first, last, |row| {
+            Ok(Plan::from_row(row))
+        })
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn processes<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        filter: Option<ProcessFilter>,
+        #[graphql(default)] order_by: ProcessOrderBy,
+        #[graphql(default)] include_deleted: bool,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Page<Process>> {
+        let pool = context.data::<SqlitePool>()?;
+        let mut list = order_by.apply(List::table("processes"));
+        filter.unwrap_or_default().apply(&mut list);
+        if !include_deleted {
+            list.filter("processes.deleted_at IS NULL", []);
+            list.filter(
+                "NOT EXISTS (
+                    SELECT 1 FROM plans
+                    WHERE plans.id = processes.plan_id AND plans.deleted_at IS NOT NULL
+                )",
+                [],
+            );
+        }
+        pagination::paginate(pool, list, after, before, first, last, |row| {
+            Ok(Process::from_row(row))
+        })
+        .await
+    }
+
+    async fn plan<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        plan_id: String,
+        #[graphql(default)] include_deleted: bool,
+    ) -> Result<Plan> {
+        let pool = context.data::<SqlitePool>()?;
+        let plan =
+            sqlx::query("SELECT * FROM plans WHERE plans.id = ? AND (? OR deleted_at IS NULL)")
+                .bind(&plan_id)
+                .bind(include_deleted)
                 .map(Plan::from_row)
-                .fetch_all(pool)
-                .await?;
-        Ok(plans.to_vec())
+                .fetch_optional(pool)
+                .await?
+                .ok_or_else(|| DomainError::not_found(format!("plan {}", plan_id)))?;
+        Ok(plan)
     }
 
-    async fn plan<'ctx>(&self, context: &Context<'ctx>, plan_id: String) -> Result<Plan> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
-        let mut plan = sqlx::query("SELECT * FROM plans WHERE plans.id = ?")
-            .bind(plan_id)
-            .map(Plan::from_row)
-            .fetch_one(pool)
+    async fn labels<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        #[graphql(default)] order_by: NameOrderBy,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Page<Label>> {
+        let pool = context.data::<SqlitePool>()?;
+        pagination::paginate(
+            pool,
+            order_by.list("labels", "name"),
+            after,
+            before,
+            first,
+            last,
+            |row| FromRow::from_row(&row),
+        )
+        .await
+    }
+
+    async fn actions<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        #[graphql(default)] order_by: NameOrderBy,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Page<Action>> {
+        let pool = context.data::<SqlitePool>()?;
+        pagination::paginate(
+            pool,
+            order_by.list("actions", "name"),
+            after,
+            before,
+            first,
+            last,
+            |row| FromRow::from_row(&row),
+        )
+        .await
+    }
+
+    async fn units<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        #[graphql(default)] order_by: NameOrderBy,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Page<Unit>> {
+        let pool = context.data::<SqlitePool>()?;
+        pagination::paginate(
+            pool,
+            order_by.list("units", "label"),
+            after,
+            before,
+            first,
+            last,
+            |row| Ok(Unit::from_row(row)),
+        )
+        .await
+    }
+
+    async fn locations<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<Location>> {
+        let pool = context.data::<SqlitePool>()?;
+        let locations = sqlx::query("SELECT * FROM locations ORDER BY name, id")
+            .map(Location::from_row)
+            .fetch_all(pool)
             .await?;
-        let mut processes = sqlx::query("SELECT * FROM processes WHERE processes.plan_id = ? ")
-            .bind(&plan.id)
-            .map(Process::from_row)
+        Ok(locations)
+    }
+
+    /// Locations within `radius_km` of a point, nearest first.
+    async fn nearby<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        lat: f64,
+        long: f64,
+        radius_km: f64,
+    ) -> Result<Vec<NearbyLocation>> {
+        let pool = context.data::<SqlitePool>()?;
+        location::nearby(pool, lat, long, radius_km).await
+    }
+
+    /// The changes mutations made to a record, oldest first. Deleted records keep theirs.
+    async fn history<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        entity_id: String,
+    ) -> Result<Vec<AuditEntry>> {
+        let pool = context.data::<SqlitePool>()?;
+        let history = sqlx::query("SELECT * FROM audit_log WHERE entity_id = ? ORDER BY id")
+            .bind(entity_id)
+            .map(AuditEntry::from_row)
             .fetch_all(pool)
             .await?;
-        let process_id_labels_tuples = sqlx::query(
+        Ok(history)
+    }
+
+    /// Agents, plans, processes and commitments whose names, titles or descriptions contain
+    /// every word of `term`, best matches first.
+    async fn search<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        term: String,
+        types: Option<Vec<SearchType>>,
+        #[graphql(default = 20)] limit: i32,
+        #[graphql(default)] include_deleted: bool,
+    ) -> Result<Vec<SearchResult>> {
+        let pool = context.data::<SqlitePool>()?;
+        search::search(pool, &term, types, limit, include_deleted).await
+    }
+
+    /// A quantity expressed in another unit of the same dimension.
+    async fn convert_measure<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        has_numerical_value: Decimal,
+        from_unit_id: String,
+        to_unit_id: String,
+    ) -> Result<Measure> {
+        let pool = context.data::<SqlitePool>()?;
+        let mut units = measure::units_by_id(pool).await?;
+        let from_unit = units
+            .get(&from_unit_id)
+            .ok_or_else(|| DomainError::not_found(format!("unit {}", from_unit_id)))?;
+        let to_unit = units
+            .get(&to_unit_id)
+            .ok_or_else(|| DomainError::not_found(format!("unit {}", to_unit_id)))?;
+        let has_numerical_value =
+            measure::convert(has_numerical_value, Some(from_unit), Some(to_unit))?;
+        Ok(Measure {
+            has_numerical_value,
+            has_unit: units.remove(&to_unit_id),
+        })
+    }
+
+    async fn resource_specifications<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        #[graphql(default)] order_by: NameOrderBy,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Page<ResourceSpecification>> {
+        let pool = context.data::<SqlitePool>()?;
+        pagination::paginate(
+            pool,
+            order_by.list("resource_specifications", "name"),
+            after,
+            before,
+            first,
+            last,
+            |row| FromRow::from_row(&row),
+        )
+        .await
+    }
+
+    async fn economic_events<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        process_id: Option<String>,
+    ) -> Result<Vec<EconomicEvent>> {
+        let pool = context.data::<SqlitePool>()?;
+        let economic_events = sqlx::query(
             "
-            SELECT labels.id, name, color, process_id
-            FROM labels
-            INNER JOIN process_labels
-            ON process_labels.label_id = labels.id
-            WHERE process_labels.process_id IN (
-                SELECT id FROM processes
-                WHERE processes.plan_id = ?
-            )",
+            SELECT * FROM economic_events
+            WHERE ? IS NULL OR input_of_id = ? OR output_of_id = ?
+            ORDER BY inserted_at DESC
+            ",
         )
-        .bind(&plan.id)
-        .map(|row| (row.get("process_id"), Label::from_row(row)))
+        .bind(&process_id)
+        .bind(&process_id)
+        .bind(&process_id)
+        .map(EconomicEvent::from_row)
         .fetch_all(pool)
         .await?;
-        let process_id_labels_hashmap: HashMap<String, Vec<Label>> =
-            process_id_labels_tuples.iter().fold(
-                HashMap::<String, Vec<Label>>::new(),
-                |mut acc: HashMap<String, Vec<Label>>, (process_id, label): &(String, Label)| {
-                    let labels = acc.entry(process_id.to_owned()).or_insert_with(Vec::new);
-                    labels.push(label.clone());
-                    acc
-                },
-            );
+        Ok(economic_events.to_vec())
+    }
 
-        let process_id_agents_tuples = sqlx::query(
+    async fn economic_resources<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        agent_id: Option<String>,
+    ) -> Result<Vec<EconomicResource>> {
+        let pool = context.data::<SqlitePool>()?;
+        let mut economic_resources = sqlx::query(
             "
-            SELECT agents.id, name, unique_name, agent_type, process_id
-            FROM agents 
-            INNER JOIN process_agents
-            ON process_agents.agent_id = agents.id
-            WHERE process_agents.process_id IN (
-                SELECT id FROM processes
-                WHERE processes.plan_id = ?
+            SELECT * FROM economic_resources
+            WHERE ? IS NULL OR primary_accountable_id = ?
+            ORDER BY inserted_at DESC
+            ",
+        )
+        .bind(&agent_id)
+        .bind(&agent_id)
+        .map(EconomicResource::from_row)
+        .fetch_all(pool)
+        .await?;
+        let resource_specifications = sqlx::query(
+            "
+            SELECT * FROM resource_specifications
+            WHERE id IN (
+                SELECT conforms_to_id FROM economic_resources
+                WHERE ? IS NULL OR primary_accountable_id = ?
             )",
         )
-        .bind(&plan.id)
-        .map(|row| (row.get("process_id"), Agent::from_row(row)))
+        .bind(&agent_id)
+        .bind(&agent_id)
+        .map(ResourceSpecification::from_row)
         .fetch_all(pool)
         .await?;
-        let process_id_agents_hashmap: HashMap<String, Vec<Agent>> =
-            process_id_agents_tuples.iter().fold(
-                HashMap::<String, Vec<Agent>>::new(),
-                |mut acc: HashMap<String, Vec<Agent>>, (process_id, agent): &(String, Agent)| {
-                    let agents = acc.entry(process_id.to_owned()).or_insert_with(Vec::new);
-                    agents.push(agent.clone());
-                    acc
-                },
-            );
-        let process_id_commitments_tuples = sqlx::query(
+        let resource_specifications_hashmap: HashMap<&String, &ResourceSpecification> =
+            resource_specifications
+                .iter()
+                .map(|resource_specification| (&resource_specification.id, resource_specification))
+                .collect();
+        let agents = sqlx::query(
             "
-            SELECT id, description, inserted_at, process_id, action_id, assigned_agent_id, quantity, unit_id, resource_specification_id
-            FROM commitments 
-            WHERE commitments.process_id IN (
-                SELECT id FROM processes
-                WHERE processes.plan_id = ?
+            SELECT * FROM agents
+            WHERE id IN (
+                SELECT primary_accountable_id FROM economic_resources
+                WHERE ? IS NULL OR primary_accountable_id = ?
             )",
         )
-        .bind(&plan.id)
-        .map(|row| (row.get("process_id"), Commitment::from_row(row)))
+        .bind(&agent_id)
+        .bind(&agent_id)
+        .map(Agent::from_row)
         .fetch_all(pool)
         .await?;
-        let process_id_commitments_hashmap: HashMap<String, Vec<Commitment>> =
-            process_id_commitments_tuples.iter().fold(
-                HashMap::<String, Vec<Commitment>>::new(),
-                |mut acc: HashMap<String, Vec<Commitment>>,
-                 (process_id, commitment): &(String, Commitment)| {
-                    let commitments = acc.entry(process_id.to_owned()).or_insert_with(Vec::new);
-                    commitments.push(commitment.clone());
-                    acc
-                },
-            );
+        let agents_hashmap: HashMap<&String, &Agent> =
+            agents.iter().map(|agent| (&agent.id, agent)).collect();
+        let units = sqlx::query_as!(
+            Unit,
+            r#"
+            SELECT id, label, symbol, dimension,
+                conversion_factor AS "conversion_factor: Decimal",
+                inserted_at AS "inserted_at: DateTime"
+            FROM units
+            "#
+        )
+        .fetch_all(pool)
+        .await?;
+        let units_hashmap: HashMap<&String, &Unit> =
+            units.iter().map(|unit| (&unit.id, unit)).collect();
+        economic_resources.iter_mut().for_each(|r| {
+            r.conforms_to = resource_specifications_hashmap
+                .get(&r.conforms_to_id)
+                .cloned()
+                .cloned();
+            r.primary_accountable = r
+                .primary_accountable_id
+                .as_ref()
+                .and_then(|id| agents_hashmap.get(id).cloned().cloned());
+            let unit = r
+                .unit_id
+                .as_ref()
+                .and_then(|id| units_hashmap.get(id).cloned().cloned());
+            r.set_unit(unit);
+        });
+        Ok(economic_resources.to_vec())
+    }
 
-        let commitment_id_action_tuples = sqlx::query(
+    async fn offers<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<Intent>> {
+        let pool = context.data::<SqlitePool>()?;
+        let mut intents = sqlx::query(
             "
-            SELECT actions.id, name, input_output, actions.inserted_at, commitments.id AS commitment_id
-            FROM actions 
-            JOIN commitments
-            ON actions.id = commitments.action_id
-            JOIN processes
-            ON processes.id = commitments.process_id
-            WHERE processes.id IN (
-                SELECT id FROM processes
-                WHERE processes.plan_id = ?
-            )",
+            SELECT * FROM intents
+            WHERE provider_id IS NOT NULL AND receiver_id IS NULL AND NOT finished
+            ORDER BY inserted_at DESC
+            ",
         )
-        .bind(&plan.id)
-        .map(|row| (row.get("commitment_id"), Action::from_row(row)))
+        .map(Intent::from_row)
         .fetch_all(pool)
         .await?;
-        let commitment_id_action_hashmap: HashMap<String, Action> =
-            commitment_id_action_tuples.iter().fold(
-                HashMap::<String, Action>::new(),
-                |mut acc: HashMap<String, Action>, (commitment_id, action): &(String, Action)| {
-                    let _ = acc
-                        .entry(commitment_id.to_owned())
-                        .or_insert_with(|| action.clone());
-                    acc
-                },
-            );
+        load_intent_relations(pool, &mut intents).await?;
+        Ok(intents.to_vec())
+    }
 
-        let commitment_id_resource_specification_tuples = sqlx::query(
+    async fn requests<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<Intent>> {
+        let pool = context.data::<SqlitePool>()?;
+        let mut intents = sqlx::query(
             "
-            SELECT resource_specifications.id, name, resource_specifications.inserted_at, commitments.id AS commitment_id
-            FROM resource_specifications 
-            JOIN commitments
-            ON resource_specifications.id = commitments.resource_specification_id
-            JOIN processes
-            ON processes.id = commitments.process_id
-            WHERE processes.id IN (
-                SELECT id FROM processes
-                WHERE processes.plan_id = ?
-            )",
+            SELECT * FROM intents
+            WHERE receiver_id IS NOT NULL AND provider_id IS NULL AND NOT finished
+            ORDER BY inserted_at DESC
+            ",
         )
-        .bind(&plan.id)
-        .map(|row| {
-            (
-                row.get("commitment_id"),
-                ResourceSpecification::from_row(row),
-            )
-        })
+        .map(Intent::from_row)
         .fetch_all(pool)
         .await?;
-        let commitment_id_resource_specification_hashmap: HashMap<String, ResourceSpecification> =
-            commitment_id_resource_specification_tuples.iter().fold(
-                HashMap::<String, ResourceSpecification>::new(),
-                |mut acc: HashMap<String, ResourceSpecification>, (commitment_id, resource_specification): &(String, ResourceSpecification)| {
-                    let _ = acc.entry(commitment_id.to_owned()).or_insert_with(|| resource_specification.clone());
-                    acc
-                },
-            );
+        load_intent_relations(pool, &mut intents).await?;
+        Ok(intents.to_vec())
+    }
 
-        let commitment_id_unit_tuples = sqlx::query(
+    async fn proposal<'ctx>(&self, context: &Context<'ctx>, id: String) -> Result<Proposal> {
+        let pool = context.data::<SqlitePool>()?;
+        let proposal = sqlx::query("SELECT * FROM proposals WHERE id = ?")
+            .bind(id)
+            .map(Proposal::from_row)
+            .fetch_one(pool)
+            .await?;
+        let mut proposals = vec![proposal];
+        load_proposal_relations(pool, &mut proposals).await?;
+        Ok(proposals.remove(0))
+    }
+
+    async fn proposals_to<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        agent_id: String,
+    ) -> Result<Vec<Proposal>> {
+        let pool = context.data::<SqlitePool>()?;
+        let mut proposals = sqlx::query(
             "
-            SELECT units.id, label, units.inserted_at, commitments.id AS commitment_id
-            FROM units 
-            JOIN commitments
-            ON units.id = commitments.unit_id
-            JOIN processes
-            ON processes.id = commitments.process_id
-            WHERE processes.id IN (
-                SELECT id FROM processes
-                WHERE processes.plan_id = ?
-            )",
+            SELECT proposals.*
+            FROM proposals
+            JOIN proposed_to
+            ON proposed_to.proposal_id = proposals.id
+            WHERE proposed_to.agent_id = ?
+            ORDER BY proposals.inserted_at DESC
+            ",
         )
-        .bind(&plan.id)
-        .map(|row| (row.get("commitment_id"), Unit::from_row(row)))
+        .bind(agent_id)
+        .map(Proposal::from_row)
         .fetch_all(pool)
         .await?;
-        let commitment_id_unit_hashmap: HashMap<String, Unit> =
-            commitment_id_unit_tuples.iter().fold(
-                HashMap::<String, Unit>::new(),
-                |mut acc: HashMap<String, Unit>, (commitment_id, unit): &(String, Unit)| {
-                    let _ = acc
-                        .entry(commitment_id.to_owned())
-                        .or_insert_with(|| unit.clone());
-                    acc
-                },
-            );
+        load_proposal_relations(pool, &mut proposals).await?;
+        Ok(proposals.to_vec())
+    }
+
+    async fn agreement<'ctx>(&self, context: &Context<'ctx>, id: String) -> Result<Agreement> {
+        let pool = context.data::<SqlitePool>()?;
+        let agreement = sqlx::query("SELECT * FROM agreements WHERE id = ?")
+            .bind(id)
+            .map(Agreement::from_row)
+            .fetch_one(pool)
+            .await?;
+        let mut agreements = vec![agreement];
+        load_agreement_relations(pool, &mut agreements).await?;
+        Ok(agreements.remove(0))
+    }
 
-        let commitment_id_agent_tuples = sqlx::query(
+    async fn agreements<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        agent_id: String,
+    ) -> Result<Vec<Agreement>> {
+        let pool = context.data::<SqlitePool>()?;
+        let mut agreements = sqlx::query(
             "
-            SELECT agents.id, name, unique_name, agents.inserted_at, commitments.id AS commitment_id
-            FROM agents 
-            JOIN commitments
-            ON agents.id = commitments.assigned_agent_id
-            JOIN processes
-            ON processes.id = commitments.process_id
-            WHERE processes.id IN (
-                SELECT id FROM processes
-                WHERE processes.plan_id = ?
-            )",
+            SELECT * FROM agreements
+            WHERE id IN (
+                SELECT clause_of_id FROM commitments
+                WHERE provider_id = ? OR receiver_id = ?
+            )
+            ORDER BY inserted_at DESC
+            ",
         )
-        .bind(&plan.id)
-        .map(|row| (row.get("commitment_id"), Agent::from_row(row)))
+        .bind(&agent_id)
+        .bind(&agent_id)
+        .map(Agreement::from_row)
         .fetch_all(pool)
         .await?;
-        let commitment_id_agent_hashmap: HashMap<String, Agent> =
-            commitment_id_agent_tuples.iter().fold(
-                HashMap::<String, Agent>::new(),
-                |mut acc: HashMap<String, Agent>, (commitment_id, agent): &(String, Agent)| {
-                    let _ = acc
-                        .entry(commitment_id.to_owned())
-                        .or_insert_with(|| agent.clone());
-                    acc
-                },
-            );
+        load_agreement_relations(pool, &mut agreements).await?;
+        Ok(agreements.to_vec())
+    }
 
-        processes.iter_mut().for_each(|p| {
-            p.labels = process_id_labels_hashmap
-                .get(&p.id)
-                .unwrap_or(&vec![])
-                .clone();
-            p.agents = process_id_agents_hashmap
-                .get(&p.id)
-                .unwrap_or(&vec![])
-                .clone();
-
-            let mut commitments = process_id_commitments_hashmap
-                .get(&p.id)
-                .unwrap_or(&vec![])
-                .clone();
-            commitments.iter_mut().for_each(|c| {
-                c.action = commitment_id_action_hashmap.get(&c.id).cloned();
-                c.resource_specification = commitment_id_resource_specification_hashmap
-                    .get(&c.id)
-                    .cloned();
-                c.unit = commitment_id_unit_hashmap.get(&c.id).cloned();
-                c.assigned_agent = commitment_id_agent_hashmap.get(&c.id).cloned();
-            });
-            p.commitments = commitments;
-        });
-        plan.processes = processes;
-        Ok(plan)
+    async fn process_specifications<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+    ) -> Result<Vec<ProcessSpecification>> {
+        let pool = context.data::<SqlitePool>()?;
+        let process_specifications = sqlx::query("SELECT * FROM process_specifications")
+            .map(ProcessSpecification::from_row)
+            .fetch_all(pool)
+            .await?;
+        Ok(process_specifications.to_vec())
     }
 
-    async fn labels<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<Label>> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
-        let labels = sqlx::query_as!(Label, "SELECT * FROM labels ORDER BY inserted_at DESC")
+    async fn recipe_resources<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<RecipeResource>> {
+        let pool = context.data::<SqlitePool>()?;
+        let recipe_resources = sqlx::query("SELECT * FROM recipe_resources")
+            .map(RecipeResource::from_row)
             .fetch_all(pool)
             .await?;
-        Ok(labels.to_vec())
+        Ok(recipe_resources.to_vec())
     }
 
-    async fn actions<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<Action>> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
-        let actions =
-            sqlx::query_as::<_, Action>("SELECT * FROM actions ORDER BY inserted_at DESC")
-                .fetch_all(pool)
-                .await?;
-        Ok(actions.to_vec())
+    async fn recipes<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<Recipe>> {
+        let pool = context.data::<SqlitePool>()?;
+        let mut recipes = sqlx::query("SELECT * FROM recipes ORDER BY name")
+            .map(Recipe::from_row)
+            .fetch_all(pool)
+            .await?;
+        load_recipe_relations(pool, &mut recipes).await?;
+        Ok(recipes.to_vec())
+    }
+
+    async fn recipe<'ctx>(&self, context: &Context<'ctx>, id: String) -> Result<Recipe> {
+        let pool = context.data::<SqlitePool>()?;
+        let recipe = sqlx::query("SELECT * FROM recipes WHERE id = ?")
+            .bind(id)
+            .map(Recipe::from_row)
+            .fetch_one(pool)
+            .await?;
+        let mut recipes = vec![recipe];
+        load_recipe_relations(pool, &mut recipes).await?;
+        Ok(recipes.remove(0))
     }
 
-    async fn units<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<Unit>> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
-        let units = sqlx::query_as!(Unit, "SELECT * FROM units ORDER BY inserted_at DESC")
+    async fn value_equations<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<ValueEquation>> {
+        let pool = context.data::<SqlitePool>()?;
+        let mut value_equations = sqlx::query("SELECT * FROM value_equations ORDER BY name")
+            .map(ValueEquation::from_row)
             .fetch_all(pool)
             .await?;
-        Ok(units.to_vec())
+        load_value_equation_relations(pool, &mut value_equations).await?;
+        Ok(value_equations.to_vec())
     }
 
-    async fn resource_specifications<'ctx>(
+    /// Shares the quantity of an income event between the agents whose `work`, `use` and
+    /// `cite` events contributed to a plan, valued by a value equation. The plan defaults to
+    /// the one the income event or the resource it moves comes from.
+    async fn distribute_income<'ctx>(
         &self,
         context: &Context<'ctx>,
-    ) -> Result<Vec<ResourceSpecification>> {
-        let pool = context
-            .data::<SqlitePool>()
-            .expect("failed to get connection pool");
-        let resource_specifications = sqlx::query_as!(
-            ResourceSpecification,
-            "SELECT * FROM resource_specifications ORDER BY inserted_at DESC",
-        )
-        .fetch_all(pool)
-        .await?;
-        Ok(resource_specifications.to_vec())
+        event_id: String,
+        value_equation_id: String,
+        plan_id: Option<String>,
+    ) -> Result<IncomeDistribution> {
+        let pool = context.data::<SqlitePool>()?;
+        let income_event = sqlx::query("SELECT * FROM economic_events WHERE id = ?")
+            .bind(event_id)
+            .map(EconomicEvent::from_row)
+            .fetch_one(pool)
+            .await?;
+        let value_equation = sqlx::query("SELECT * FROM value_equations WHERE id = ?")
+            .bind(value_equation_id)
+            .map(ValueEquation::from_row)
+            .fetch_one(pool)
+            .await?;
+        let mut value_equations = vec![value_equation];
+        load_value_equation_relations(pool, &mut value_equations).await?;
+        let distribution =
+            contribution::distribute_income(pool, &income_event, &value_equations[0], plan_id)
+                .await?;
+        Ok(distribution)
     }
 }
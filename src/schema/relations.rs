@@ -0,0 +1,207 @@
+use super::error::Result;
+use super::loader::{
+    ActionId, AgentId, AgentPlans, CommitmentFulfillments, EntityHistory, PlanProcesses,
+    ProcessAgents, ProcessCommitments, ProcessLabels, ResourceSpecificationId, SqliteLoader,
+    UnitId,
+};
+use super::location::load_location;
+use super::measure::Decimal;
+use super::provenance::{walk, Direction};
+use super::timeline;
+use super::{
+    Action, Agent, AuditEntry, Commitment, Fulfillment, Label, Location, Plan, Process,
+    ProvenanceNode, ResourceSpecification, TimelineProcess, Unit,
+};
+use async_graphql::dataloader::DataLoader;
+use async_graphql::{ComplexObject, Context};
+use sqlx::sqlite::SqlitePool;
+
+fn loader<'a>(context: &'a Context<'_>) -> Result<&'a DataLoader<SqliteLoader>> {
+    Ok(context.data::<DataLoader<SqliteLoader>>()?)
+}
+
+async fn load_agent(context: &Context<'_>, id: &Option<String>) -> Result<Option<Agent>> {
+    match id {
+        Some(id) => Ok(loader(context)?.load_one(AgentId(id.clone())).await?),
+        None => Ok(None),
+    }
+}
+
+async fn load_history(context: &Context<'_>, id: &str) -> Result<Vec<AuditEntry>> {
+    let history = loader(context)?
+        .load_one(EntityHistory(id.to_string()))
+        .await?;
+    Ok(history.unwrap_or_default())
+}
+
+#[ComplexObject]
+impl Agent {
+    /// Where the agent is usually found, such as the workshop of an organization.
+    async fn primary_location<'ctx>(&self, context: &Context<'ctx>) -> Result<Option<Location>> {
+        load_location(context, &self.primary_location_id).await
+    }
+
+    /// The plans the agent takes part in, newest first.
+    async fn plans<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<Plan>> {
+        let plans = loader(context)?
+            .load_one(AgentPlans(self.id.clone()))
+            .await?;
+        Ok(plans.unwrap_or_default())
+    }
+}
+
+#[ComplexObject]
+impl Plan {
+    async fn processes<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<Process>> {
+        let processes = loader(context)?
+            .load_one(PlanProcesses(self.id.clone()))
+            .await?;
+        Ok(processes.unwrap_or_default())
+    }
+
+    /// The changes mutations made to the plan, oldest first.
+    async fn history<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<AuditEntry>> {
+        load_history(context, &self.id).await
+    }
+
+    /// The processes of the plan with their critical path schedule, by earliest start.
+    async fn timeline<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<TimelineProcess>> {
+        let processes = loader(context)?
+            .load_one(PlanProcesses(self.id.clone()))
+            .await?;
+        let pool = context.data::<SqlitePool>()?;
+        timeline::timeline(pool, processes.unwrap_or_default()).await
+    }
+}
+
+#[ComplexObject]
+impl Process {
+    async fn labels<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<Label>> {
+        let labels = loader(context)?
+            .load_one(ProcessLabels(self.id.clone()))
+            .await?;
+        Ok(labels.unwrap_or_default())
+    }
+
+    async fn agents<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<Agent>> {
+        let agents = loader(context)?
+            .load_one(ProcessAgents(self.id.clone()))
+            .await?;
+        Ok(agents.unwrap_or_default())
+    }
+
+    async fn commitments<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<Commitment>> {
+        let commitments = loader(context)?
+            .load_one(ProcessCommitments(self.id.clone()))
+            .await?;
+        Ok(commitments.unwrap_or_default())
+    }
+
+    /// The inputs of this process and where they came from, nearest first.
+    async fn trace<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<ProvenanceNode>> {
+        let pool = context.data::<SqlitePool>()?;
+        let start = ProvenanceNode::Process(self.clone());
+        Ok(walk(pool, start, Direction::Trace).await?)
+    }
+
+    /// The outputs of this process and where they went, nearest first.
+    async fn track<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<ProvenanceNode>> {
+        let pool = context.data::<SqlitePool>()?;
+        let start = ProvenanceNode::Process(self.clone());
+        Ok(walk(pool, start, Direction::Track).await?)
+    }
+
+    async fn at_location<'ctx>(&self, context: &Context<'ctx>) -> Result<Option<Location>> {
+        load_location(context, &self.at_location_id).await
+    }
+
+    /// The changes mutations made to the process, oldest first.
+    async fn history<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<AuditEntry>> {
+        load_history(context, &self.id).await
+    }
+}
+
+impl Commitment {
+    async fn load_fulfillments(&self, context: &Context<'_>) -> Result<Vec<Fulfillment>> {
+        let fulfillments = loader(context)?
+            .load_one(CommitmentFulfillments(self.id.clone()))
+            .await?;
+        Ok(fulfillments.unwrap_or_default())
+    }
+}
+
+#[ComplexObject]
+impl Commitment {
+    async fn action<'ctx>(&self, context: &Context<'ctx>) -> Result<Option<Action>> {
+        Ok(loader(context)?
+            .load_one(ActionId(self.action_id.clone()))
+            .await?)
+    }
+
+    async fn assigned_agent<'ctx>(&self, context: &Context<'ctx>) -> Result<Option<Agent>> {
+        load_agent(context, &self.assigned_agent_id).await
+    }
+
+    async fn provider<'ctx>(&self, context: &Context<'ctx>) -> Result<Option<Agent>> {
+        load_agent(context, &self.provider_id).await
+    }
+
+    async fn receiver<'ctx>(&self, context: &Context<'ctx>) -> Result<Option<Agent>> {
+        load_agent(context, &self.receiver_id).await
+    }
+
+    async fn unit<'ctx>(&self, context: &Context<'ctx>) -> Result<Option<Unit>> {
+        match &self.unit_id {
+            Some(id) => Ok(loader(context)?.load_one(UnitId(id.clone())).await?),
+            None => Ok(None),
+        }
+    }
+
+    async fn resource_specification<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+    ) -> Result<Option<ResourceSpecification>> {
+        match &self.resource_specification_id {
+            Some(id) => Ok(loader(context)?
+                .load_one(ResourceSpecificationId(id.clone()))
+                .await?),
+            None => Ok(None),
+        }
+    }
+
+    async fn fulfilled_by<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<Fulfillment>> {
+        self.load_fulfillments(context).await
+    }
+
+    /// The quantity not yet covered by fulfillments.
+    async fn remaining_quantity<'ctx>(&self, context: &Context<'ctx>) -> Result<Decimal> {
+        let effort = loader(context)?
+            .load_one(ActionId(self.action_id.clone()))
+            .await?
+            .map(|action| action.is_effort())
+            .unwrap_or(false);
+        let fulfilled_quantity: Decimal = self
+            .load_fulfillments(context)
+            .await?
+            .iter()
+            .map(|fulfillment| fulfillment.quantity(effort))
+            .sum();
+        Ok((self.quantity - fulfilled_quantity).max(Decimal::ZERO))
+    }
+
+    async fn at_location<'ctx>(&self, context: &Context<'ctx>) -> Result<Option<Location>> {
+        load_location(context, &self.at_location_id).await
+    }
+
+    /// The changes mutations made to the commitment, oldest first.
+    async fn history<'ctx>(&self, context: &Context<'ctx>) -> Result<Vec<AuditEntry>> {
+        load_history(context, &self.id).await
+    }
+}
+
+#[ComplexObject]
+impl AuditEntry {
+    async fn agent<'ctx>(&self, context: &Context<'ctx>) -> Result<Option<Agent>> {
+        load_agent(context, &self.agent_id).await
+    }
+}
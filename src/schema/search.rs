@@ -0,0 +1,158 @@
+use super::error::{DomainError, Result};
+use super::{Agent, Commitment, Plan, Process, SearchNode, SearchResult, SearchType};
+use sqlx::{sqlite::SqlitePool, Row};
+use std::collections::HashMap;
+
+/// Largest number of results served by one search.
+const MAX_RESULTS: i32 = 100;
+
+const ALL_TYPES: [SearchType; 4] = [
+    SearchType::Agent,
+    SearchType::Plan,
+    SearchType::Process,
+    SearchType::Commitment,
+];
+
+/// Ids of the deleted records of every type, counting the processes and commitments of a
+/// deleted plan and the commitments of a deleted process as deleted.
+const DELETED_IDS: &str = "
+    SELECT id FROM agents WHERE deleted_at IS NOT NULL
+    UNION ALL
+    SELECT id FROM plans WHERE deleted_at IS NOT NULL
+    UNION ALL
+    SELECT processes.id FROM processes
+    LEFT JOIN plans ON plans.id = processes.plan_id
+    WHERE processes.deleted_at IS NOT NULL OR plans.deleted_at IS NOT NULL
+    UNION ALL
+    SELECT commitments.id FROM commitments
+    LEFT JOIN processes ON processes.id = commitments.process_id
+    LEFT JOIN plans ON plans.id = processes.plan_id
+    WHERE commitments.deleted_at IS NOT NULL
+    OR processes.deleted_at IS NOT NULL
+    OR plans.deleted_at IS NOT NULL
+";
+
+/// The `kind` of the records of a type in the search index.
+fn kind(search_type: SearchType) -> &'static str {
+    match search_type {
+        SearchType::Agent => "agent",
+        SearchType::Plan => "plan",
+        SearchType::Process => "process",
+        SearchType::Commitment => "commitment",
+    }
+}
+
+/// Turns what a member typed into an FTS5 query for the records containing every word, the
+/// last one possibly unfinished. Words are quoted so that punctuation and FTS5 operators in
+/// them are read as text.
+fn match_expression(term: &str) -> Option<String> {
+    let words = term
+        .split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>();
+    if words.is_empty() {
+        None
+    } else {
+        Some(format!("{}*", words.join(" ")))
+    }
+}
+
+/// Agents, plans, processes and commitments whose text matches `term`, best matches first.
+/// Deleted records are only found with `include_deleted`.
+pub(super) async fn search(
+    pool: &SqlitePool,
+    term: &str,
+    types: Option<Vec<SearchType>>,
+    limit: i32,
+    include_deleted: bool,
+) -> Result<Vec<SearchResult>> {
+    if !(1..=MAX_RESULTS).contains(&limit) {
+        return Err(DomainError::validation(format!(
+            "limit must be between 1 and {}",
+            MAX_RESULTS
+        )));
+    }
+    let expression = match_expression(term)
+        .ok_or_else(|| DomainError::validation("term must contain a word"))?;
+    let kinds = types
+        .unwrap_or_else(|| ALL_TYPES.to_vec())
+        .into_iter()
+        .map(kind)
+        .collect::<Vec<_>>();
+    let sql = format!(
+        "
+        SELECT kind, id, snippet(search_index, -1, '<mark>', '</mark>', '…', 16) AS snippet
+        FROM search_index
+        WHERE search_index MATCH ? AND kind IN (SELECT value FROM json_each(?))
+        AND (? OR id NOT IN ({}))
+        ORDER BY rank
+        LIMIT ?
+        ",
+        DELETED_IDS
+    );
+    let hits: Vec<(String, String, String)> = sqlx::query(&sql)
+        .bind(expression)
+        .bind(serde_json::json!(kinds).to_string())
+        .bind(include_deleted)
+        .bind(limit)
+        .map(|row| (row.get("kind"), row.get("id"), row.get("snippet")))
+        .fetch_all(pool)
+        .await?;
+    let ids = |search_type| {
+        serde_json::json!(hits
+            .iter()
+            .filter(|(kind, _, _)| kind == self::kind(search_type))
+            .map(|(_, id, _)| id)
+            .collect::<Vec<_>>())
+        .to_string()
+    };
+
+    let agents = sqlx::query("SELECT * FROM agents WHERE id IN (SELECT value FROM json_each(?))")
+        .bind(ids(SearchType::Agent))
+        .map(Agent::from_row)
+        .fetch_all(pool)
+        .await?;
+    let plans = sqlx::query("SELECT * FROM plans WHERE id IN (SELECT value FROM json_each(?))")
+        .bind(ids(SearchType::Plan))
+        .map(Plan::from_row)
+        .fetch_all(pool)
+        .await?;
+    let processes =
+        sqlx::query("SELECT * FROM processes WHERE id IN (SELECT value FROM json_each(?))")
+            .bind(ids(SearchType::Process))
+            .map(Process::from_row)
+            .fetch_all(pool)
+            .await?;
+    let commitments =
+        sqlx::query("SELECT * FROM commitments WHERE id IN (SELECT value FROM json_each(?))")
+            .bind(ids(SearchType::Commitment))
+            .map(Commitment::from_row)
+            .fetch_all(pool)
+            .await?;
+
+    // ULIDs are unique across tables
+    let mut nodes: HashMap<String, SearchNode> = agents
+        .into_iter()
+        .map(|agent| (agent.id.clone(), SearchNode::Agent(agent)))
+        .chain(
+            plans
+                .into_iter()
+                .map(|plan| (plan.id.clone(), SearchNode::Plan(plan))),
+        )
+        .chain(
+            processes
+                .into_iter()
+                .map(|process| (process.id.clone(), SearchNode::Process(process))),
+        )
+        .chain(
+            commitments
+                .into_iter()
+                .map(|commitment| (commitment.id.clone(), SearchNode::Commitment(commitment))),
+        )
+        .collect();
+    Ok(hits
+        .into_iter()
+        .filter_map(|(_, id, snippet)| nodes.remove(&id).map(|node| SearchResult { node, snippet }))
+        .collect())
+}
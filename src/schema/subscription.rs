@@ -0,0 +1,185 @@
+use super::error::Result;
+use super::{Agent, ChangeKind, Commitment, CommitmentChange, Plan};
+use async_graphql::{Context, Subscription};
+use futures::{Stream, StreamExt};
+use sqlx::sqlite::SqlitePool;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Changes a subscriber can fall behind by before it misses some.
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Something a mutation changed.
+#[derive(Clone, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub(super) enum Change {
+    /// The plan with this id, or one of its processes or commitments.
+    Plan(String),
+    Commitment(CommitmentChange),
+    AgentCreated(Agent),
+}
+
+/// Carries the changes made by mutations to the subscriptions of every client connected to
+/// this process.
+pub struct ChangeBus {
+    sender: broadcast::Sender<Change>,
+}
+
+impl Default for ChangeBus {
+    fn default() -> Self {
+        ChangeBus::new()
+    }
+}
+
+impl ChangeBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        ChangeBus { sender }
+    }
+
+    pub(super) fn publish(&self, change: Change) {
+        // sending only fails when nobody is subscribed
+        let _ = self.sender.send(change);
+    }
+
+    /// Publishes a change to the plan of a process, if it belongs to one.
+    pub(super) async fn publish_process_change(
+        &self,
+        pool: &SqlitePool,
+        process_id: &str,
+    ) -> sqlx::Result<()> {
+        let plan_id: Option<Option<String>> =
+            sqlx::query_scalar("SELECT plan_id FROM processes WHERE id = ?")
+                .bind(process_id)
+                .fetch_optional(pool)
+                .await?;
+        if let Some(plan_id) = plan_id.flatten() {
+            self.publish(Change::Plan(plan_id));
+        }
+        Ok(())
+    }
+
+    /// Publishes a change to a commitment and to the plan of its process.
+    pub(super) async fn publish_commitment_change(
+        &self,
+        pool: &SqlitePool,
+        kind: ChangeKind,
+        commitment: Commitment,
+    ) -> sqlx::Result<()> {
+        if let Some(process_id) = &commitment.process_id {
+            self.publish_process_change(pool, process_id).await?;
+        }
+        self.publish(Change::Commitment(CommitmentChange { kind, commitment }));
+        Ok(())
+    }
+
+    /// Publishes commitments created together in a plan, whose change the caller publishes once.
+    pub(super) fn publish_created_commitments(&self, commitments: Vec<Commitment>) {
+        for commitment in commitments {
+            self.publish(Change::Commitment(CommitmentChange {
+                kind: ChangeKind::Created,
+                commitment,
+            }));
+        }
+    }
+
+    /// Publishes the current state of an updated commitment, unless it no longer exists.
+    pub(super) async fn publish_commitment_update(
+        &self,
+        pool: &SqlitePool,
+        commitment_id: &str,
+    ) -> sqlx::Result<()> {
+        let commitment = sqlx::query("SELECT * FROM commitments WHERE id = ?")
+            .bind(commitment_id)
+            .map(Commitment::from_row)
+            .fetch_optional(pool)
+            .await?;
+        match commitment {
+            Some(commitment) => {
+                self.publish_commitment_change(pool, ChangeKind::Updated, commitment)
+                    .await
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Changes published from now on. A subscriber that falls behind skips what it missed.
+    fn subscribe(&self) -> impl Stream<Item = Change> {
+        futures::stream::unfold(self.sender.subscribe(), |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(change) => return Some((change, receiver)),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+}
+
+pub struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    /// The plan each time it, one of its processes or one of their commitments changes.
+    async fn plan_updated<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        plan_id: String,
+    ) -> Result<impl Stream<Item = Result<Plan>>> {
+        let pool = context.data::<SqlitePool>()?.clone();
+        let bus = context.data::<ChangeBus>()?;
+        Ok(bus.subscribe().filter_map(move |change| {
+            let pool = pool.clone();
+            let plan_id = plan_id.clone();
+            async move {
+                match change {
+                    Change::Plan(id) if id == plan_id => {
+                        sqlx::query("SELECT * FROM plans WHERE id = ?")
+                            .bind(id)
+                            .map(Plan::from_row)
+                            .fetch_optional(&pool)
+                            .await
+                            .map_err(Into::into)
+                            .transpose()
+                    }
+                    _ => None,
+                }
+            }
+        }))
+    }
+
+    /// Commitments of a process as they are created, updated and deleted.
+    async fn commitment_changed<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+        process_id: String,
+    ) -> Result<impl Stream<Item = CommitmentChange>> {
+        let bus = context.data::<ChangeBus>()?;
+        Ok(bus.subscribe().filter_map(move |change| {
+            let process_id = process_id.clone();
+            async move {
+                match change {
+                    Change::Commitment(change)
+                        if change.commitment.process_id.as_ref() == Some(&process_id) =>
+                    {
+                        Some(change)
+                    }
+                    _ => None,
+                }
+            }
+        }))
+    }
+
+    async fn agent_created<'ctx>(
+        &self,
+        context: &Context<'ctx>,
+    ) -> Result<impl Stream<Item = Agent>> {
+        let bus = context.data::<ChangeBus>()?;
+        Ok(bus.subscribe().filter_map(|change| async move {
+            match change {
+                Change::AgentCreated(agent) => Some(agent),
+                _ => None,
+            }
+        }))
+    }
+}
@@ -0,0 +1,125 @@
+use async_graphql::{InputValueError, InputValueResult, Scalar, ScalarType, Value};
+use chrono::{Duration, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use sqlx::{
+    decode::Decode,
+    encode::{Encode, IsNull},
+    error::BoxDynError,
+    sqlite::{Sqlite, SqliteArgumentValue, SqliteTypeInfo, SqliteValueRef},
+    Type, ValueRef,
+};
+use std::ops::{Add, Sub};
+
+/// How dates are stored: UTC, in the format of `CURRENT_TIMESTAMP`, so that those the
+/// database sets and those we set compare and sort as text.
+const STORAGE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// A point in time, with a precision of a second.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime(chrono::DateTime<Utc>);
+
+impl DateTime {
+    pub fn now() -> Self {
+        DateTime::from(Utc::now())
+    }
+
+    /// The date as it is stored, to compare with stored dates in SQL.
+    pub fn stored(&self) -> String {
+        self.0.format(STORAGE_FORMAT).to_string()
+    }
+}
+
+/// The epoch, for records built before they are read.
+impl Default for DateTime {
+    fn default() -> Self {
+        DateTime(Utc.timestamp(0, 0))
+    }
+}
+
+impl From<chrono::DateTime<Utc>> for DateTime {
+    fn from(date: chrono::DateTime<Utc>) -> Self {
+        DateTime(Utc.timestamp(date.timestamp(), 0))
+    }
+}
+
+impl Add<Duration> for DateTime {
+    type Output = DateTime;
+
+    fn add(self, duration: Duration) -> DateTime {
+        DateTime(self.0 + duration)
+    }
+}
+
+impl Sub<Duration> for DateTime {
+    type Output = DateTime;
+
+    fn sub(self, duration: Duration) -> DateTime {
+        DateTime(self.0 - duration)
+    }
+}
+
+impl Sub for DateTime {
+    type Output = Duration;
+
+    fn sub(self, other: DateTime) -> Duration {
+        self.0 - other.0
+    }
+}
+
+/// Dates are read from RFC 3339 strings in any time zone, such as
+/// `2022-05-02T10:00:00+02:00`, and written as RFC 3339 strings in UTC.
+#[Scalar]
+impl ScalarType for DateTime {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        match &value {
+            Value::String(s) => chrono::DateTime::parse_from_rfc3339(s)
+                .map(|date| DateTime::from(date.with_timezone(&Utc)))
+                .map_err(|_| InputValueError::custom("expected an RFC 3339 date")),
+            _ => Err(InputValueError::expected_type(value)),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::String(self.0.to_rfc3339_opts(SecondsFormat::Secs, true))
+    }
+}
+
+impl Type<Sqlite> for DateTime {
+    fn type_info() -> SqliteTypeInfo {
+        <String as Type<Sqlite>>::type_info()
+    }
+
+    fn compatible(ty: &SqliteTypeInfo) -> bool {
+        <String as Type<Sqlite>>::compatible(ty) || <i64 as Type<Sqlite>>::compatible(ty)
+    }
+}
+
+impl<'q> Encode<'q, Sqlite> for DateTime {
+    fn encode_by_ref(&self, args: &mut Vec<SqliteArgumentValue<'q>>) -> IsNull {
+        <String as Encode<Sqlite>>::encode(self.stored(), args)
+    }
+}
+
+/// Besides the storage format, reads the seconds since the epoch and the RFC 3339 dates of
+/// databases that weren't migrated yet.
+impl<'r> Decode<'r, Sqlite> for DateTime {
+    fn decode(value: SqliteValueRef<'r>) -> Result<DateTime, BoxDynError> {
+        if <i64 as Type<Sqlite>>::compatible(&value.type_info()) {
+            let seconds = <i64 as Decode<Sqlite>>::decode(value)?;
+            return Utc
+                .timestamp_opt(seconds, 0)
+                .single()
+                .map(DateTime)
+                .ok_or_else(|| {
+                    format!("{} seconds since the epoch is not a date", seconds).into()
+                });
+        }
+        let text = <&str as Decode<Sqlite>>::decode(value)?;
+        NaiveDateTime::parse_from_str(text, STORAGE_FORMAT)
+            .map(|date| DateTime(Utc.from_utc_datetime(&date)))
+            .or_else(|_| {
+                chrono::DateTime::parse_from_rfc3339(text)
+                    .map(|date| DateTime::from(date.with_timezone(&Utc)))
+            })
+            .map_err(|_| format!("{} is not a date", text).into())
+    }
+}
@@ -0,0 +1,385 @@
+use super::error::{DomainError, Result};
+use super::time::DateTime;
+use super::{InputOutput, Process, TimelineProcess};
+use chrono::Duration;
+use sqlx::{
+    sqlite::{SqlitePool, SqliteRow},
+    Row,
+};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+/// Commitments of the processes with a resource specification and an action that is an input
+/// or an output, as `(process_id, resource_specification_id, input_output)`.
+async fn fetch_flows(
+    pool: &SqlitePool,
+    processes: &[Process],
+) -> sqlx::Result<Vec<(String, String, InputOutput)>> {
+    let process_ids = serde_json::json!(processes.iter().map(|p| &p.id).collect::<Vec<_>>());
+    sqlx::query(
+        "
+        SELECT commitments.process_id, commitments.resource_specification_id, actions.input_output
+        FROM commitments
+        JOIN actions ON actions.id = commitments.action_id
+        WHERE commitments.process_id IN (SELECT value FROM json_each(?))
+        AND commitments.resource_specification_id IS NOT NULL
+        AND actions.input_output IS NOT NULL
+        AND commitments.deleted_at IS NULL
+        ",
+    )
+    .bind(process_ids.to_string())
+    .map(|row: SqliteRow| {
+        (
+            row.get("process_id"),
+            row.get("resource_specification_id"),
+            row.get("input_output"),
+        )
+    })
+    .fetch_all(pool)
+    .await
+}
+
+/// For each process, the processes producing a resource specification it takes as input.
+fn dependencies(
+    processes: &[Process],
+    flows: &[(String, String, InputOutput)],
+) -> Vec<BTreeSet<usize>> {
+    let positions: HashMap<&str, usize> = processes
+        .iter()
+        .enumerate()
+        .map(|(index, p)| (p.id.as_str(), index))
+        .collect();
+    let mut producers: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (process_id, resource_specification_id, input_output) in flows {
+        if *input_output == InputOutput::Output {
+            producers
+                .entry(resource_specification_id.as_str())
+                .or_default()
+                .push(positions[process_id.as_str()]);
+        }
+    }
+    let mut predecessors = vec![BTreeSet::new(); processes.len()];
+    for (process_id, resource_specification_id, input_output) in flows {
+        if *input_output != InputOutput::Input {
+            continue;
+        }
+        let consumer = positions[process_id.as_str()];
+        let consumer_producers = producers
+            .get(resource_specification_id.as_str())
+            .into_iter()
+            .flatten();
+        for &producer in consumer_producers {
+            if producer != consumer {
+                predecessors[consumer].insert(producer);
+            }
+        }
+    }
+    predecessors
+}
+
+/// Orders the processes so that each comes after those it depends on.
+fn topological_order(
+    processes: &[Process],
+    predecessors: &[BTreeSet<usize>],
+    successors: &[Vec<usize>],
+) -> Result<Vec<usize>> {
+    let mut waiting_for: Vec<usize> = predecessors.iter().map(BTreeSet::len).collect();
+    let mut pending: VecDeque<usize> = (0..processes.len())
+        .filter(|&index| waiting_for[index] == 0)
+        .collect();
+    let mut order = Vec::with_capacity(processes.len());
+    while let Some(index) = pending.pop_front() {
+        order.push(index);
+        for &successor in &successors[index] {
+            waiting_for[successor] -= 1;
+            if waiting_for[successor] == 0 {
+                pending.push_back(successor);
+            }
+        }
+    }
+    if let Some(mut index) = (0..processes.len()).find(|&index| waiting_for[index] > 0) {
+        // Every process left waits for another one left. Going back through them long enough
+        // ends up inside the cycle rather than after it.
+        for _ in 0..processes.len() {
+            index = *predecessors[index]
+                .iter()
+                .find(|&&predecessor| waiting_for[predecessor] > 0)
+                .expect("a waiting process waits for another one");
+        }
+        return Err(DomainError::validation(format!(
+            "process {} is part of a dependency cycle",
+            processes[index].title
+        )));
+    }
+    Ok(order)
+}
+
+/// Schedules the processes of a plan with the critical path method.
+///
+/// A process depends on the processes with an output commitment of a resource specification
+/// it has an input commitment of. It lasts from its start to its due date, or no time when it
+/// lacks one of them. It starts at the earliest when those it depends on are done, and not
+/// before its own start. The plan ends when its last process is done, and every process has to
+/// be done at the latest when the processes depending on it have to start, or when the plan
+/// ends. Processes without slack between the two are critical.
+pub(super) async fn timeline(
+    pool: &SqlitePool,
+    processes: Vec<Process>,
+) -> Result<Vec<TimelineProcess>> {
+    let flows = fetch_flows(pool, &processes).await?;
+    let predecessors = dependencies(&processes, &flows);
+    let mut successors = vec![vec![]; processes.len()];
+    for (index, process_predecessors) in predecessors.iter().enumerate() {
+        for &predecessor in process_predecessors {
+            successors[predecessor].push(index);
+        }
+    }
+    let order = topological_order(&processes, &predecessors, &successors)?;
+
+    let origin = processes
+        .iter()
+        .filter_map(|p| p.start_at.or(p.due_at))
+        .min();
+    let offset = |date: DateTime| origin.map_or(0, |origin| (date - origin).num_seconds());
+    let durations: Vec<i64> = processes
+        .iter()
+        .map(|p| match (p.start_at, p.due_at) {
+            (Some(start_at), Some(due_at)) => (due_at - start_at).num_seconds().max(0),
+            _ => 0,
+        })
+        .collect();
+
+    let mut earliest_starts = vec![0; processes.len()];
+    for &index in &order {
+        let planned_start = processes[index].start_at.or(processes[index].due_at);
+        earliest_starts[index] = predecessors[index]
+            .iter()
+            .map(|&predecessor| earliest_starts[predecessor] + durations[predecessor])
+            .chain(planned_start.map(offset))
+            .max()
+            .unwrap_or(0);
+    }
+    let end = (0..processes.len())
+        .map(|index| earliest_starts[index] + durations[index])
+        .max()
+        .unwrap_or(0);
+    let mut latest_starts = vec![0; processes.len()];
+    for &index in order.iter().rev() {
+        let latest_due = successors[index]
+            .iter()
+            .map(|&successor| latest_starts[successor])
+            .min()
+            .unwrap_or(end);
+        latest_starts[index] = latest_due - durations[index];
+    }
+
+    let date = |seconds: i64| origin.map(|origin| origin + Duration::seconds(seconds));
+    let ids: Vec<String> = processes.iter().map(|p| p.id.clone()).collect();
+    let mut timeline = processes
+        .into_iter()
+        .enumerate()
+        .map(|(index, process)| {
+            let slack_seconds = latest_starts[index] - earliest_starts[index];
+            TimelineProcess {
+                process,
+                depends_on_ids: predecessors[index]
+                    .iter()
+                    .map(|&predecessor| ids[predecessor].clone())
+                    .collect(),
+                duration_seconds: durations[index],
+                earliest_start_at: date(earliest_starts[index]),
+                earliest_due_at: date(earliest_starts[index] + durations[index]),
+                latest_start_at: date(latest_starts[index]),
+                latest_due_at: date(latest_starts[index] + durations[index]),
+                slack_seconds,
+                critical: slack_seconds == 0,
+            }
+        })
+        .zip(&earliest_starts)
+        .collect::<Vec<_>>();
+    // Stable, so processes starting together keep their order.
+    timeline.sort_by_key(|(_, &earliest_start)| earliest_start);
+    Ok(timeline.into_iter().map(|(process, _)| process).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_pool;
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    /// Milling makes flour, which kneading and sifting take to make dough and bran, which
+    /// baking takes.
+    const DIAMOND: &str = "
+        INSERT INTO actions (id, name, input_output) VALUES ('consume', 'consume', 'Input');
+        INSERT INTO actions (id, name, input_output) VALUES ('produce', 'produce', 'Output');
+        INSERT INTO resource_specifications (id, name, unique_name) VALUES ('flour', 'flour', 'flour');
+        INSERT INTO resource_specifications (id, name, unique_name) VALUES ('dough', 'dough', 'dough');
+        INSERT INTO resource_specifications (id, name, unique_name) VALUES ('bran', 'bran', 'bran');
+        INSERT INTO plans (id, title) VALUES ('bakery', 'Bakery');
+        INSERT INTO processes (id, title, plan_id) VALUES ('mill', 'Mill', 'bakery');
+        INSERT INTO processes (id, title, plan_id) VALUES ('knead', 'Knead', 'bakery');
+        INSERT INTO processes (id, title, plan_id) VALUES ('sift', 'Sift', 'bakery');
+        INSERT INTO processes (id, title, plan_id) VALUES ('bake', 'Bake', 'bakery');
+        INSERT INTO commitments (id, process_id, action_id, resource_specification_id)
+        VALUES ('mill-flour', 'mill', 'produce', 'flour'),
+               ('knead-flour', 'knead', 'consume', 'flour'),
+               ('knead-dough', 'knead', 'produce', 'dough'),
+               ('sift-flour', 'sift', 'consume', 'flour'),
+               ('sift-bran', 'sift', 'produce', 'bran'),
+               ('bake-dough', 'bake', 'consume', 'dough'),
+               ('bake-bran', 'bake', 'consume', 'bran');
+    ";
+
+    fn at(hour: u32) -> Option<DateTime> {
+        Some(Utc.ymd(2022, 5, 2).and_hms(hour, 0, 0).into())
+    }
+
+    /// The processes of the diamond, lasting from `start` to `due` hours.
+    fn processes(hours: &[(&str, u32, u32)]) -> Vec<Process> {
+        hours
+            .iter()
+            .map(|&(id, start, due)| Process {
+                id: id.to_string(),
+                title: id.to_string(),
+                start_at: at(start),
+                due_at: at(due),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn flow(
+        process_id: &str,
+        resource_specification_id: &str,
+        input_output: InputOutput,
+    ) -> (String, String, InputOutput) {
+        (
+            process_id.to_string(),
+            resource_specification_id.to_string(),
+            input_output,
+        )
+    }
+
+    fn diamond_flows() -> Vec<(String, String, InputOutput)> {
+        vec![
+            flow("mill", "flour", InputOutput::Output),
+            flow("knead", "flour", InputOutput::Input),
+            flow("knead", "dough", InputOutput::Output),
+            flow("sift", "flour", InputOutput::Input),
+            flow("sift", "bran", InputOutput::Output),
+            flow("bake", "dough", InputOutput::Input),
+            flow("bake", "bran", InputOutput::Input),
+        ]
+    }
+
+    fn successors(predecessors: &[BTreeSet<usize>]) -> Vec<Vec<usize>> {
+        let mut successors = vec![vec![]; predecessors.len()];
+        for (index, process_predecessors) in predecessors.iter().enumerate() {
+            for &predecessor in process_predecessors {
+                successors[predecessor].push(index);
+            }
+        }
+        successors
+    }
+
+    #[test]
+    fn consumers_depend_on_the_producers_of_their_inputs() {
+        let processes = processes(&[
+            ("mill", 8, 9),
+            ("knead", 9, 11),
+            ("sift", 9, 10),
+            ("bake", 11, 12),
+        ]);
+        let predecessors = dependencies(&processes, &diamond_flows());
+        assert_eq!(
+            predecessors,
+            [
+                BTreeSet::new(),
+                BTreeSet::from([0]),
+                BTreeSet::from([0]),
+                BTreeSet::from([1, 2]),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_process_using_what_it_makes_does_not_depend_on_itself() {
+        let processes = processes(&[("mill", 8, 9)]);
+        let flows = [
+            flow("mill", "flour", InputOutput::Input),
+            flow("mill", "flour", InputOutput::Output),
+        ];
+        assert_eq!(dependencies(&processes, &flows), [BTreeSet::new()]);
+    }
+
+    #[test]
+    fn processes_come_after_those_they_depend_on() {
+        // listed backwards, so that the order has to be worked out
+        let processes = processes(&[
+            ("bake", 11, 12),
+            ("sift", 9, 10),
+            ("knead", 9, 11),
+            ("mill", 8, 9),
+        ]);
+        let predecessors = dependencies(&processes, &diamond_flows());
+        let order =
+            topological_order(&processes, &predecessors, &successors(&predecessors)).unwrap();
+        let ids: Vec<&str> = order
+            .iter()
+            .map(|&index| processes[index].id.as_str())
+            .collect();
+        assert_eq!(ids, ["mill", "sift", "knead", "bake"]);
+    }
+
+    #[test]
+    fn a_dependency_cycle_is_rejected() {
+        let processes = processes(&[("mill", 8, 9), ("knead", 9, 11), ("bake", 11, 12)]);
+        // milling needs the bread baking makes
+        let flows = [
+            flow("mill", "bread", InputOutput::Input),
+            flow("mill", "flour", InputOutput::Output),
+            flow("knead", "flour", InputOutput::Input),
+            flow("knead", "dough", InputOutput::Output),
+            flow("bake", "dough", InputOutput::Input),
+            flow("bake", "bread", InputOutput::Output),
+        ];
+        let predecessors = dependencies(&processes, &flows);
+        let error =
+            topological_order(&processes, &predecessors, &successors(&predecessors)).unwrap_err();
+        match error {
+            DomainError::Validation(message) => assert!(message.contains("dependency cycle")),
+            error => panic!("unexpected error {:?}", error),
+        }
+    }
+
+    #[tokio::test]
+    async fn the_shorter_branch_of_a_diamond_has_slack() {
+        let pool = test_pool(DIAMOND).await;
+        // kneading takes two hours and sifting one
+        let processes = processes(&[
+            ("mill", 8, 9),
+            ("knead", 9, 11),
+            ("sift", 9, 10),
+            ("bake", 11, 12),
+        ]);
+        let timeline = timeline(&pool, processes).await.unwrap();
+        let slack: Vec<(&str, i64, bool)> = timeline
+            .iter()
+            .map(|p| (p.process.id.as_str(), p.slack_seconds, p.critical))
+            .collect();
+        assert_eq!(
+            slack,
+            [
+                ("mill", 0, true),
+                ("knead", 0, true),
+                ("sift", 3600, false),
+                ("bake", 0, true),
+            ]
+        );
+        let sift = &timeline[2];
+        assert_eq!(sift.depends_on_ids, ["mill"]);
+        assert_eq!(sift.earliest_start_at, at(9));
+        assert_eq!(sift.latest_start_at, at(10));
+        assert_eq!(timeline[3].earliest_due_at, at(12));
+    }
+}
@@ -0,0 +1,470 @@
+use super::error::{DomainError, Result};
+use super::measure::Decimal;
+use super::time::DateTime;
+use async_graphql::async_trait::async_trait;
+use sqlx::sqlite::{SqliteConnection, SqlitePool};
+use std::fmt::Display;
+
+/// Tables whose deleted records can't be referred to anymore.
+const SOFT_DELETED_TABLES: [&str; 4] = ["agents", "plans", "processes", "commitments"];
+
+/// Color keywords of CSS Color Module Level 4.
+const COLOR_NAMES: [&str; 149] = [
+    "aliceblue",
+    "antiquewhite",
+    "aqua",
+    "aquamarine",
+    "azure",
+    "beige",
+    "bisque",
+    "black",
+    "blanchedalmond",
+    "blue",
+    "blueviolet",
+    "brown",
+    "burlywood",
+    "cadetblue",
+    "chartreuse",
+    "chocolate",
+    "coral",
+    "cornflowerblue",
+    "cornsilk",
+    "crimson",
+    "cyan",
+    "darkblue",
+    "darkcyan",
+    "darkgoldenrod",
+    "darkgray",
+    "darkgreen",
+    "darkgrey",
+    "darkkhaki",
+    "darkmagenta",
+    "darkolivegreen",
+    "darkorange",
+    "darkorchid",
+    "darkred",
+    "darksalmon",
+    "darkseagreen",
+    "darkslateblue",
+    "darkslategray",
+    "darkslategrey",
+    "darkturquoise",
+    "darkviolet",
+    "deeppink",
+    "deepskyblue",
+    "dimgray",
+    "dimgrey",
+    "dodgerblue",
+    "firebrick",
+    "floralwhite",
+    "forestgreen",
+    "fuchsia",
+    "gainsboro",
+    "ghostwhite",
+    "gold",
+    "goldenrod",
+    "gray",
+    "green",
+    "greenyellow",
+    "grey",
+    "honeydew",
+    "hotpink",
+    "indianred",
+    "indigo",
+    "ivory",
+    "khaki",
+    "lavender",
+    "lavenderblush",
+    "lawngreen",
+    "lemonchiffon",
+    "lightblue",
+    "lightcoral",
+    "lightcyan",
+    "lightgoldenrodyellow",
+    "lightgray",
+    "lightgreen",
+    "lightgrey",
+    "lightpink",
+    "lightsalmon",
+    "lightseagreen",
+    "lightskyblue",
+    "lightslategray",
+    "lightslategrey",
+    "lightsteelblue",
+    "lightyellow",
+    "lime",
+    "limegreen",
+    "linen",
+    "magenta",
+    "maroon",
+    "mediumaquamarine",
+    "mediumblue",
+    "mediumorchid",
+    "mediumpurple",
+    "mediumseagreen",
+    "mediumslateblue",
+    "mediumspringgreen",
+    "mediumturquoise",
+    "mediumvioletred",
+    "midnightblue",
+    "mintcream",
+    "mistyrose",
+    "moccasin",
+    "navajowhite",
+    "navy",
+    "oldlace",
+    "olive",
+    "olivedrab",
+    "orange",
+    "orangered",
+    "orchid",
+    "palegoldenrod",
+    "palegreen",
+    "paleturquoise",
+    "palevioletred",
+    "papayawhip",
+    "peachpuff",
+    "peru",
+    "pink",
+    "plum",
+    "powderblue",
+    "purple",
+    "rebeccapurple",
+    "red",
+    "rosybrown",
+    "royalblue",
+    "saddlebrown",
+    "salmon",
+    "sandybrown",
+    "seagreen",
+    "seashell",
+    "sienna",
+    "silver",
+    "skyblue",
+    "slateblue",
+    "slategray",
+    "slategrey",
+    "snow",
+    "springgreen",
+    "steelblue",
+    "tan",
+    "teal",
+    "thistle",
+    "tomato",
+    "transparent",
+    "turquoise",
+    "violet",
+    "wheat",
+    "white",
+    "whitesmoke",
+    "yellow",
+    "yellowgreen",
+];
+
+/// What is wrong with one field of the input of a mutation.
+#[derive(Clone, Debug)]
+pub(super) struct InvalidField {
+    /// The argument and the fields leading to the value, such as `newProcess.dueDate`.
+    pub(super) path: String,
+    pub(super) message: String,
+}
+
+/// Collects what is wrong with an argument of a mutation, field by field, so that all of it
+/// is reported at once.
+pub(super) struct Validator<'a> {
+    connection: &'a mut SqliteConnection,
+    argument: &'a str,
+    invalid_fields: Vec<InvalidField>,
+}
+
+impl<'a> Validator<'a> {
+    pub(super) fn invalid(&mut self, field: &str, message: impl Display) {
+        self.invalid_fields.push(InvalidField {
+            path: format!("{}.{}", self.argument, field),
+            message: message.to_string(),
+        });
+    }
+
+    pub(super) fn email(&mut self, field: &str, email: Option<&str>) {
+        let valid = match email.map(|email| email.split('@').collect::<Vec<_>>()) {
+            Some(parts) => {
+                parts.len() == 2
+                    && !parts[0].is_empty()
+                    && parts[1].split('.').count() > 1
+                    && parts[1].split('.').all(|label| !label.is_empty())
+                    && !parts.concat().contains(char::is_whitespace)
+            }
+            None => true,
+        };
+        if !valid {
+            self.invalid(field, "must be an email address");
+        }
+    }
+
+    /// A hex color, such as `#1e90ff` or `#fff`, or a CSS color keyword.
+    pub(super) fn color(&mut self, field: &str, color: &str) {
+        let valid = match color.strip_prefix('#') {
+            Some(hex) => {
+                [3, 4, 6, 8].contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit())
+            }
+            None => COLOR_NAMES.contains(&color.to_lowercase().as_str()),
+        };
+        if !valid {
+            self.invalid(field, "must be a hex color or a CSS color name");
+        }
+    }
+
+    pub(super) fn positive(&mut self, field: &str, quantity: Option<Decimal>) {
+        if let Some(false) = quantity.map(|quantity| quantity.is_positive()) {
+            self.invalid(field, "must be positive");
+        }
+    }
+
+    pub(super) fn not_negative(&mut self, field: &str, quantity: Option<Decimal>) {
+        if let Some(true) = quantity.map(|quantity| quantity < Decimal::ZERO) {
+            self.invalid(field, "must not be negative");
+        }
+    }
+
+    /// Checks that a period doesn't end before it starts.
+    pub(super) fn period(
+        &mut self,
+        (start_field, start): (&str, Option<DateTime>),
+        (end_field, end): (&str, Option<DateTime>),
+    ) {
+        if let (Some(start), Some(end)) = (start, end) {
+            if end < start {
+                self.invalid(end_field, format!("must not be before {}", start_field));
+            }
+        }
+    }
+
+    /// Checks that the record with `id` is in `table`.
+    pub(super) async fn exists(
+        &mut self,
+        field: &str,
+        table: &str,
+        id: Option<&str>,
+    ) -> Result<()> {
+        let id = match id {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+        let deleted_condition = if SOFT_DELETED_TABLES.contains(&table) {
+            "AND deleted_at IS NULL"
+        } else {
+            ""
+        };
+        let exists: bool = sqlx::query_scalar(&format!(
+            "SELECT EXISTS (SELECT 1 FROM {} WHERE id = ? {})",
+            table, deleted_condition
+        ))
+        .bind(id)
+        .fetch_one(&mut *self.connection)
+        .await?;
+        if !exists {
+            self.invalid(field, format!("{} does not exist", id));
+        }
+        Ok(())
+    }
+
+    /// Checks every id of a list, reporting the missing ones by their index.
+    pub(super) async fn all_exist(
+        &mut self,
+        field: &str,
+        table: &str,
+        ids: &[String],
+    ) -> Result<()> {
+        for (index, id) in ids.iter().enumerate() {
+            self.exists(&format!("{}.{}", field, index), table, Some(id))
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Input objects checking their fields before a mutation changes anything.
+#[async_trait]
+pub(super) trait Validate {
+    async fn validate(&self, validator: &mut Validator<'_>) -> Result<()>;
+}
+
+/// Checks `input`, the value of `argument`, and fails with every invalid field.
+pub(super) async fn validate<T: Validate + Sync>(
+    pool: &SqlitePool,
+    argument: &str,
+    input: &T,
+) -> Result<()> {
+    validate_in(&mut *pool.acquire().await?, argument, input).await
+}
+
+/// Like `validate`, on a connection that may be in a transaction, so that records the
+/// transaction inserted already count as existing.
+pub(super) async fn validate_in<T: Validate + Sync>(
+    connection: &mut SqliteConnection,
+    argument: &str,
+    input: &T,
+) -> Result<()> {
+    let mut validator = Validator {
+        connection,
+        argument,
+        invalid_fields: Vec::new(),
+    };
+    input.validate(&mut validator).await?;
+    if validator.invalid_fields.is_empty() {
+        Ok(())
+    } else {
+        Err(DomainError::InvalidFields(validator.invalid_fields))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_pool;
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    /// Ann is a member and Bob left.
+    const AGENTS: &str = "
+        INSERT INTO agents (id, unique_name, name, agent_type) VALUES ('ann', 'ann', 'Ann', 'Individual');
+        INSERT INTO agents (id, unique_name, name, agent_type, deleted_at)
+        VALUES ('bob', 'bob', 'Bob', 'Individual', '2022-05-01 00:00:00');
+    ";
+
+    fn at(hour: u32) -> Option<DateTime> {
+        Some(Utc.ymd(2022, 5, 2).and_hms(hour, 0, 0).into())
+    }
+
+    /// A shift some agents work on, with every kind of check.
+    struct NewShift {
+        email: Option<&'static str>,
+        color: &'static str,
+        hours: Option<Decimal>,
+        break_hours: Option<Decimal>,
+        start_at: Option<DateTime>,
+        end_at: Option<DateTime>,
+        agent_ids: Vec<String>,
+    }
+
+    impl Default for NewShift {
+        fn default() -> Self {
+            NewShift {
+                email: Some("ann@example.org"),
+                color: "#1e90ff",
+                hours: Some(Decimal::from(8)),
+                break_hours: Some(Decimal::ZERO),
+                start_at: at(9),
+                end_at: at(17),
+                agent_ids: vec!["ann".to_string()],
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Validate for NewShift {
+        async fn validate(&self, validator: &mut Validator<'_>) -> Result<()> {
+            validator.email("email", self.email);
+            validator.color("color", self.color);
+            validator.positive("hours", self.hours);
+            validator.not_negative("breakHours", self.break_hours);
+            validator.period(("startAt", self.start_at), ("endAt", self.end_at));
+            validator
+                .all_exist("agentIds", "agents", &self.agent_ids)
+                .await
+        }
+    }
+
+    /// The paths of the fields `validate` rejects.
+    async fn invalid_paths(shift: NewShift) -> Vec<String> {
+        match validate(&test_pool(AGENTS).await, "newShift", &shift).await {
+            Ok(()) => Vec::new(),
+            Err(DomainError::InvalidFields(fields)) => {
+                fields.into_iter().map(|field| field.path).collect()
+            }
+            Err(error) => panic!("unexpected error {:?}", error),
+        }
+    }
+
+    #[tokio::test]
+    async fn valid_input_passes() {
+        assert!(invalid_paths(NewShift::default()).await.is_empty());
+        let shift = NewShift {
+            email: None,
+            color: "RebeccaPurple",
+            hours: None,
+            break_hours: None,
+            end_at: None,
+            ..Default::default()
+        };
+        assert!(invalid_paths(shift).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn every_invalid_field_is_reported() {
+        let shift = NewShift {
+            email: Some("ann@example"),
+            color: "#12345",
+            hours: Some(Decimal::ZERO),
+            break_hours: Some(Decimal::from(-1)),
+            end_at: at(8),
+            ..Default::default()
+        };
+        assert_eq!(
+            invalid_paths(shift).await,
+            [
+                "newShift.email",
+                "newShift.color",
+                "newShift.hours",
+                "newShift.breakHours",
+                "newShift.endAt",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn emails_need_a_local_part_and_a_domain() {
+        for email in [
+            "ann",
+            "@example.org",
+            "ann@example.",
+            "ann@.org",
+            "a nn@example.org",
+        ] {
+            let shift = NewShift {
+                email: Some(email),
+                ..Default::default()
+            };
+            assert_eq!(invalid_paths(shift).await, ["newShift.email"], "{}", email);
+        }
+    }
+
+    #[tokio::test]
+    async fn colors_are_hex_or_css_names() {
+        for color in ["#fff", "#ffff", "#1E90FF", "#1e90ff80", "transparent"] {
+            let shift = NewShift {
+                color,
+                ..Default::default()
+            };
+            assert!(invalid_paths(shift).await.is_empty(), "{}", color);
+        }
+        for color in ["fff", "#ff", "#gggggg", "dodger blue"] {
+            let shift = NewShift {
+                color,
+                ..Default::default()
+            };
+            assert_eq!(invalid_paths(shift).await, ["newShift.color"], "{}", color);
+        }
+    }
+
+    #[tokio::test]
+    async fn missing_and_deleted_records_are_reported_by_index() {
+        let shift = NewShift {
+            agent_ids: vec!["ann".to_string(), "bob".to_string(), "cid".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            invalid_paths(shift).await,
+            ["newShift.agentIds.1", "newShift.agentIds.2"]
+        );
+    }
+}